@@ -111,6 +111,7 @@ fn main() {
     let text_alphabet_txt = dir.join("alphabet_94.txt");
 
     tonic_build::compile_protos("proto/input.proto").unwrap();
+    tonic_build::compile_protos("proto/frame.proto").unwrap();
     println!(
         "cargo:rustc-env=ESC_SETTING_TEMPLATE={}",
         esc_setting.to_str().unwrap()
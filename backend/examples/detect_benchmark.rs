@@ -0,0 +1,31 @@
+//! Benchmarks detections over a labelled image corpus and reports accuracy and latency.
+//!
+//! Usage: `cargo run -p backend --example detect_benchmark -- <corpus_dir> [min_accuracy]`
+//!
+//! Exits with an error when the lowest accuracy among all detections is below `min_accuracy`.
+
+use std::env;
+
+use anyhow::{Result, bail};
+use backend::benchmark_detection;
+
+fn main() -> Result<()> {
+    let mut args = env::args().skip(1);
+    let Some(corpus_dir) = args.next() else {
+        bail!("usage: detect_benchmark <corpus_dir> [min_accuracy]");
+    };
+    let min_accuracy = args.next().map(|arg| arg.parse::<f32>()).transpose()?;
+
+    let report = benchmark_detection(corpus_dir)?;
+    print!("{report}");
+    if let Some(min_accuracy) = min_accuracy
+        && report.min_accuracy() < min_accuracy
+    {
+        bail!(
+            "accuracy {:.3} is below {min_accuracy:.3}",
+            report.min_accuracy()
+        );
+    }
+
+    Ok(())
+}
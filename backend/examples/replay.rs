@@ -0,0 +1,24 @@
+//! Replays recorded frames through the update loop and writes a per-tick trace.
+//!
+//! Usage: `cargo run -p backend --example replay -- <frames_dir> <trace_path>`
+
+use std::env;
+
+use anyhow::{Result, bail};
+use backend::{ReplayOptions, replay};
+
+fn main() -> Result<()> {
+    let mut args = env::args().skip(1);
+    let (Some(frames_dir), Some(trace_path)) = (args.next(), args.next()) else {
+        bail!("usage: replay <frames_dir> <trace_path>");
+    };
+
+    let ticks = replay(ReplayOptions {
+        frames_dir: frames_dir.into(),
+        trace_path: trace_path.into(),
+        ..ReplayOptions::default()
+    })?;
+    println!("replayed {ticks} ticks");
+
+    Ok(())
+}
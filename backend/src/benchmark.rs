@@ -0,0 +1,684 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::{self, Display},
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Result, bail};
+use opencv::{
+    core::{Mat, MatTraitConst, Point, Rect},
+    imgcodecs::{IMREAD_COLOR, IMREAD_GRAYSCALE, imread},
+};
+use serde::Deserialize;
+use strum::IntoEnumIterator;
+
+use crate::{
+    buff::BuffKind,
+    detect::{ArrowsCalibrating, ArrowsState, CachedDetector, Detector, OtherPlayerKind},
+    mat::OwnedMat,
+    minimap::MINIMAP_BORDER_WHITENESS_THRESHOLD,
+    replay::frame_from,
+};
+
+/// The minimum intersection over union for a detected box to match an expected box.
+const MIN_BOX_IOU: f32 = 0.5;
+
+/// The maximum distance for a detected point to match an expected point.
+const MAX_POINT_DISTANCE: f32 = 5.0;
+
+/// The number of times rune arrows are detected on the same image while still calibrating.
+const MAX_RUNE_ARROWS_ATTEMPTS: u32 = 3;
+
+/// Expected detection results of a corpus image.
+///
+/// Stored next to the image with the same file stem as `<stem>.json`. Only the detections listed
+/// are benchmarked for that image.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Labels {
+    /// Expected `[x, y, width, height]` boxes keyed by detection name.
+    ///
+    /// An empty list expects nothing to be detected. Boxes of detections inside the minimap are
+    /// relative to the minimap.
+    boxes: HashMap<String, Vec<[i32; 4]>>,
+    /// Expected `[x, y]` points keyed by detection name.
+    ///
+    /// Points of detections inside the minimap are relative to the minimap.
+    points: HashMap<String, Vec<[i32; 2]>>,
+    /// Expected flags keyed by detection name.
+    flags: HashMap<String, bool>,
+    /// Expected textual values keyed by detection name.
+    values: HashMap<String, String>,
+    /// Expected results of matching the minimap against snapshots.
+    minimap_matches: Vec<MinimapMatchLabel>,
+}
+
+/// Expected result of matching the minimap against a minimap and minimap name snapshot.
+#[derive(Debug, Deserialize)]
+struct MinimapMatchLabel {
+    /// Path to the minimap snapshot relative to the corpus directory.
+    minimap_snapshot: String,
+    /// Whether the minimap snapshot is grayscale.
+    #[serde(default)]
+    minimap_snapshot_grayscale: bool,
+    /// Path to the minimap name snapshot relative to the corpus directory.
+    name_snapshot: String,
+    matched: bool,
+}
+
+/// Detection results of an image used as inputs to other detections.
+///
+/// Each is taken from the labels when available so that a failure of one detection does not fail
+/// all of the detections depending on it.
+#[derive(Debug, Default)]
+struct Anchors {
+    minimap: Option<Rect>,
+    minimap_name: Option<Rect>,
+    /// The player inside the minimap in bottom-left coordinate as used by player states.
+    player: Option<Point>,
+    health_bar: Option<Rect>,
+    mana_bar: Option<Rect>,
+}
+
+/// Accuracy and latency of a single detection across the corpus.
+#[derive(Debug, Default, Clone)]
+pub struct DetectionStats {
+    pub samples: u32,
+    pub correct: u32,
+    pub total_latency: Duration,
+    pub max_latency: Duration,
+    /// File names of the images with mismatched results.
+    pub failures: Vec<String>,
+}
+
+impl DetectionStats {
+    #[inline]
+    pub fn accuracy(&self) -> f32 {
+        if self.samples == 0 {
+            return 0.0;
+        }
+        self.correct as f32 / self.samples as f32
+    }
+
+    #[inline]
+    pub fn mean_latency(&self) -> Duration {
+        if self.samples == 0 {
+            return Duration::ZERO;
+        }
+        self.total_latency / self.samples
+    }
+}
+
+/// The result of benchmarking detections over a labelled corpus.
+#[derive(Debug, Default)]
+pub struct DetectionReport {
+    pub images: u32,
+    pub stats: BTreeMap<String, DetectionStats>,
+    /// Detection names found in labels that are not known to the benchmark.
+    pub unknown_labels: BTreeSet<String>,
+}
+
+impl DetectionReport {
+    /// The lowest accuracy among all benchmarked detections.
+    pub fn min_accuracy(&self) -> f32 {
+        self.stats
+            .values()
+            .map(DetectionStats::accuracy)
+            .reduce(f32::min)
+            .unwrap_or(1.0)
+    }
+
+    fn record(&mut self, name: &str, image: &str, latency: Duration, correct: bool) {
+        let stats = self.stats.entry(name.to_string()).or_default();
+        stats.samples += 1;
+        stats.total_latency += latency;
+        stats.max_latency = stats.max_latency.max(latency);
+        if correct {
+            stats.correct += 1;
+        } else {
+            stats.failures.push(image.to_string());
+        }
+    }
+}
+
+impl Display for DetectionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} images", self.images)?;
+        writeln!(
+            f,
+            "{:<40} {:>8} {:>9} {:>10} {:>10}",
+            "detection", "samples", "accuracy", "mean (ms)", "max (ms)"
+        )?;
+        for (name, stats) in &self.stats {
+            writeln!(
+                f,
+                "{:<40} {:>8} {:>8.1}% {:>10.2} {:>10.2}",
+                name,
+                stats.samples,
+                stats.accuracy() * 100.0,
+                stats.mean_latency().as_secs_f64() * 1000.0,
+                stats.max_latency.as_secs_f64() * 1000.0,
+            )?;
+            if !stats.failures.is_empty() {
+                writeln!(f, "    failed: {}", stats.failures.join(", "))?;
+            }
+        }
+        for name in &self.unknown_labels {
+            writeln!(f, "unknown label: {name}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Runs [`Detector`] methods over the labelled images in `corpus_dir`.
+///
+/// Each image in `corpus_dir` with a `<stem>.json` labels file is benchmarked. Images without
+/// labels are skipped. Icons and snapshots referenced by labels are resolved relative to
+/// `corpus_dir`.
+///
+/// Every detection of [`Detector`] is covered except:
+/// - [`Detector::mat`] and [`Detector::grayscale_mat`] as they only expose the frame
+/// - Spinning rune arrows as their calibration requires consecutive frames while each corpus image
+///   is a single frame
+pub fn benchmark_detection(corpus_dir: impl AsRef<Path>) -> Result<DetectionReport> {
+    let corpus_dir = corpus_dir.as_ref();
+    let mut images = fs::read_dir(corpus_dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension != "json")
+        })
+        .collect::<Vec<_>>();
+    images.sort();
+
+    let mut report = DetectionReport::default();
+    for image in images {
+        let labels_path = image.with_extension("json");
+        if !labels_path.exists() {
+            continue;
+        }
+        let labels = serde_json::from_str::<Labels>(&fs::read_to_string(&labels_path)?)?;
+        let Some(frame) = frame_from(&image) else {
+            bail!("failed to read image {}", image.display());
+        };
+        let name = image
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        let detector = CachedDetector::new(OwnedMat::new_from_frame(frame)?);
+
+        benchmark_image(&detector, &labels, corpus_dir, name, &mut report)?;
+        report.images += 1;
+    }
+
+    Ok(report)
+}
+
+fn benchmark_image(
+    detector: &dyn Detector,
+    labels: &Labels,
+    corpus_dir: &Path,
+    image: &str,
+    report: &mut DetectionReport,
+) -> Result<()> {
+    let anchors = anchors_from(detector, labels);
+    // Icons are loaded up front so that reading them is not counted as detection latency
+    let icons = labels
+        .boxes
+        .keys()
+        .filter_map(|name| name.strip_prefix("skill:"))
+        .chain(
+            labels
+                .flags
+                .keys()
+                .filter_map(|name| name.strip_prefix("player_custom_buff:")),
+        )
+        .map(|path| Ok((path, read_image(corpus_dir, path, true)?)))
+        .collect::<Result<HashMap<_, _>>>()?;
+
+    for (name, expected) in &labels.boxes {
+        let expected = expected.iter().map(rect_from).collect::<Vec<_>>();
+        let instant = Instant::now();
+        let Some(actual) = detect_boxes(detector, name, &anchors, &icons) else {
+            report.unknown_labels.insert(name.clone());
+            continue;
+        };
+        let latency = instant.elapsed();
+
+        report.record(name, image, latency, boxes_match(&expected, &actual));
+    }
+
+    for (name, expected) in &labels.points {
+        let expected = expected
+            .iter()
+            .map(|[x, y]| Point::new(*x, *y))
+            .collect::<Vec<_>>();
+        let instant = Instant::now();
+        let Some(actual) = detect_points(detector, name, &anchors) else {
+            report.unknown_labels.insert(name.clone());
+            continue;
+        };
+        let latency = instant.elapsed();
+
+        report.record(name, image, latency, points_match(&expected, &actual));
+    }
+
+    for (name, expected) in &labels.flags {
+        let instant = Instant::now();
+        let Some(actual) = detect_flag(detector, name, &anchors, &icons) else {
+            report.unknown_labels.insert(name.clone());
+            continue;
+        };
+        let latency = instant.elapsed();
+
+        report.record(name, image, latency, *expected == actual);
+    }
+
+    for (name, expected) in &labels.values {
+        let instant = Instant::now();
+        let Some(actual) = detect_value(detector, name, &anchors) else {
+            report.unknown_labels.insert(name.clone());
+            continue;
+        };
+        let latency = instant.elapsed();
+
+        report.record(name, image, latency, *expected == actual);
+    }
+
+    for label in &labels.minimap_matches {
+        let minimap_snapshot = read_image(
+            corpus_dir,
+            &label.minimap_snapshot,
+            label.minimap_snapshot_grayscale,
+        )?;
+        let name_snapshot = read_image(corpus_dir, &label.name_snapshot, true)?;
+        let instant = Instant::now();
+        let matched =
+            anchors
+                .minimap
+                .zip(anchors.minimap_name)
+                .is_some_and(|(minimap, minimap_name)| {
+                    detector
+                        .detect_minimap_match(
+                            &minimap_snapshot,
+                            label.minimap_snapshot_grayscale,
+                            &name_snapshot,
+                            minimap,
+                            minimap_name,
+                        )
+                        .is_ok()
+                });
+        let latency = instant.elapsed();
+
+        report.record("minimap_match", image, latency, label.matched == matched);
+    }
+
+    Ok(())
+}
+
+fn anchors_from(detector: &dyn Detector, labels: &Labels) -> Anchors {
+    let labelled = |name: &str| {
+        labels
+            .boxes
+            .get(name)
+            .and_then(|boxes| boxes.first())
+            .map(rect_from)
+    };
+    let minimap = labelled("minimap").or_else(|| {
+        detector
+            .detect_minimap(MINIMAP_BORDER_WHITENESS_THRESHOLD)
+            .ok()
+    });
+    let minimap_name = labelled("minimap_name")
+        .or_else(|| minimap.and_then(|minimap| detector.detect_minimap_name(minimap).ok()));
+    let player = minimap.and_then(|minimap| {
+        let bbox = labelled("player").or_else(|| detector.detect_player(minimap).ok())?;
+        Some(Point::new(
+            bbox.x + bbox.width / 2,
+            minimap.height - bbox.br().y,
+        ))
+    });
+    let health_bar =
+        labelled("player_health_bar").or_else(|| detector.detect_player_health_bar().ok());
+    let mana_bar = labelled("player_mana_bar").or_else(|| {
+        health_bar.and_then(|health_bar| detector.detect_player_mana_bar(health_bar).ok())
+    });
+
+    Anchors {
+        minimap,
+        minimap_name,
+        player,
+        health_bar,
+        mana_bar,
+    }
+}
+
+fn detect_boxes(
+    detector: &dyn Detector,
+    name: &str,
+    anchors: &Anchors,
+    icons: &HashMap<&str, Mat>,
+) -> Option<Vec<Rect>> {
+    let minimap = anchors.minimap;
+    let boxes = match name {
+        "minimap" => detector
+            .detect_minimap(MINIMAP_BORDER_WHITENESS_THRESHOLD)
+            .into_iter()
+            .collect(),
+        "minimap_name" => minimap
+            .and_then(|minimap| detector.detect_minimap_name(minimap).ok())
+            .into_iter()
+            .collect(),
+        "minimap_portals" => minimap
+            .map(|minimap| detector.detect_minimap_portals(minimap))
+            .unwrap_or_default(),
+        "minimap_rune" => minimap
+            .and_then(|minimap| detector.detect_minimap_rune(minimap).ok())
+            .into_iter()
+            .collect(),
+        "player" => minimap
+            .and_then(|minimap| detector.detect_player(minimap).ok())
+            .into_iter()
+            .collect(),
+        "player_on_screen" => detector.detect_player_on_screen().into_iter().collect(),
+        "esc_confirm_button" => detector.detect_esc_confirm_button().into_iter().collect(),
+        "tomb_ok_button" => detector.detect_tomb_ok_button().into_iter().collect(),
+        "player_health_bar" => detector.detect_player_health_bar().into_iter().collect(),
+        "player_current_max_health_bars" => anchors
+            .health_bar
+            .and_then(|bar| detector.detect_player_current_max_health_bars(bar).ok())
+            .map(|(current, max)| vec![current, max])
+            .unwrap_or_default(),
+        "player_mana_bar" => anchors
+            .health_bar
+            .and_then(|bar| detector.detect_player_mana_bar(bar).ok())
+            .into_iter()
+            .collect(),
+        "player_current_max_mana_bars" => anchors
+            .mana_bar
+            .and_then(|bar| detector.detect_player_current_max_mana_bars(bar).ok())
+            .map(|(current, max)| vec![current, max])
+            .unwrap_or_default(),
+        "erda_shower" => detector.detect_erda_shower().into_iter().collect(),
+        "familiar_save_button" => detector.detect_familiar_save_button().into_iter().collect(),
+        "familiar_setup_button" => detector
+            .detect_familiar_setup_button()
+            .into_iter()
+            .collect(),
+        "familiar_level_button" => detector
+            .detect_familiar_level_button()
+            .into_iter()
+            .collect(),
+        "familiar_scrollbar" => detector.detect_familiar_scrollbar().into_iter().collect(),
+        "familiar_slots" => detector
+            .detect_familiar_slots()
+            .into_iter()
+            .map(|(bbox, _)| bbox)
+            .collect(),
+        "familiar_cards" => detector
+            .detect_familiar_cards()
+            .into_iter()
+            .map(|(bbox, _)| bbox)
+            .collect(),
+        _ => {
+            let icon = icons.get(name.strip_prefix("skill:")?)?;
+            detector.detect_skill(icon).into_iter().collect()
+        }
+    };
+
+    Some(boxes)
+}
+
+fn detect_points(detector: &dyn Detector, name: &str, anchors: &Anchors) -> Option<Vec<Point>> {
+    let points = match name {
+        // Mobs are detected within the whole minimap the same as auto-mobbing while pathing
+        "mobs" => anchors
+            .minimap
+            .zip(anchors.player)
+            .and_then(|(minimap, player)| {
+                let bound = Rect::new(0, 0, minimap.width, minimap.height);
+                detector.detect_mobs(minimap, bound, player).ok()
+            })
+            .unwrap_or_default(),
+        _ => return None,
+    };
+
+    Some(points)
+}
+
+fn detect_flag(
+    detector: &dyn Detector,
+    name: &str,
+    anchors: &Anchors,
+    icons: &HashMap<&str, Mat>,
+) -> Option<bool> {
+    let flag = match name {
+        "esc_settings" => detector.detect_esc_settings(),
+        "elite_boss_bar" => detector.detect_elite_boss_bar(),
+        "player_is_dead" => detector.detect_player_is_dead(),
+        "player_in_cash_shop" => detector.detect_player_in_cash_shop(),
+        "familiar_menu_opened" => detector.detect_familiar_menu_opened(),
+        "familiar_essence_depleted" => detector.detect_familiar_essence_depleted(),
+        "change_channel_menu_opened" => detector.detect_change_channel_menu_opened(),
+        "chat_menu_opened" => detector.detect_chat_menu_opened(),
+        _ => {
+            if let Some(kind) = name.strip_prefix("player_buff:") {
+                let kind = BuffKind::iter().find(|buff| format!("{buff:?}") == kind)?;
+                detector.detect_player_buff(kind)
+            } else if let Some(path) = name.strip_prefix("player_custom_buff:") {
+                detector.detect_player_custom_buff(icons.get(path)?)
+            } else if let Some(kind) = name.strip_prefix("player_kind:") {
+                let kind = match kind {
+                    "Guildie" => OtherPlayerKind::Guildie,
+                    "Stranger" => OtherPlayerKind::Stranger,
+                    "Friend" => OtherPlayerKind::Friend,
+                    _ => return None,
+                };
+                anchors
+                    .minimap
+                    .is_some_and(|minimap| detector.detect_player_kind(minimap, kind))
+            } else {
+                return None;
+            }
+        }
+    };
+
+    Some(flag)
+}
+
+/// Detects a value formatted as text for comparing against the labels.
+///
+/// A failed detection is formatted as an empty string.
+fn detect_value(detector: &dyn Detector, name: &str, anchors: &Anchors) -> Option<String> {
+    let value = match name {
+        "minimap_name_text" => anchors
+            .minimap
+            .and_then(|minimap| detector.detect_minimap_name_text(minimap).ok()),
+        "player_health" => anchors
+            .health_bar
+            .and_then(|bar| detector.detect_player_current_max_health_bars(bar).ok())
+            .and_then(|(current, max)| detector.detect_player_health(current, max).ok())
+            .map(|(current, max)| format!("{current}/{max}")),
+        "player_mana" => anchors
+            .mana_bar
+            .and_then(|bar| detector.detect_player_current_max_mana_bars(bar).ok())
+            .and_then(|(current, max)| detector.detect_player_mana(current, max).ok())
+            .map(|(current, max)| format!("{current}/{max}")),
+        "player_exp" => detector
+            .detect_player_exp()
+            .ok()
+            .map(|exp| format!("{exp:.2}")),
+        "rune_arrows" => detect_rune_arrows(detector),
+        "template_scale" => detector
+            .detect_template_scale()
+            .ok()
+            .map(|scale| format!("{scale:.2}")),
+        "familiar_slots_free" => Some(
+            detector
+                .detect_familiar_slots()
+                .into_iter()
+                .map(|(bbox, _)| detector.detect_familiar_slot_is_free(bbox).to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+        ),
+        "familiar_cards_rank" => Some(
+            detector
+                .detect_familiar_cards()
+                .into_iter()
+                .map(|(_, rank)| format!("{rank:?}"))
+                .collect::<Vec<_>>()
+                .join(","),
+        ),
+        "familiar_hover_level" => detector
+            .detect_familiar_hover_level()
+            .ok()
+            .map(|level| format!("{level:?}")),
+        _ => return None,
+    };
+
+    Some(value.unwrap_or_default())
+}
+
+fn detect_rune_arrows(detector: &dyn Detector) -> Option<String> {
+    let mut calibrating = ArrowsCalibrating::default();
+    for _ in 0..MAX_RUNE_ARROWS_ATTEMPTS {
+        match detector.detect_rune_arrows(calibrating).ok()? {
+            ArrowsState::Calibrating(new_calibrating) => calibrating = new_calibrating,
+            ArrowsState::Complete(arrows) => {
+                return Some(
+                    arrows
+                        .into_iter()
+                        .map(|(_, key)| format!("{key:?}"))
+                        .collect::<Vec<_>>()
+                        .join(","),
+                );
+            }
+        }
+    }
+
+    None
+}
+
+fn read_image(corpus_dir: &Path, path: &str, grayscale: bool) -> Result<Mat> {
+    let path = corpus_dir.join(path);
+    let flag = if grayscale {
+        IMREAD_GRAYSCALE
+    } else {
+        IMREAD_COLOR
+    };
+    let Some(mat) = path
+        .to_str()
+        .and_then(|path| imread(path, flag).ok())
+        .filter(|mat| !mat.empty())
+    else {
+        bail!("failed to read image {}", path.display());
+    };
+
+    Ok(mat)
+}
+
+#[inline]
+fn rect_from([x, y, width, height]: &[i32; 4]) -> Rect {
+    Rect::new(*x, *y, *width, *height)
+}
+
+/// Whether each of `expected` matches a distinct box in `actual` and there is no extra box.
+fn boxes_match(expected: &[Rect], actual: &[Rect]) -> bool {
+    if expected.len() != actual.len() {
+        return false;
+    }
+
+    let mut matched = vec![false; actual.len()];
+    expected.iter().all(|expected| {
+        let index = actual.iter().enumerate().position(|(index, actual)| {
+            !matched[index] && intersection_over_union(*expected, *actual) >= MIN_BOX_IOU
+        });
+        if let Some(index) = index {
+            matched[index] = true;
+        }
+        index.is_some()
+    })
+}
+
+/// Whether each of `expected` is close to a distinct point in `actual` and there is no extra point.
+fn points_match(expected: &[Point], actual: &[Point]) -> bool {
+    if expected.len() != actual.len() {
+        return false;
+    }
+
+    let mut matched = vec![false; actual.len()];
+    expected.iter().all(|expected| {
+        let index = actual.iter().enumerate().position(|(index, actual)| {
+            !matched[index] && (*expected - *actual).norm() as f32 <= MAX_POINT_DISTANCE
+        });
+        if let Some(index) = index {
+            matched[index] = true;
+        }
+        index.is_some()
+    })
+}
+
+fn intersection_over_union(a: Rect, b: Rect) -> f32 {
+    let intersection = (a & b).area();
+    let union = a.area() + b.area() - intersection;
+    if union <= 0 {
+        return 0.0;
+    }
+    intersection as f32 / union as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boxes_match_with_iou() {
+        let expected = [Rect::new(0, 0, 10, 10), Rect::new(50, 50, 10, 10)];
+
+        assert!(boxes_match(
+            &expected,
+            &[Rect::new(51, 51, 10, 10), Rect::new(1, 0, 10, 10)]
+        ));
+        assert!(!boxes_match(
+            &expected,
+            &[Rect::new(0, 0, 10, 10), Rect::new(0, 1, 10, 10)]
+        ));
+        assert!(!boxes_match(&expected, &[Rect::new(0, 0, 10, 10)]));
+        assert!(!boxes_match(&[], &[Rect::new(0, 0, 10, 10)]));
+        assert!(boxes_match(&[], &[]));
+    }
+
+    #[test]
+    fn points_match_within_distance() {
+        let expected = [Point::new(10, 10), Point::new(50, 50)];
+
+        assert!(points_match(
+            &expected,
+            &[Point::new(52, 47), Point::new(10, 12)]
+        ));
+        assert!(!points_match(
+            &expected,
+            &[Point::new(10, 10), Point::new(60, 50)]
+        ));
+        assert!(!points_match(&expected, &[Point::new(10, 10)]));
+        assert!(points_match(&[], &[]));
+    }
+
+    #[test]
+    fn report_records_accuracy_and_latency() {
+        let mut report = DetectionReport::default();
+
+        report.record("minimap", "a.png", Duration::from_millis(2), true);
+        report.record("minimap", "b.png", Duration::from_millis(4), false);
+
+        let stats = report.stats.get("minimap").unwrap();
+        assert_eq!(stats.accuracy(), 0.5);
+        assert_eq!(stats.mean_latency(), Duration::from_millis(3));
+        assert_eq!(stats.max_latency, Duration::from_millis(4));
+        assert_eq!(stats.failures, vec!["b.png".to_string()]);
+        assert_eq!(report.min_accuracy(), 0.5);
+    }
+}
@@ -1,10 +1,11 @@
 use std::{
     cell::RefCell,
-    collections::{HashMap, hash_map::Entry},
+    collections::{HashMap, VecDeque, hash_map::Entry},
     fmt::Debug,
 };
 
 use anyhow::{Result, bail};
+use log::error;
 #[cfg(test)]
 use mockall::automock;
 #[cfg(windows)]
@@ -20,13 +21,15 @@ use platforms::{
 };
 
 use crate::{
-    CaptureMode, KeyBinding,
+    CaptureMode, CapturePlayback, KeyBinding,
     context::{MS_PER_TICK, MS_PER_TICK_F32},
     database::Seeds,
+    playback::PlaybackCapture,
     rng::Rng,
     rpc::{
-        Coordinate as RpcCoordinate, InputService, Key as RpcKeyKind, KeyState as RpcKeyState,
-        MouseAction as RpcMouseKind,
+        Coordinate as RpcCoordinate, FrameService, InputConnectionEvent, InputService,
+        Key as RpcKeyKind, KeyState as RpcKeyState, MouseAction as RpcMouseKind,
+        SequenceEvent as RpcSequenceEvent,
     },
 };
 
@@ -73,7 +76,7 @@ impl From<RpcKeyState> for KeyState {
 /// The kind of mouse movement/action to perform.
 ///
 /// This is a bridge enum between platform-specific and gRPC.
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum MouseKind {
     Move,
     Click,
@@ -541,6 +544,21 @@ enum InputMethodInner {
     Default(PlatformInput),
 }
 
+/// An input event to send with [`Input::send_sequence`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum InputEvent {
+    /// Presses a single key.
+    Key(KeyKind),
+    /// Releases a held key.
+    KeyUp(KeyKind),
+    /// Holds down a key.
+    KeyDown(KeyKind),
+    /// Sends mouse kind to `(x, y)` relative to the client coordinate.
+    Mouse(i32, i32, MouseKind),
+    /// Waits for the milliseconds before sending the next event.
+    Wait(u64),
+}
+
 /// States of input delay tracking.
 #[derive(Debug)]
 enum InputDelay {
@@ -553,7 +571,9 @@ enum InputDelay {
 #[cfg_attr(test, automock)]
 pub trait Input: Debug {
     /// Performs a tick update.
-    fn update(&mut self, tick: u64);
+    ///
+    /// Returns a change of connection to the input server if the input method is RPC.
+    fn update(&mut self, tick: u64) -> Option<InputConnectionEvent>;
 
     /// Overwrites the current input method with new `method`.
     fn set_method(&mut self, method: InputMethod);
@@ -572,6 +592,15 @@ pub trait Input: Debug {
     /// Holds down key `kind`.
     fn send_key_down(&self, kind: KeyKind) -> Result<()>;
 
+    /// Sends `events` in order as a whole sequence.
+    ///
+    /// The sequence is sent at once when the input method supports it. Otherwise, events after
+    /// [`InputEvent::Wait`] are sent later by [`Self::update`].
+    fn send_sequence(&self, events: &[InputEvent]) -> Result<()>;
+
+    /// Drops the events of sequences not yet sent by [`Self::update`].
+    fn clear_sequence(&self);
+
     /// Whether all keys are cleared.
     fn all_keys_cleared(&self) -> bool;
 }
@@ -583,6 +612,8 @@ pub struct DefaultInput {
     delay_rng: Rng,
     delay_mean_std_pair: (f32, f32),
     delay_map: RefCell<HashMap<KeyKind, (u32, bool)>>,
+    /// Remaining events of sequences that cannot be sent at once.
+    sequence: RefCell<VecDeque<InputEvent>>,
 }
 
 impl DefaultInput {
@@ -592,6 +623,7 @@ impl DefaultInput {
             delay_rng: Rng::new(seeds.seed),
             delay_mean_std_pair: (BASE_MEAN_MS_DELAY, BASE_STD_MS_DELAY),
             delay_map: RefCell::new(HashMap::new()),
+            sequence: RefCell::new(VecDeque::new()),
         }
     }
 
@@ -664,6 +696,105 @@ impl DefaultInput {
         Ok(())
     }
 
+    /// Sends `events` with `SendSequence` if the method is [`InputMethodInner::Rpc`].
+    ///
+    /// Returns `None` if the method is not [`InputMethodInner::Rpc`] or the server does not
+    /// support `SendSequence`.
+    fn send_rpc_sequence(&self, events: &[InputEvent]) -> Option<Result<()>> {
+        let InputMethodInner::Rpc(window, Some(cell)) = &self.kind else {
+            return None;
+        };
+        let mut service = cell.borrow_mut();
+        if !service.sequence_supported() {
+            return None;
+        }
+
+        let mut delay_ms = 0.0;
+        let mut sequence = Vec::with_capacity(events.len());
+        for event in events {
+            let event = match *event {
+                InputEvent::Key(kind) => {
+                    RpcSequenceEvent::Key(kind.into(), self.random_input_delay_tick_count().0)
+                }
+                InputEvent::KeyUp(kind) => RpcSequenceEvent::KeyUp(kind.into()),
+                InputEvent::KeyDown(kind) => RpcSequenceEvent::KeyDown(kind.into()),
+                InputEvent::Mouse(x, y, kind) => {
+                    let relative = match service.mouse_coordinate() {
+                        RpcCoordinate::Screen => CoordinateRelative::Monitor,
+                        RpcCoordinate::Relative => CoordinateRelative::Window,
+                    };
+                    let coordinates = match window.convert_coordinate(x, y, relative) {
+                        Ok(coordinates) => coordinates,
+                        Err(err) => return Some(Err(err.into())),
+                    };
+
+                    RpcSequenceEvent::Mouse {
+                        width: coordinates.width,
+                        height: coordinates.height,
+                        x: coordinates.x,
+                        y: coordinates.y,
+                        action: kind.into(),
+                    }
+                }
+                InputEvent::Wait(ms) => {
+                    delay_ms += ms as f32;
+                    continue;
+                }
+            };
+            sequence.push((delay_ms, event));
+            delay_ms = 0.0;
+        }
+
+        let result = service.send_sequence(sequence);
+        if result.is_err() && !service.sequence_supported() {
+            return None;
+        }
+        Some(result)
+    }
+
+    /// Checks the connection to the input server if the method is [`InputMethodInner::Rpc`].
+    ///
+    /// The queued sequence events are dropped on disconnect so they are not sent late once the
+    /// server is reconnected.
+    fn update_connection(&self) -> Option<InputConnectionEvent> {
+        let InputMethodInner::Rpc(_, Some(cell)) = &self.kind else {
+            return None;
+        };
+        let event = cell.borrow_mut().update_connection();
+        if matches!(event, Some(InputConnectionEvent::Disconnected)) {
+            self.sequence.borrow_mut().clear();
+        }
+
+        event
+    }
+
+    /// Sends the queued sequence events until a [`InputEvent::Wait`] that has not elapsed after
+    /// `elapsed_ms`.
+    fn update_sequence(&self, mut elapsed_ms: u64) {
+        let mut sequence = self.sequence.borrow_mut();
+        while let Some(event) = sequence.pop_front() {
+            match event {
+                InputEvent::Wait(ms) if ms > elapsed_ms => {
+                    sequence.push_front(InputEvent::Wait(ms - elapsed_ms));
+                    return;
+                }
+                InputEvent::Wait(ms) => elapsed_ms -= ms,
+                InputEvent::Key(kind) => {
+                    let _ = self.send_key_inner(kind);
+                }
+                InputEvent::KeyUp(kind) => {
+                    let _ = self.send_key_up_inner(kind, false);
+                }
+                InputEvent::KeyDown(kind) => {
+                    let _ = self.send_key_down_inner(kind);
+                }
+                InputEvent::Mouse(x, y, kind) => {
+                    let _ = self.send_mouse(x, y, kind);
+                }
+            }
+        }
+    }
+
     #[inline]
     fn has_input_delay(&self, kind: KeyKind) -> bool {
         self.delay_map.borrow().contains_key(&kind)
@@ -711,6 +842,8 @@ impl DefaultInput {
             )
         }
 
+        self.update_sequence(MS_PER_TICK);
+
         let mut map = self.delay_map.borrow_mut();
         if map.is_empty() {
             return;
@@ -740,11 +873,15 @@ impl DefaultInput {
 }
 
 impl Input for DefaultInput {
-    fn update(&mut self, tick: u64) {
+    fn update(&mut self, tick: u64) -> Option<InputConnectionEvent> {
+        let event = self.update_connection();
         self.update(tick);
+        event
     }
 
     fn set_method(&mut self, method: InputMethod) {
+        // Queued events were meant for the previous input method
+        self.sequence.get_mut().clear();
         self.kind = input_method_inner_from(method, self.delay_rng.seed());
     }
 
@@ -793,6 +930,20 @@ impl Input for DefaultInput {
         self.send_key_down_inner(kind)
     }
 
+    fn send_sequence(&self, events: &[InputEvent]) -> Result<()> {
+        if let Some(result) = self.send_rpc_sequence(events) {
+            return result;
+        }
+
+        self.sequence.borrow_mut().extend(events.iter().copied());
+        self.update_sequence(0);
+        Ok(())
+    }
+
+    fn clear_sequence(&self) {
+        self.sequence.borrow_mut().clear();
+    }
+
     #[inline]
     fn all_keys_cleared(&self) -> bool {
         self.delay_map.borrow().is_empty()
@@ -813,11 +964,19 @@ pub trait Capture: Debug + 'static {
     fn mode(&self) -> CaptureMode;
 
     fn set_mode(&mut self, mode: CaptureMode);
+
+    /// Sets the source to play back from when the mode is [`CaptureMode::Playback`].
+    fn set_playback(&mut self, playback: CapturePlayback);
+
+    /// Sets the frame source server to connect to when the mode is [`CaptureMode::Rpc`].
+    fn set_rpc_server_url(&mut self, url: String);
 }
 
 #[derive(Debug)]
 pub struct DefaultCapture {
     inner: PlatformCapture,
+    playback: Option<PlaybackCapture>,
+    rpc: Option<FrameService>,
     mode: CaptureMode,
 }
 
@@ -825,6 +984,8 @@ impl DefaultCapture {
     pub fn new(window: Window) -> Self {
         Self {
             inner: PlatformCapture::new(window).expect("supported platform"),
+            playback: None,
+            rpc: None,
             mode: CaptureMode::BitBlt,
         }
     }
@@ -833,7 +994,19 @@ impl DefaultCapture {
 impl Capture for DefaultCapture {
     #[inline]
     fn grab(&mut self) -> Result<Frame, Error> {
-        self.inner.grab()
+        match self.mode {
+            CaptureMode::Playback => self
+                .playback
+                .as_mut()
+                .ok_or(Error::WindowFrameNotAvailable)?
+                .grab(),
+            CaptureMode::Rpc => self
+                .rpc
+                .as_mut()
+                .ok_or(Error::WindowFrameNotAvailable)?
+                .grab(),
+            _ => self.inner.grab(),
+        }
     }
 
     #[inline]
@@ -855,15 +1028,34 @@ impl Capture for DefaultCapture {
     fn set_mode(&mut self, mode: CaptureMode) {
         self.mode = mode;
 
-        if cfg!(windows) {
+        #[cfg(windows)]
+        {
             let kind = match mode {
                 CaptureMode::BitBlt => WindowsCaptureKind::BitBlt,
                 CaptureMode::WindowsGraphicsCapture => WindowsCaptureKind::Wgc(MS_PER_TICK),
                 CaptureMode::BitBltArea => WindowsCaptureKind::BitBltArea,
+                CaptureMode::Playback | CaptureMode::Rpc => return,
             };
             let _ = self.inner.windows_capture_kind(kind);
         }
     }
+
+    fn set_playback(&mut self, playback: CapturePlayback) {
+        self.playback = PlaybackCapture::new(&playback)
+            .inspect_err(|err| {
+                error!(target: "capture", "failed to play back {} {err}", playback.path);
+            })
+            .ok();
+    }
+
+    fn set_rpc_server_url(&mut self, url: String) {
+        self.rpc = None; // Drops the previous stream before connecting
+        self.rpc = FrameService::connect(url.clone())
+            .inspect_err(|err| {
+                error!(target: "capture", "invalid frame source URL {url} {err}");
+            })
+            .ok();
+    }
 }
 
 #[inline]
@@ -943,6 +1135,51 @@ mod tests {
         assert!(!sender.has_input_delay(KeyKind::Ctrl));
     }
 
+    #[test]
+    fn send_sequence_default_queue_events_after_wait() {
+        let mut sender = test_key_sender();
+
+        sender
+            .send_sequence(&[
+                InputEvent::Key(KeyKind::A),
+                InputEvent::Wait(MS_PER_TICK * 2),
+                InputEvent::Key(KeyKind::S),
+            ])
+            .unwrap();
+        assert_eq!(
+            *sender.sequence.borrow(),
+            [
+                InputEvent::Wait(MS_PER_TICK * 2),
+                InputEvent::Key(KeyKind::S)
+            ]
+        );
+
+        sender.update(1);
+        assert_eq!(
+            *sender.sequence.borrow(),
+            [InputEvent::Wait(MS_PER_TICK), InputEvent::Key(KeyKind::S)]
+        );
+
+        sender.update(2);
+        assert!(sender.sequence.borrow().is_empty());
+    }
+
+    #[test]
+    fn set_method_clear_queued_sequence() {
+        let mut sender = test_key_sender();
+
+        sender
+            .send_sequence(&[InputEvent::Wait(MS_PER_TICK), InputEvent::Key(KeyKind::A)])
+            .unwrap();
+        assert!(!sender.sequence.borrow().is_empty());
+
+        sender.set_method(InputMethod::Default(
+            Window::new("Handle"),
+            PlatformInputKind::Focused,
+        ));
+        assert!(sender.sequence.borrow().is_empty());
+    }
+
     #[test]
     fn update_input_delay_refresh_mean_std_pair_every_interval() {
         let mut sender = test_key_sender();
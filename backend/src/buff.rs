@@ -1,13 +1,18 @@
 use std::{
-    mem,
     ops::{Index, IndexMut},
+    sync::Arc,
 };
 
 use anyhow::Result;
-use strum::EnumIter;
+use log::error;
+use opencv::{
+    core::{Mat, MatTraitConst, Vector},
+    imgcodecs::{IMREAD_GRAYSCALE, imdecode},
+};
+use strum::{EnumIter, IntoEnumIterator};
 
 use crate::{
-    Character, Settings,
+    BuffIcon, Character, Settings,
     context::{Context, Contextual, ControlFlow},
     player::Player,
     task::{Task, Update, update_detection_task},
@@ -22,6 +27,10 @@ const RUNE_FAIL_COUNT: u32 = 1;
 pub struct BuffState {
     /// The kind of buff.
     kind: BuffKind,
+    /// The grayscale icon of [`BuffKind::Custom`].
+    ///
+    /// This is [`None`] if the buff is not custom or its icon cannot be decoded.
+    icon: Option<Arc<Mat>>,
     /// Task for detecting if the coresponding buff exists.
     task: Option<Task<Result<bool>>>,
     /// The number of time [`Buff::Volatile`] has failed to detect if the buff exists.
@@ -37,6 +46,7 @@ impl BuffState {
     pub fn new(kind: BuffKind) -> Self {
         Self {
             kind,
+            icon: None,
             task: None,
             fail_count: 0,
             max_fail_count: match kind {
@@ -58,12 +68,29 @@ impl BuffState {
                 | BuffKind::ExtremeRedPotion
                 | BuffKind::ExtremeBluePotion
                 | BuffKind::ExtremeGreenPotion
-                | BuffKind::ExtremeGoldPotion => COMMON_FAIL_COUNT,
+                | BuffKind::ExtremeGoldPotion
+                | BuffKind::Custom(_) => COMMON_FAIL_COUNT,
             },
             enabled: true,
         }
     }
 
+    /// Creates a state for the custom buff at `index` from its configuration.
+    pub fn new_custom(index: usize, buff: &BuffIcon) -> Self {
+        let icon = imdecode(&Vector::<u8>::from_slice(&buff.icon), IMREAD_GRAYSCALE)
+            .ok()
+            .filter(|icon| !icon.empty());
+        if icon.is_none() {
+            error!(target: "buff", "failed to decode icon of custom buff {index}");
+        }
+
+        Self {
+            icon: icon.map(Arc::new),
+            max_fail_count: buff.max_fail_count.max(1),
+            ..Self::new(BuffKind::Custom(index))
+        }
+    }
+
     /// Updates the enabled states of each buff to only detect if enabled.
     pub fn update_enabled_state(&mut self, character: &Character, settings: &Settings) {
         self.enabled = match self.kind {
@@ -90,6 +117,10 @@ impl BuffState {
             BuffKind::ExtremeBluePotion => character.extreme_blue_potion_key.enabled,
             BuffKind::ExtremeGreenPotion => character.extreme_green_potion_key.enabled,
             BuffKind::ExtremeGoldPotion => character.extreme_gold_potion_key.enabled,
+            BuffKind::Custom(index) => character
+                .buffs
+                .get(index)
+                .is_some_and(|buff| buff.key.enabled),
         };
         if !self.enabled {
             self.fail_count = 0;
@@ -98,6 +129,20 @@ impl BuffState {
     }
 }
 
+/// Creates the states of all built-in buffs ordered by [`BuffKind::index`].
+pub fn buff_states() -> Vec<BuffState> {
+    BuffKind::iter().map(BuffState::new).collect()
+}
+
+/// Creates the states of the custom buffs in `character` ordered by [`BuffKind::index`].
+pub fn custom_buff_states(character: &Character) -> impl Iterator<Item = BuffState> + '_ {
+    character
+        .buffs
+        .iter()
+        .enumerate()
+        .map(|(index, buff)| BuffState::new_custom(index, buff))
+}
+
 /// Buff contextual state.
 #[derive(Clone, Copy, Debug)]
 pub enum Buff {
@@ -109,9 +154,7 @@ pub enum Buff {
     Volatile,
 }
 
-#[derive(Clone, Copy, Debug, EnumIter)]
-#[cfg_attr(test, derive(PartialEq))]
-#[repr(usize)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, EnumIter)]
 pub enum BuffKind {
     // NOTE: Upon failing to solving rune, there is a cooldown
     // that looks exactly like the normal rune buff.
@@ -134,23 +177,56 @@ pub enum BuffKind {
     ExtremeBluePotion,
     ExtremeGreenPotion,
     ExtremeGoldPotion,
+    /// A user-defined buff at the index of [`Character::buffs`].
+    #[strum(disabled)]
+    Custom(usize),
 }
 
 impl BuffKind {
-    pub const COUNT: usize = mem::variant_count::<BuffKind>();
+    /// The number of built-in buffs.
+    pub const COUNT: usize = BuffKind::ExtremeGoldPotion.index() + 1;
+
+    /// The index of this buff in [`Context::buffs`].
+    ///
+    /// Built-in buffs come first in declaration order followed by the custom ones.
+    #[inline]
+    pub const fn index(self) -> usize {
+        match self {
+            BuffKind::Rune => 0,
+            BuffKind::Familiar => 1,
+            BuffKind::SayramElixir => 2,
+            BuffKind::AureliaElixir => 3,
+            BuffKind::ExpCouponX2 => 4,
+            BuffKind::ExpCouponX3 => 5,
+            BuffKind::BonusExpCoupon => 6,
+            BuffKind::LegionWealth => 7,
+            BuffKind::LegionLuck => 8,
+            BuffKind::WealthAcquisitionPotion => 9,
+            BuffKind::ExpAccumulationPotion => 10,
+            BuffKind::SmallWealthAcquisitionPotion => 11,
+            BuffKind::SmallExpAccumulationPotion => 12,
+            BuffKind::ForTheGuild => 13,
+            BuffKind::HardHitter => 14,
+            BuffKind::ExtremeRedPotion => 15,
+            BuffKind::ExtremeBluePotion => 16,
+            BuffKind::ExtremeGreenPotion => 17,
+            BuffKind::ExtremeGoldPotion => 18,
+            BuffKind::Custom(index) => BuffKind::COUNT + index,
+        }
+    }
 }
 
-impl Index<BuffKind> for [Buff; BuffKind::COUNT] {
+impl Index<BuffKind> for Vec<Buff> {
     type Output = Buff;
 
     fn index(&self, index: BuffKind) -> &Self::Output {
-        self.get(index as usize).unwrap()
+        self.get(index.index()).unwrap()
     }
 }
 
-impl IndexMut<BuffKind> for [Buff; BuffKind::COUNT] {
+impl IndexMut<BuffKind> for Vec<Buff> {
     fn index_mut(&mut self, index: BuffKind) -> &mut Self::Output {
-        self.get_mut(index as usize).unwrap()
+        self.get_mut(index.index()).unwrap()
     }
 }
 
@@ -173,9 +249,15 @@ impl Contextual for Buff {
 #[inline]
 fn update_context(contextual: Buff, context: &Context, state: &mut BuffState) -> Buff {
     let kind = state.kind;
+    let icon = state.icon.clone();
     let Update::Ok(has_buff) =
         update_detection_task(context, 5000, &mut state.task, move |detector| {
-            Ok(detector.detect_player_buff(kind))
+            Ok(match kind {
+                BuffKind::Custom(_) => {
+                    icon.is_some_and(|icon| detector.detect_player_custom_buff(&icon))
+                }
+                kind => detector.detect_player_buff(kind),
+            })
         })
     else {
         return contextual;
@@ -210,11 +292,10 @@ mod tests {
     use std::{assert_matches::assert_matches, time::Duration};
 
     use mockall::predicate::eq;
-    use strum::IntoEnumIterator;
     use tokio::time::advance;
 
     use super::*;
-    use crate::detect::MockDetector;
+    use crate::{KeyBinding, KeyBindingConfiguration, detect::MockDetector};
 
     fn detector_with_kind(kind: BuffKind, result: bool) -> MockDetector {
         let mut detector = MockDetector::new();
@@ -311,6 +392,42 @@ mod tests {
         assert!(state.task.is_none());
     }
 
+    #[test]
+    fn buff_kind_index_custom_after_built_in() {
+        assert_eq!(BuffKind::Rune.index(), 0);
+        assert_eq!(BuffKind::ExtremeGoldPotion.index(), BuffKind::COUNT - 1);
+        assert_eq!(BuffKind::Custom(1).index(), BuffKind::COUNT + 1);
+        assert!(BuffKind::iter().all(|kind| !matches!(kind, BuffKind::Custom(_))));
+        assert_eq!(BuffKind::iter().count(), BuffKind::COUNT);
+        for (index, kind) in BuffKind::iter().enumerate() {
+            assert_eq!(kind.index(), index);
+        }
+    }
+
+    #[test]
+    fn custom_buff_state_from_config() {
+        let character = Character {
+            buffs: vec![BuffIcon {
+                key: KeyBindingConfiguration {
+                    key: KeyBinding::A,
+                    enabled: false,
+                },
+                max_fail_count: 3,
+                ..BuffIcon::default()
+            }],
+            ..Character::default()
+        };
+
+        let mut states = custom_buff_states(&character).collect::<Vec<_>>();
+        assert_eq!(states.len(), 1);
+        assert_eq!(states[0].kind, BuffKind::Custom(0));
+        assert_eq!(states[0].max_fail_count, 3);
+        assert!(states[0].icon.is_none());
+
+        states[0].update_enabled_state(&character, &Settings::default());
+        assert!(!states[0].enabled);
+    }
+
     #[tokio::test(start_paused = true)]
     async fn buff_volatile_stay_before_threshold() {
         for kind in BuffKind::iter() {
@@ -7,40 +7,44 @@ use std::{
     time::{Duration, Instant},
 };
 
+use anyhow::Result;
 use dyn_clone::clone_box;
 #[cfg(debug_assertions)]
 use log::debug;
+use log::{error, info};
 #[cfg(debug_assertions)]
 use opencv::core::Rect;
 use opencv::{
-    core::{Vector, VectorToVec},
+    core::{MatTraitConst, Size, Vector, VectorToVec},
     imgcodecs::imencode_def,
 };
-use strum::IntoEnumIterator;
-use tokio::sync::broadcast::channel;
+use tokio::sync::broadcast::{Receiver, Sender, channel};
 
 #[cfg(debug_assertions)]
 use crate::bridge::KeyKind;
 #[cfg(debug_assertions)]
 use crate::debug::save_rune_for_training;
 use crate::{
-    CycleRunStopMode,
+    CycleRunStopMode, RequestHandler, Settings,
     bridge::{Capture, Input},
-    buff::{Buff, BuffKind, BuffState},
+    buff::{Buff, BuffKind, BuffState, buff_states},
     database::{query_seeds, query_settings},
     detect::{CachedDetector, Detector},
     mat::OwnedMat,
     minimap::{Minimap, MinimapState},
+    models,
     navigator::{DefaultNavigator, Navigator},
     notification::DiscordNotification,
     player::{Player, PlayerState},
     rng::Rng,
     rotator::{DefaultRotator, Rotator},
+    rpc::InputConnectionEvent,
     services::{DefaultService, PollArgs},
-    skill::{Skill, SkillKind, SkillState},
+    skill::{Skill, SkillState, skill_states_from},
+    task::{Task, Update, update_detection_task},
 };
 #[cfg(test)]
-use crate::{Settings, bridge::MockInput, detect::MockDetector};
+use crate::{bridge::MockInput, detect::MockDetector};
 
 /// The FPS the bot runs at.
 ///
@@ -137,10 +141,10 @@ pub struct Context {
     pub minimap: Minimap,
     /// The player contextual state.
     pub player: Player,
-    /// The skill contextual states.
-    pub skills: [Skill; SkillKind::COUNT],
+    /// The skill contextual states indexed by [`crate::skill::SkillKind::index`].
+    pub skills: Vec<Skill>,
     /// The buff contextual states.
-    pub buffs: [Buff; BuffKind::COUNT],
+    pub buffs: Vec<Buff>,
     /// The bot current's operation.
     pub operation: Operation,
     /// The game current tick.
@@ -161,8 +165,8 @@ impl Context {
             detector: detector.map(|detector| Box::new(detector) as Box<dyn Detector>),
             minimap: Minimap::Detecting,
             player: Player::Detecting,
-            skills: [Skill::Detecting; SkillKind::COUNT],
-            buffs: [Buff::No; BuffKind::COUNT],
+            skills: vec![Skill::Detecting],
+            buffs: vec![Buff::No; BuffKind::COUNT],
             operation: Operation::Running,
             tick: 0,
         }
@@ -188,6 +192,9 @@ pub enum ContextEvent {
     PlayerDied,
     MinimapChanged,
     CaptureFailed,
+    InputConnected,
+    InputDisconnected,
+    InputReconnected,
 }
 
 /// Current operating state of the bot.
@@ -335,6 +342,7 @@ pub fn init() {
             .join("onnxruntime.dll");
 
         ort::init_from(dll.to_str().unwrap()).commit().unwrap();
+        models::init();
         platforms::init();
         thread::spawn(|| {
             let tokio_rt = tokio::runtime::Builder::new_multi_thread()
@@ -355,103 +363,245 @@ fn update_loop() {
     let seeds = query_seeds(); // Fixed, unchanged
     let rng = Rng::new(seeds.seed); // Create one for Context
     let (event_tx, event_rx) = channel::<ContextEvent>(5);
-    let (mut service, keys, mut capture) =
+    let (service, input, capture) =
         DefaultService::new(seeds, settings.clone(), event_tx.subscribe());
 
-    let mut rotator = DefaultRotator::default();
-    let mut navigator = DefaultNavigator::new(event_rx);
-    let mut context = Context {
-        #[cfg(debug_assertions)]
-        debug: Debug::default(),
-        input: Box::new(keys),
+    UpdateLoop::new(
+        settings,
         rng,
-        notification: DiscordNotification::new(settings.clone()),
-        detector: None,
-        minimap: Minimap::Detecting,
-        player: Player::Idle,
-        skills: [Skill::Detecting],
-        buffs: [Buff::No; BuffKind::COUNT],
-        operation: Operation::Halting,
-        tick: 0,
-    };
-    let mut player_state = PlayerState::default();
-    let mut minimap_state = MinimapState::default();
-    let mut skill_states = SkillKind::iter()
-        .map(SkillState::new)
-        .collect::<Vec<SkillState>>();
-    let mut buff_states = BuffKind::iter()
-        .map(BuffState::new)
-        .collect::<Vec<BuffState>>();
-    let mut is_capturing_normally = false;
-
-    loop_with_fps(FPS, || {
-        let detector = capture
+        service,
+        Box::new(input),
+        Box::new(capture),
+        event_tx,
+        event_rx,
+    )
+    .run(|_| true);
+}
+
+/// All the states owned by the update loop that persist across ticks.
+pub(crate) struct UpdateLoop {
+    context: Context,
+    service: DefaultService,
+    capture: Box<dyn Capture>,
+    event_tx: Sender<ContextEvent>,
+    rotator: DefaultRotator,
+    navigator: DefaultNavigator,
+    player_state: PlayerState,
+    minimap_state: MinimapState,
+    skill_states: Vec<SkillState>,
+    buff_states: Vec<BuffState>,
+    /// The frame size and its template scale detected in the background.
+    template_scale: Option<(Size, f64)>,
+    template_scale_task: Option<Task<Result<(Size, f64)>>>,
+    is_capturing_normally: bool,
+    /// Whether [`Context::operation`] was halting in the previous tick.
+    was_halting: bool,
+}
+
+impl UpdateLoop {
+    pub fn new(
+        settings: Rc<RefCell<Settings>>,
+        rng: Rng,
+        service: DefaultService,
+        input: Box<dyn Input>,
+        capture: Box<dyn Capture>,
+        event_tx: Sender<ContextEvent>,
+        event_rx: Receiver<ContextEvent>,
+    ) -> Self {
+        let context = Context {
+            #[cfg(debug_assertions)]
+            debug: Debug::default(),
+            input,
+            rng,
+            notification: DiscordNotification::new(settings),
+            detector: None,
+            minimap: Minimap::Detecting,
+            player: Player::Idle,
+            skills: vec![Skill::Detecting],
+            buffs: vec![Buff::No; BuffKind::COUNT],
+            operation: Operation::Halting,
+            tick: 0,
+        };
+
+        Self {
+            context,
+            service,
+            capture,
+            event_tx,
+            rotator: DefaultRotator::default(),
+            navigator: DefaultNavigator::new(event_rx),
+            player_state: PlayerState::default(),
+            minimap_state: MinimapState::default(),
+            skill_states: skill_states_from(None),
+            buff_states: buff_states(),
+            template_scale: None,
+            template_scale_task: None,
+            is_capturing_normally: false,
+            was_halting: true,
+        }
+    }
+
+    #[inline]
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    /// Invokes `f` with the service's [`RequestHandler`] outside of the normal request polling.
+    pub fn handle(&mut self, f: impl FnOnce(&mut dyn RequestHandler)) {
+        let args = self.poll_args();
+        self.service.handle(args, f);
+    }
+
+    /// Ticks at [`FPS`] until `on_ticked` returns `false`.
+    pub fn run(mut self, mut on_ticked: impl FnMut(&Context) -> bool) {
+        loop_with_fps(FPS, || {
+            self.tick();
+            on_ticked(&self.context)
+        });
+    }
+
+    fn tick(&mut self) {
+        let context = &mut self.context;
+        let detector = self
+            .capture
             .grab()
-            .map(OwnedMat::new_from_frame)
-            .map(CachedDetector::new);
-        let was_capturing_normally = is_capturing_normally;
+            .ok()
+            .and_then(|frame| {
+                OwnedMat::new_from_frame(frame)
+                    .inspect_err(
+                        |err| error!(target: "context", "failed to convert captured frame {err:?}"),
+                    )
+                    .ok()
+            })
+            .map(|mat| {
+                let size = mat.size().unwrap();
+                let scale = self
+                    .template_scale
+                    .filter(|(scale_size, _)| *scale_size == size)
+                    .map_or(1.0, |(_, scale)| scale);
+                CachedDetector::new_with_scale(mat, scale)
+            });
+        let was_capturing_normally = self.is_capturing_normally;
 
-        is_capturing_normally = detector.is_ok();
+        self.is_capturing_normally = detector.is_some();
         context.tick += 1;
-        if let Ok(detector) = detector {
-            let was_player_alive = !player_state.is_dead();
+        if let Some(detector) = detector {
+            let was_player_alive = !self.player_state.is_dead();
             let was_running_cycle = matches!(context.operation, Operation::RunUntil { .. });
             let was_minimap_idle = matches!(context.minimap, Minimap::Idle(_));
 
             context.operation = context.operation.update();
             context.detector = Some(Box::new(detector));
-            context.minimap = fold_context(&context, context.minimap, &mut minimap_state);
-            context.player = fold_context(&context, context.player, &mut player_state);
-            for (i, state) in skill_states
+            update_template_scale(
+                context,
+                &mut self.template_scale,
+                &mut self.template_scale_task,
+            );
+            context.minimap = fold_context(context, context.minimap, &mut self.minimap_state);
+            context.player = fold_context(context, context.player, &mut self.player_state);
+            for (i, state) in self
+                .skill_states
                 .iter_mut()
                 .enumerate()
                 .take(context.skills.len())
             {
-                context.skills[i] = fold_context(&context, context.skills[i], state);
+                context.skills[i] = fold_context(context, context.skills[i], state);
             }
-            for (i, state) in buff_states.iter_mut().enumerate().take(context.buffs.len()) {
-                context.buffs[i] = fold_context(&context, context.buffs[i], state);
+            for (i, state) in self
+                .buff_states
+                .iter_mut()
+                .enumerate()
+                .take(context.buffs.len())
+            {
+                context.buffs[i] = fold_context(context, context.buffs[i], state);
             }
 
-            if navigator.navigate_player(&context, &mut player_state) {
-                rotator.rotate_action(&context, &mut player_state);
+            if self
+                .navigator
+                .navigate_player(context, &mut self.player_state)
+            {
+                self.rotator.rotate_action(context, &mut self.player_state);
             }
 
             let did_cycled_to_stop = context.operation.halting();
             // Go to town on stop cycle
             if was_running_cycle && did_cycled_to_stop {
-                let _ = event_tx.send(ContextEvent::CycledToHalt);
+                let _ = self.event_tx.send(ContextEvent::CycledToHalt);
             }
 
-            let player_died = was_player_alive && player_state.is_dead();
+            let player_died = was_player_alive && self.player_state.is_dead();
             if player_died {
-                let _ = event_tx.send(ContextEvent::PlayerDied);
+                let _ = self.event_tx.send(ContextEvent::PlayerDied);
             }
 
             let minimap_detecting = matches!(context.minimap, Minimap::Detecting);
             if was_minimap_idle && minimap_detecting {
-                let _ = event_tx.send(ContextEvent::MinimapChanged);
+                let _ = self.event_tx.send(ContextEvent::MinimapChanged);
             }
         }
 
-        if was_capturing_normally && !is_capturing_normally {
-            let _ = event_tx.send(ContextEvent::CaptureFailed);
+        if was_capturing_normally && !self.is_capturing_normally {
+            let _ = self.event_tx.send(ContextEvent::CaptureFailed);
         }
 
-        context.input.update(context.tick);
+        // Sequences queued while running must not continue after halting
+        let halting = context.operation.halting();
+        if halting && !self.was_halting {
+            context.input.clear_sequence();
+        }
+        self.was_halting = halting;
+
+        if let Some(event) = context.input.update(context.tick) {
+            let event = match event {
+                InputConnectionEvent::Connected => ContextEvent::InputConnected,
+                InputConnectionEvent::Disconnected => ContextEvent::InputDisconnected,
+                InputConnectionEvent::Reconnected => ContextEvent::InputReconnected,
+            };
+            let _ = self.event_tx.send(event);
+        }
         context
             .notification
             .update(|| to_png(context.detector.as_ref().map(|detector| detector.mat())));
-        service.poll(PollArgs {
-            context: &mut context,
-            player: &mut player_state,
-            minimap: &mut minimap_state,
-            buffs: &mut buff_states,
-            rotator: &mut rotator,
-            navigator: &mut navigator,
-            capture: &mut capture,
-        });
+        let args = self.poll_args();
+        self.service.poll(args);
+    }
+
+    #[inline]
+    fn poll_args(&mut self) -> PollArgs<'_> {
+        PollArgs {
+            context: &mut self.context,
+            player: &mut self.player_state,
+            minimap: &mut self.minimap_state,
+            skills: &mut self.skill_states,
+            buffs: &mut self.buff_states,
+            rotator: &mut self.rotator,
+            navigator: &mut self.navigator,
+            capture: self.capture.as_mut(),
+        }
+    }
+}
+
+/// Detects the template scale in the background when the frame size changes.
+///
+/// Until detected, templates are matched at their original size. Detection is retried every 3
+/// seconds while failing (e.g. the health bar is not visible).
+fn update_template_scale(
+    context: &Context,
+    template_scale: &mut Option<(Size, f64)>,
+    task: &mut Option<Task<Result<(Size, f64)>>>,
+) {
+    let size = context.detector_unwrap().mat().size().unwrap();
+    if template_scale.is_some_and(|(scale_size, _)| scale_size == size) {
+        return;
+    }
+
+    let update = update_detection_task(context, 3000, task, |detector| {
+        Ok((detector.mat().size()?, detector.detect_template_scale()?))
     });
+    if let Update::Ok((size, scale)) = update {
+        info!(target: "context", "detected template scale {scale} for frame size {size:?}");
+        *template_scale = Some((size, scale));
+    }
 }
 
 #[inline]
@@ -475,7 +625,7 @@ where
 }
 
 #[inline]
-fn loop_with_fps(fps: u32, mut on_tick: impl FnMut()) {
+fn loop_with_fps(fps: u32, mut on_tick: impl FnMut() -> bool) {
     #[cfg(debug_assertions)]
     const LOG_INTERVAL_SECS: u64 = 5;
 
@@ -486,7 +636,9 @@ fn loop_with_fps(fps: u32, mut on_tick: impl FnMut()) {
     loop {
         let start = Instant::now();
 
-        on_tick();
+        if !on_tick() {
+            break;
+        }
 
         let now = Instant::now();
         let elapsed_duration = now.duration_since(start);
@@ -1,12 +1,17 @@
 use std::{
+    cell::OnceCell,
     collections::{HashMap, HashSet},
-    env,
+    env, fs,
+    ops::{Deref, DerefMut},
+    path::PathBuf,
     sync::{LazyLock, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::{Result, bail};
+use log::{error, info};
 use opencv::core::Rect;
-use rusqlite::{Connection, Params, Statement, types::Null};
+use rusqlite::{Connection, OptionalExtension, Transaction, types::Null};
 use serde::{Deserialize, Deserializer, Serialize, de::DeserializeOwned};
 use serde_json::Value;
 use strum::{Display, EnumIter, EnumString};
@@ -20,44 +25,68 @@ const NAVIGATION_PATHS: &str = "navigation_paths";
 const CHARACTERS: &str = "characters";
 const SETTINGS: &str = "settings";
 const SEEDS: &str = "seeds";
-
-static CONNECTION: LazyLock<Mutex<Connection>> = LazyLock::new(|| {
-    let path = env::current_exe()
-        .unwrap()
-        .parent()
-        .unwrap()
-        .join("local.db")
-        .to_path_buf();
-    let conn = Connection::open(path.to_str().unwrap()).expect("failed to open local.db");
-    conn.execute_batch(
-        r#"
-        CREATE TABLE IF NOT EXISTS maps (
-            id INTEGER PRIMARY KEY,
-            data TEXT NOT NULL
-        );
-        CREATE TABLE IF NOT EXISTS navigation_paths (
-            id INTEGER PRIMARY KEY,
-            data TEXT NOT NULL
-        );
-        CREATE TABLE IF NOT EXISTS characters (
-            id INTEGER PRIMARY KEY,
-            data TEXT NOT NULL
-        );
-        CREATE TABLE IF NOT EXISTS settings (
-            id INTEGER PRIMARY KEY,
-            data TEXT NOT NULL
-        );
-        CREATE TABLE IF NOT EXISTS seeds (
-            id INTEGER PRIMARY KEY,
-            data TEXT NOT NULL
-        );
-        "#,
-    )
-    .unwrap();
-    Mutex::new(conn)
+const SCHEMA_VERSION_TABLE: &str = "schema_version";
+const REVISIONS: &str = "revisions";
+const QUARANTINE: &str = "quarantine";
+const EXP_SESSIONS: &str = "exp_sessions";
+const MAX_REVISIONS_PER_DATA: u32 = 50;
+const LEGACY_PRESET: &str = "Default";
+const BUNDLE_VERSION: u32 = 1;
+const DATABASE_FILE_NAME: &str = "local.db";
+const DATABASE_FLAG: &str = "--database";
+const DATABASE_ENV: &str = "KOMARI_DATABASE";
+const PROFILES_DIR: &str = "profiles";
+const PROFILE_FLAG: &str = "--profile";
+const PROFILE_ENV: &str = "KOMARI_PROFILE";
+
+/// The schema version this binary reads and writes.
+///
+/// Appending a step to [`MIGRATIONS`] bumps this version.
+const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// Ordered migration steps where the step at index `i` upgrades version `i` to `i + 1`.
+///
+/// Steps must never be removed or reordered once released.
+const MIGRATIONS: [fn(&Transaction<'_>) -> Result<()>; 2] = [migrate_v0_to_v1, migrate_v1_to_v2];
+
+static DATABASE: LazyLock<Mutex<Database>> = LazyLock::new(|| {
+    let profile = arg_or_env(PROFILE_FLAG, PROFILE_ENV).filter(|name| is_valid_profile_name(name));
+    Mutex::new(Database {
+        profile,
+        conn: OnceCell::new(),
+    })
 });
 static EVENT: LazyLock<Sender<DatabaseEvent>> = LazyLock::new(|| channel(5).0);
 
+/// The opened database connection together with its profile.
+///
+/// Both are guarded by the same lock so that switching profile is atomic. The connection is
+/// opened on first dereference so that a profile can be switched to without opening the default
+/// database first.
+#[derive(Debug)]
+struct Database {
+    /// The currently opened profile or `None` for the default database.
+    profile: Option<String>,
+    conn: OnceCell<Connection>,
+}
+
+impl Deref for Database {
+    type Target = Connection;
+
+    fn deref(&self) -> &Self::Target {
+        self.conn.get_or_init(|| {
+            open_connection(self.profile.as_deref()).expect("failed to open database")
+        })
+    }
+}
+
+impl DerefMut for Database {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        let _ = self.deref();
+        self.conn.get_mut().unwrap()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum DatabaseEvent {
     MinimapUpdated(Minimap),
@@ -67,6 +96,9 @@ pub enum DatabaseEvent {
     SettingsUpdated(Settings),
     CharacterUpdated(Character),
     CharacterDeleted(i64),
+    ExpSessionUpdated,
+    ProfileSwitched(Settings),
+    RowQuarantined(QuarantinedRow),
 }
 
 trait Identifiable {
@@ -176,6 +208,19 @@ pub enum CycleRunStopMode {
     Repeat,
 }
 
+/// The policy to confirm switching to a stored [`Minimap`] matched on map entry.
+#[derive(
+    Clone, Copy, PartialEq, Default, Debug, Serialize, Deserialize, EnumIter, Display, EnumString,
+)]
+pub enum MinimapAutoSelectPolicy {
+    #[default]
+    Disabled,
+    /// Switches as soon as a stored minimap matches.
+    Immediately,
+    /// Switches only after the same stored minimap matches multiple times in a row.
+    Consecutive,
+}
+
 #[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
 pub struct Notifications {
     pub discord_webhook_url: String,
@@ -187,6 +232,8 @@ pub struct Notifications {
     pub notify_on_player_guildie_appear: bool,
     pub notify_on_player_stranger_appear: bool,
     pub notify_on_player_friend_appear: bool,
+    #[serde(default)]
+    pub notify_on_input_disconnect_or_reconnect: bool,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -194,6 +241,10 @@ pub struct Settings {
     #[serde(skip_serializing, default)]
     pub id: Option<i64>,
     pub capture_mode: CaptureMode,
+    #[serde(default)]
+    pub capture_playback: CapturePlayback,
+    #[serde(default)]
+    pub capture_rpc_server_url: String,
     #[serde(default = "enable_rune_solving_default")]
     pub enable_rune_solving: bool,
     pub enable_panic_mode: bool,
@@ -204,6 +255,8 @@ pub struct Settings {
     pub cycle_run_duration_millis: u64,
     #[serde(default = "cycle_stop_duration_millis_default")]
     pub cycle_stop_duration_millis: u64,
+    #[serde(default, deserialize_with = "deserialize_with_ok_or_default")]
+    pub minimap_auto_select: MinimapAutoSelectPolicy,
     pub input_method: InputMethod,
     pub input_method_rpc_server_url: String,
     #[serde(default)]
@@ -225,6 +278,8 @@ impl Default for Settings {
         Self {
             id: None,
             capture_mode: CaptureMode::default(),
+            capture_playback: CapturePlayback::default(),
+            capture_rpc_server_url: String::default(),
             enable_rune_solving: enable_rune_solving_default(),
             enable_panic_mode: false,
             input_method: InputMethod::default(),
@@ -233,6 +288,7 @@ impl Default for Settings {
             cycle_run_stop: CycleRunStopMode::default(),
             cycle_run_duration_millis: cycle_run_duration_millis_default(),
             cycle_stop_duration_millis: cycle_stop_duration_millis_default(),
+            minimap_auto_select: MinimapAutoSelectPolicy::default(),
             discord_bot_access_token: String::default(),
             notifications: Notifications::default(),
             familiars: Familiars::default(),
@@ -295,6 +351,41 @@ pub enum CaptureMode {
     #[strum(to_string = "Windows 10 (1903 and up)")] // Thanks OBS
     WindowsGraphicsCapture,
     BitBltArea,
+    /// Plays back frames from [`Settings::capture_playback`] instead of capturing a window.
+    #[strum(to_string = "Image directory or video")]
+    Playback,
+    /// Receives frames streamed from [`Settings::capture_rpc_server_url`] instead of capturing a
+    /// window.
+    #[strum(to_string = "Remote frame source (RPC)")]
+    Rpc,
+}
+
+/// Options for [`CaptureMode::Playback`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CapturePlayback {
+    /// The path to an image directory or a video file.
+    ///
+    /// Images in a directory are played back in file name order, one image per tick.
+    pub path: String,
+    /// The playback speed multiplier.
+    #[serde(default = "capture_playback_speed_default")]
+    pub speed: f32,
+    /// Whether to restart from the first frame after the last one.
+    pub looping: bool,
+}
+
+impl Default for CapturePlayback {
+    fn default() -> Self {
+        Self {
+            path: String::default(),
+            speed: capture_playback_speed_default(),
+            looping: true,
+        }
+    }
+}
+
+fn capture_playback_speed_default() -> f32 {
+    1.0
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -324,6 +415,10 @@ pub struct Character {
     pub potion_key: KeyBindingConfiguration,
     pub potion_mode: PotionMode,
     pub health_update_millis: u64,
+    #[serde(default)]
+    pub mana_potion_key: KeyBindingConfiguration,
+    #[serde(default = "potion_mode_default")]
+    pub mana_potion_mode: PotionMode,
     pub familiar_buff_key: KeyBindingConfiguration,
     #[serde(default = "key_default")]
     pub familiar_essence_key: KeyBindingConfiguration,
@@ -356,12 +451,30 @@ pub struct Character {
     pub elite_boss_behavior: EliteBossBehavior,
     #[serde(default)]
     pub elite_boss_behavior_key: KeyBinding,
+    // Not FK, loose coupling to a minimap and its actions preset to load on select
+    #[serde(default)]
+    pub minimap_id_preset: Option<(i64, String)>,
+    /// User-registered skills referenced by [`ActionCondition::SkillOffCooldown`].
+    #[serde(default)]
+    pub skills: Vec<SkillIcon>,
+    /// The id given to the next registered skill.
+    ///
+    /// Kept separately from [`Self::skills`] so ids of deleted skills are never reused.
+    #[serde(default)]
+    pub next_skill_id: u32,
+    /// User-defined buffs casted along with the built-in ones.
+    #[serde(default)]
+    pub buffs: Vec<BuffIcon>,
 }
 
 fn num_pets_default() -> u32 {
     3
 }
 
+fn potion_mode_default() -> PotionMode {
+    PotionMode::EveryMillis(180000)
+}
+
 fn jump_key_default() -> KeyBindingConfiguration {
     // Enabled is not neccessary but for semantic purpose
     KeyBindingConfiguration {
@@ -396,8 +509,10 @@ impl Default for Character {
             feed_pet_millis: 320000,
             num_pets: num_pets_default(),
             potion_key: KeyBindingConfiguration::default(),
-            potion_mode: PotionMode::EveryMillis(180000),
+            potion_mode: potion_mode_default(),
             health_update_millis: 1000,
+            mana_potion_key: KeyBindingConfiguration::default(),
+            mana_potion_mode: potion_mode_default(),
             familiar_buff_key: KeyBindingConfiguration::default(),
             familiar_essence_key: key_default(),
             sayram_elixir_key: KeyBindingConfiguration::default(),
@@ -422,10 +537,59 @@ impl Default for Character {
             actions: vec![],
             elite_boss_behavior_key: KeyBinding::default(),
             elite_boss_behavior: EliteBossBehavior::default(),
+            minimap_id_preset: None,
+            skills: vec![],
+            next_skill_id: 0,
+            buffs: vec![],
         }
     }
 }
 
+/// A user-registered skill detected from its quick slot icon.
+#[derive(Clone, PartialEq, Default, Debug, Serialize, Deserialize)]
+pub struct SkillIcon {
+    /// The id referenced by [`ActionCondition::SkillOffCooldown`].
+    ///
+    /// Unlike the index in [`Character::skills`], it does not change when other skills are
+    /// deleted.
+    #[serde(default)]
+    pub id: u32,
+    pub name: String,
+    /// The PNG-encoded icon cropped from the quick slots.
+    ///
+    /// The icon is cropped at the game current resolution so it is matched without scaling.
+    pub icon: Vec<u8>,
+}
+
+/// A user-defined buff detected from its icon in the buffs region.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct BuffIcon {
+    pub name: String,
+    /// The PNG-encoded icon cropped from the buffs region.
+    ///
+    /// The icon is cropped at the game current resolution so it is matched without scaling.
+    pub icon: Vec<u8>,
+    pub key: KeyBindingConfiguration,
+    /// The number of consecutive failed detections before the buff is considered expired.
+    #[serde(default = "buff_max_fail_count_default")]
+    pub max_fail_count: u32,
+}
+
+impl Default for BuffIcon {
+    fn default() -> Self {
+        Self {
+            name: String::default(),
+            icon: vec![],
+            key: KeyBindingConfiguration::default(),
+            max_fail_count: buff_max_fail_count_default(),
+        }
+    }
+}
+
+fn buff_max_fail_count_default() -> u32 {
+    5
+}
+
 #[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize, EnumIter, Display, EnumString)]
 pub enum PotionMode {
     EveryMillis(u64),
@@ -588,6 +752,12 @@ pub struct Minimap {
     #[serde(skip_serializing)]
     pub id: Option<i64>,
     pub name: String,
+    /// The in-game map name recognized from the minimap title when this minimap was created.
+    #[serde(default)]
+    pub map_name: Option<String>,
+    /// Snapshots used to automatically select this minimap on map entry.
+    #[serde(default)]
+    pub fingerprint: Option<MinimapFingerprint>,
     pub width: i32,
     pub height: i32,
     #[serde(default, deserialize_with = "deserialize_with_ok_or_default")]
@@ -617,6 +787,20 @@ pub struct Minimap {
 
 impl_identifiable!(Minimap);
 
+/// Snapshots of a minimap used to identify the map the player is in.
+#[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MinimapFingerprint {
+    /// The PNG-encoded base64 snapshot of the minimap.
+    pub minimap_snapshot_base64: String,
+    /// Whether [`Self::minimap_snapshot_base64`] is grayscale.
+    ///
+    /// Only fingerprints taken from a [`NavigationPath`] can be grayscale.
+    #[serde(default)]
+    pub minimap_snapshot_grayscale: bool,
+    /// The PNG-encoded base64 grayscale snapshot of the minimap name.
+    pub name_snapshot_base64: String,
+}
+
 #[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
 pub struct NavigationPaths {
     #[serde(skip_serializing, default)]
@@ -655,6 +839,115 @@ pub enum NavigationTransition {
     Portal,
 }
 
+/// A row that failed to deserialize and was moved out of its table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuarantinedRow {
+    pub id: i64,
+    /// The table the row was moved from.
+    pub table: String,
+    /// The `id` of the row in its original table.
+    pub data_id: i64,
+    /// The `name` field of the raw JSON if there is one.
+    pub name: Option<String>,
+    /// The raw JSON of the row.
+    pub data: String,
+    /// The deserialization error.
+    pub error: String,
+    pub timestamp_millis: i64,
+}
+
+/// The kind of data that keeps revisions on every upsert.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum RevisionKind {
+    Minimap,
+    Character,
+}
+
+impl RevisionKind {
+    fn table(self) -> &'static str {
+        match self {
+            RevisionKind::Minimap => MAPS,
+            RevisionKind::Character => CHARACTERS,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Revision {
+    pub id: i64,
+    pub kind: RevisionKind,
+    /// The `id` of the [`Minimap`] or [`Character`] this revision belongs to.
+    pub data_id: i64,
+    pub timestamp_millis: i64,
+}
+
+/// A changed JSON value between two revisions.
+///
+/// `path` is a dot-separated path to the value with array indices in brackets. `from` and `to`
+/// are the JSON encoded values or `None` if the value does not exist.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RevisionChange {
+    pub path: String,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+/// EXP gained by a character on a minimap within a continuous running period.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExpSession {
+    pub id: Option<i64>,
+    pub character_id: i64,
+    pub minimap_id: i64,
+    pub preset: Option<String>,
+    pub start_millis: i64,
+    pub end_millis: i64,
+    /// The EXP gained in percentage of a level.
+    pub gained: f32,
+}
+
+impl ExpSession {
+    /// The EXP gained per hour in percentage of a level.
+    ///
+    /// Returns `None` if the session is too short to extrapolate.
+    pub fn exp_per_hour(&self) -> Option<f32> {
+        const MIN_DURATION_MILLIS: i64 = 60_000;
+        const HOUR_MILLIS: f32 = 3_600_000.0;
+
+        let duration = self.end_millis - self.start_millis;
+        if duration < MIN_DURATION_MILLIS {
+            return None;
+        }
+        Some(self.gained * HOUR_MILLIS / duration as f32)
+    }
+}
+
+/// A single-file export of characters, minimaps, navigation paths and settings.
+///
+/// Entries keep their original ids so cross-references can be remapped on import. Secrets in
+/// [`Settings`] are cleared before exporting.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Bundle {
+    version: u32,
+    characters: Vec<BundleEntry<Character>>,
+    minimaps: Vec<BundleEntry<Minimap>>,
+    navigation_paths: Vec<BundleEntry<NavigationPaths>>,
+    settings: Option<Settings>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct BundleEntry<T> {
+    id: i64,
+    data: T,
+}
+
+#[derive(Debug, Default)]
+struct ImportedBundle {
+    characters: Vec<Character>,
+    minimaps: Vec<Minimap>,
+    navigation_paths: Vec<NavigationPaths>,
+    settings: Option<Settings>,
+}
+
 fn deserialize_with_ok_or_default<'a, T, D>(deserializer: D) -> Result<T, D::Error>
 where
     T: Deserialize<'a> + Default,
@@ -814,6 +1107,8 @@ pub enum ActionCondition {
     Any,
     EveryMillis(u64),
     ErdaShowerOffCooldown,
+    /// Off cooldown of the skill with the [`SkillIcon::id`] in [`Character::skills`].
+    SkillOffCooldown(u32),
     Linked,
 }
 
@@ -1030,13 +1325,15 @@ pub fn query_characters() -> Result<Vec<Character>> {
 }
 
 pub fn upsert_character(character: &mut Character) -> Result<()> {
-    upsert_to_table(CHARACTERS, character).inspect(|_| {
+    dedup_skill_ids(character);
+    upsert_to_table_with_revision(CHARACTERS, character).inspect(|_| {
         let _ = EVENT.send(DatabaseEvent::CharacterUpdated(character.clone()));
     })
 }
 
 pub fn delete_character(character: &Character) -> Result<()> {
     delete_from_table(CHARACTERS, character).inspect(|_| {
+        let _ = delete_revisions(CHARACTERS, character.id.expect("valid id if deleted"));
         let _ = EVENT.send(DatabaseEvent::MinimapDeleted(
             character.id.expect("valid id if deleted"),
         ));
@@ -1048,19 +1345,119 @@ pub fn query_minimaps() -> Result<Vec<Minimap>> {
 }
 
 pub fn upsert_minimap(minimap: &mut Minimap) -> Result<()> {
-    upsert_to_table(MAPS, minimap).inspect(|_| {
+    upsert_to_table_with_revision(MAPS, minimap).inspect(|_| {
         let _ = EVENT.send(DatabaseEvent::MinimapUpdated(minimap.clone()));
     })
 }
 
 pub fn delete_minimap(minimap: &Minimap) -> Result<()> {
     delete_from_table(MAPS, minimap).inspect(|_| {
+        let _ = delete_revisions(MAPS, minimap.id.expect("valid id if deleted"));
         let _ = EVENT.send(DatabaseEvent::MinimapDeleted(
             minimap.id.expect("valid id if deleted"),
         ));
     })
 }
 
+/// Queries revisions of the data with `data_id` from newest to oldest.
+pub fn query_revisions(kind: RevisionKind, data_id: i64) -> Result<Vec<Revision>> {
+    let conn = DATABASE.lock().unwrap();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, timestamp_millis FROM {REVISIONS} WHERE table_name = ?1 AND data_id = ?2 ORDER BY id DESC;"
+    ))?;
+    let revisions = stmt
+        .query_map((kind.table(), data_id), |row| {
+            Ok(Revision {
+                id: row.get(0)?,
+                kind,
+                data_id,
+                timestamp_millis: row.get(1)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(revisions)
+}
+
+/// Computes the changed values from revision `from_id` to revision `to_id`.
+pub fn diff_revisions(from_id: i64, to_id: i64) -> Result<Vec<RevisionChange>> {
+    let conn = DATABASE.lock().unwrap();
+    let query = |id: i64| -> Result<Value> {
+        let data = conn.query_row(
+            &format!("SELECT data FROM {REVISIONS} WHERE id = ?1;"),
+            [id],
+            |row| row.get::<_, String>(0),
+        )?;
+        Ok(serde_json::from_str(&data)?)
+    };
+    let from = query(from_id)?;
+    let to = query(to_id)?;
+
+    let mut changes = vec![];
+    diff_values(String::new(), Some(&from), Some(&to), &mut changes);
+    Ok(changes)
+}
+
+/// Restores the minimap to revision `id`.
+///
+/// The restored minimap is recorded as a new revision.
+pub fn restore_minimap_revision(id: i64) -> Result<Minimap> {
+    restore_revision::<Minimap>(RevisionKind::Minimap, id).inspect(|minimap| {
+        let _ = EVENT.send(DatabaseEvent::MinimapUpdated(minimap.clone()));
+    })
+}
+
+/// Restores the character to revision `id`.
+///
+/// The restored character is recorded as a new revision.
+pub fn restore_character_revision(id: i64) -> Result<Character> {
+    restore_revision::<Character>(RevisionKind::Character, id).inspect(|character| {
+        let _ = EVENT.send(DatabaseEvent::CharacterUpdated(character.clone()));
+    })
+}
+
+pub fn query_quarantined_rows() -> Result<Vec<QuarantinedRow>> {
+    let conn = DATABASE.lock().unwrap();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, table_name, data_id, data, error, timestamp_millis FROM {QUARANTINE} ORDER BY id DESC;"
+    ))?;
+    let rows = stmt
+        .query_map([], |row| {
+            let data = row.get::<_, String>(3)?;
+            Ok(QuarantinedRow {
+                id: row.get(0)?,
+                table: row.get(1)?,
+                data_id: row.get(2)?,
+                name: quarantined_row_name(&data),
+                data,
+                error: row.get(4)?,
+                timestamp_millis: row.get(5)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+pub fn delete_quarantined_row(id: i64) -> Result<()> {
+    let conn = DATABASE.lock().unwrap();
+    if conn.execute(&format!("DELETE FROM {QUARANTINE} WHERE id = ?1;"), [id])? > 0 {
+        Ok(())
+    } else {
+        bail!("no row was deleted")
+    }
+}
+
+/// Queries EXP sessions from newest to oldest.
+pub fn query_exp_sessions() -> Result<Vec<ExpSession>> {
+    query_exp_sessions_with(&DATABASE.lock().unwrap())
+}
+
+/// Inserts `session` if it does not have an id or updates the existing one.
+pub fn upsert_exp_session(session: &mut ExpSession) -> Result<()> {
+    upsert_exp_session_with(&DATABASE.lock().unwrap(), session).inspect(|_| {
+        let _ = EVENT.send(DatabaseEvent::ExpSessionUpdated);
+    })
+}
+
 pub fn query_navigation_paths() -> Result<Vec<NavigationPaths>> {
     query_from_table(NAVIGATION_PATHS)
 }
@@ -1077,38 +1474,599 @@ pub fn delete_navigation_paths(paths: &NavigationPaths) -> Result<()> {
     })
 }
 
-fn map_data<T>(mut stmt: Statement<'_>, params: impl Params) -> Result<Vec<T>>
+pub fn export_bundle() -> Result<Bundle> {
+    let mut settings = query_settings();
+    settings.id = None;
+    settings.discord_bot_access_token.clear();
+    settings.notifications.discord_webhook_url.clear();
+
+    Ok(Bundle {
+        version: BUNDLE_VERSION,
+        characters: bundle_entries(query_characters()?),
+        minimaps: bundle_entries(query_minimaps()?),
+        navigation_paths: bundle_entries(query_navigation_paths()?),
+        settings: Some(settings),
+    })
+}
+
+pub fn import_bundle(bundle: Bundle) -> Result<()> {
+    if bundle.version > BUNDLE_VERSION {
+        bail!(
+            "bundle version {} is newer than the supported version {BUNDLE_VERSION}",
+            bundle.version
+        );
+    }
+
+    let imported = {
+        let mut conn = DATABASE.lock().unwrap();
+        let tx = conn.transaction()?;
+        let imported = import_bundle_with(&tx, bundle)?;
+        tx.commit()?;
+        imported
+    };
+    if !imported.navigation_paths.is_empty() {
+        let _ = EVENT.send(DatabaseEvent::NavigationPathsUpdated);
+    }
+    for minimap in imported.minimaps {
+        let _ = EVENT.send(DatabaseEvent::MinimapUpdated(minimap));
+    }
+    for character in imported.characters {
+        let _ = EVENT.send(DatabaseEvent::CharacterUpdated(character));
+    }
+    if let Some(settings) = imported.settings {
+        let _ = EVENT.send(DatabaseEvent::SettingsUpdated(settings));
+    }
+    Ok(())
+}
+
+fn bundle_entries<T: Identifiable>(values: Vec<T>) -> Vec<BundleEntry<T>> {
+    values
+        .into_iter()
+        .filter_map(|value| {
+            Some(BundleEntry {
+                id: value.id()?,
+                data: value,
+            })
+        })
+        .collect()
+}
+
+/// Inserts every entry of `bundle` as a new row and remaps navigation paths and minimap
+/// references.
+///
+/// References to navigation paths or minimaps not included in `bundle` are cleared. Imported
+/// settings replace the current settings but keep the current secrets.
+fn import_bundle_with(conn: &Connection, bundle: Bundle) -> Result<ImportedBundle> {
+    let mut imported = ImportedBundle::default();
+    let mut paths_ids = HashMap::<i64, i64>::new();
+
+    for entry in bundle.navigation_paths {
+        let mut paths = entry.data;
+        paths.id = None;
+        upsert_to_table_with(conn, NAVIGATION_PATHS, &mut paths)?;
+        paths_ids.insert(entry.id, paths.id.expect("inserted"));
+        imported.navigation_paths.push(paths);
+    }
+
+    let remap = |id_index: Option<(i64, usize)>| {
+        id_index.and_then(|(id, index)| Some((*paths_ids.get(&id)?, index)))
+    };
+    for paths in imported.navigation_paths.iter_mut() {
+        for point in paths
+            .paths
+            .iter_mut()
+            .flat_map(|path| path.points.iter_mut())
+        {
+            point.next_paths_id_index = remap(point.next_paths_id_index);
+        }
+        upsert_to_table_with(conn, NAVIGATION_PATHS, paths)?;
+    }
+
+    let mut minimap_ids = HashMap::<i64, i64>::new();
+    for entry in bundle.minimaps {
+        let mut minimap = entry.data;
+        minimap.id = None;
+        minimap.paths_id_index = remap(minimap.paths_id_index);
+        upsert_to_table_with(conn, MAPS, &mut minimap)?;
+        insert_revision(conn, MAPS, &minimap)?;
+        minimap_ids.insert(entry.id, minimap.id.expect("inserted"));
+        imported.minimaps.push(minimap);
+    }
+
+    for entry in bundle.characters {
+        let mut character = entry.data;
+        character.id = None;
+        character.minimap_id_preset = character
+            .minimap_id_preset
+            .and_then(|(id, preset)| Some((*minimap_ids.get(&id)?, preset)));
+        dedup_skill_ids(&mut character);
+        upsert_to_table_with(conn, CHARACTERS, &mut character)?;
+        insert_revision(conn, CHARACTERS, &character)?;
+        imported.characters.push(character);
+    }
+
+    if let Some(mut settings) = bundle.settings {
+        let current = query_from_table_with::<Settings>(conn, SETTINGS)?
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+        settings.id = current.id;
+        settings.discord_bot_access_token = current.discord_bot_access_token;
+        settings.notifications.discord_webhook_url = current.notifications.discord_webhook_url;
+        upsert_to_table_with(conn, SETTINGS, &mut settings)?;
+        imported.settings = Some(settings);
+    }
+
+    Ok(imported)
+}
+
+pub fn current_profile() -> Option<String> {
+    DATABASE.lock().unwrap().profile.clone()
+}
+
+pub fn query_profiles() -> Result<Vec<String>> {
+    let dir = profile_path(None).with_file_name(PROFILES_DIR);
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut profiles = fs::read_dir(dir)?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "db" {
+                return None;
+            }
+            path.file_stem()?.to_str().map(|name| name.to_string())
+        })
+        .filter(|name| is_valid_profile_name(name))
+        .collect::<Vec<_>>();
+    profiles.sort();
+    Ok(profiles)
+}
+
+/// Switches the database to `profile` or the default database if `None`.
+///
+/// The profile database is created if it does not exist yet.
+pub fn switch_profile(profile: Option<String>) -> Result<()> {
+    if let Some(name) = profile.as_deref()
+        && !is_valid_profile_name(name)
+    {
+        bail!("invalid profile name {name}");
+    }
+
+    {
+        let mut database = DATABASE.lock().unwrap();
+        let conn = open_connection(profile.as_deref())?;
+        database.conn = OnceCell::from(conn);
+        database.profile = profile;
+    }
+
+    let _ = EVENT.send(DatabaseEvent::ProfileSwitched(query_settings()));
+    Ok(())
+}
+
+pub fn delete_profile(profile: &str) -> Result<()> {
+    if !is_valid_profile_name(profile) {
+        bail!("invalid profile name {profile}");
+    }
+    if current_profile().as_deref() == Some(profile) {
+        bail!("cannot delete the currently opened profile");
+    }
+
+    fs::remove_file(profile_path(Some(profile)))?;
+    Ok(())
+}
+
+fn is_valid_profile_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | ' '))
+}
+
+/// Finds the value of `flag` in the command line arguments or falls back to the `var`
+/// environment variable.
+///
+/// Both `--flag value` and `--flag=value` forms are accepted.
+fn arg_or_env(flag: &str, var: &str) -> Option<String> {
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == flag {
+            return args.next();
+        }
+        if let Some(value) = arg
+            .strip_prefix(flag)
+            .and_then(|value| value.strip_prefix('='))
+        {
+            return Some(value.to_string());
+        }
+    }
+
+    env::var(var).ok().filter(|value| !value.is_empty())
+}
+
+/// Resolves the database path of `profile`.
+///
+/// The default database is located next to the executable unless overridden. Profile databases
+/// are located in a sibling `profiles` directory of the default database.
+fn profile_path(profile: Option<&str>) -> PathBuf {
+    let path = arg_or_env(DATABASE_FLAG, DATABASE_ENV)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            env::current_exe()
+                .unwrap()
+                .parent()
+                .unwrap()
+                .join(DATABASE_FILE_NAME)
+        });
+
+    match profile {
+        Some(profile) => path
+            .with_file_name(PROFILES_DIR)
+            .join(format!("{profile}.db")),
+        None => path,
+    }
+}
+
+fn open_connection(profile: Option<&str>) -> Result<Connection> {
+    let path = profile_path(profile);
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)?;
+    }
+
+    info!(target: "database", "opening database at {}", path.display());
+    let mut conn = Connection::open(path)?;
+    initialize(&mut conn)?;
+    Ok(conn)
+}
+
+fn initialize(conn: &mut Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS maps (
+            id INTEGER PRIMARY KEY,
+            data TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS navigation_paths (
+            id INTEGER PRIMARY KEY,
+            data TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS characters (
+            id INTEGER PRIMARY KEY,
+            data TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS settings (
+            id INTEGER PRIMARY KEY,
+            data TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS seeds (
+            id INTEGER PRIMARY KEY,
+            data TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS schema_version (
+            id INTEGER PRIMARY KEY,
+            version INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS revisions (
+            id INTEGER PRIMARY KEY,
+            table_name TEXT NOT NULL,
+            data_id INTEGER NOT NULL,
+            timestamp_millis INTEGER NOT NULL,
+            data TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS revisions_table_name_data_id
+            ON revisions (table_name, data_id);
+        CREATE TABLE IF NOT EXISTS quarantine (
+            id INTEGER PRIMARY KEY,
+            table_name TEXT NOT NULL,
+            data_id INTEGER NOT NULL,
+            data TEXT NOT NULL,
+            error TEXT NOT NULL,
+            timestamp_millis INTEGER NOT NULL
+        );
+        "#,
+    )?;
+    migrate(conn)
+}
+
+/// Runs all pending migrations in a single transaction.
+///
+/// Refuses to touch a database written by a newer binary because the older shape cannot be
+/// guaranteed to round-trip without losing fields.
+fn migrate(conn: &mut Connection) -> Result<()> {
+    let tx = conn.transaction()?;
+    let version = tx
+        .query_row(
+            &format!("SELECT version FROM {SCHEMA_VERSION_TABLE} WHERE id = 1;"),
+            [],
+            |row| row.get::<_, u32>(0),
+        )
+        .optional()?
+        .unwrap_or(0);
+    if version > SCHEMA_VERSION {
+        bail!(
+            "database schema version {version} is newer than the supported version {SCHEMA_VERSION}"
+        );
+    }
+    if version == SCHEMA_VERSION {
+        return Ok(());
+    }
+
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        info!(target: "database", "migrating schema from version {from} to {}", from + 1);
+        migration(&tx)?;
+    }
+    tx.execute(
+        &format!(
+            "INSERT INTO {SCHEMA_VERSION_TABLE} (id, version) VALUES (1, ?1) ON CONFLICT (id) DO UPDATE SET version = ?1;"
+        ),
+        [SCHEMA_VERSION],
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Rewrites the JSON of every row in `table` with `migration`.
+///
+/// Rows that are not valid JSON are left untouched.
+fn migrate_rows(
+    tx: &Transaction<'_>,
+    table: &str,
+    migration: impl Fn(&mut serde_json::Map<String, Value>),
+) -> Result<()> {
+    let rows = tx
+        .prepare(&format!("SELECT id, data FROM {table};"))?
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let stmt = format!("UPDATE {table} SET data = ?2 WHERE id = ?1;");
+
+    for (id, data) in rows {
+        let Ok(Value::Object(mut object)) = serde_json::from_str::<Value>(&data) else {
+            continue;
+        };
+        migration(&mut object);
+        tx.execute(&stmt, (id, serde_json::to_string(&object)?))?;
+    }
+    Ok(())
+}
+
+/// Replaces `field` with the default value of `T` if it exists but no longer parses as `T`.
+fn reset_field_if_invalid<T>(object: &mut serde_json::Map<String, Value>, field: &str)
 where
-    T: DeserializeOwned + Identifiable + Default,
+    T: DeserializeOwned + Serialize + Default,
 {
-    Ok(stmt
-        .query_map::<T, _, _>(params, |row| {
-            let id = row.get::<_, i64>(0).unwrap();
-            let data = row.get::<_, String>(1).unwrap();
-            let mut value = serde_json::from_str::<'_, T>(data.as_str()).unwrap_or_default();
-            value.set_id(id);
-            Ok(value)
-        })?
-        .filter_map(|c| c.ok())
-        .collect::<Vec<_>>())
+    if let Some(value) = object.get_mut(field)
+        && T::deserialize(&*value).is_err()
+    {
+        *value = serde_json::to_value(T::default()).expect("serializable");
+    }
+}
+
+/// Baseline migration for databases created before schema versioning.
+///
+/// Minimap actions were a flat list before presets, so they are moved into a single preset
+/// instead of being dropped. Fields that used to be silently defaulted on read are rewritten to
+/// the default they were read as.
+fn migrate_v0_to_v1(tx: &Transaction<'_>) -> Result<()> {
+    migrate_rows(tx, MAPS, |minimap| {
+        if let Some(actions) = minimap.get_mut("actions")
+            && actions.is_array()
+        {
+            let mut presets = serde_json::Map::new();
+            presets.insert(LEGACY_PRESET.to_string(), actions.take());
+            *actions = Value::Object(presets);
+        }
+        reset_field_if_invalid::<RotationMode>(minimap, "rotation_mode");
+    })?;
+    migrate_rows(tx, CHARACTERS, |character| {
+        reset_field_if_invalid::<EliteBossBehavior>(character, "elite_boss_behavior");
+    })?;
+    migrate_rows(tx, SETTINGS, |settings| {
+        reset_field_if_invalid::<CycleRunStopMode>(settings, "cycle_run_stop");
+    })
+}
+
+/// Adds the EXP sessions table.
+fn migrate_v1_to_v2(tx: &Transaction<'_>) -> Result<()> {
+    tx.execute_batch(&format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS {EXP_SESSIONS} (
+            id INTEGER PRIMARY KEY,
+            character_id INTEGER NOT NULL,
+            minimap_id INTEGER NOT NULL,
+            preset TEXT,
+            start_millis INTEGER NOT NULL,
+            end_millis INTEGER NOT NULL,
+            gained REAL NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS {EXP_SESSIONS}_character_id_minimap_id
+            ON {EXP_SESSIONS} (character_id, minimap_id);
+        "#
+    ))?;
+    Ok(())
+}
+
+/// Gives a new id to each skill of `character` having the same id as a skill before it.
+///
+/// Skills without ids (e.g. in a hand-edited bundle) all default to `0`. The
+/// [`Character::next_skill_id`] is also moved past every id so newly registered skills never get
+/// an id already in use.
+fn dedup_skill_ids(character: &mut Character) {
+    let mut next_id = character
+        .skills
+        .iter()
+        .map(|skill| skill.id + 1)
+        .max()
+        .unwrap_or_default()
+        .max(character.next_skill_id);
+    let mut ids = HashSet::new();
+    for skill in &mut character.skills {
+        if !ids.insert(skill.id) {
+            skill.id = next_id;
+            ids.insert(next_id);
+            next_id += 1;
+        }
+    }
+    character.next_skill_id = next_id;
 }
 
 fn query_from_table<T>(table: &str) -> Result<Vec<T>>
 where
-    T: DeserializeOwned + Identifiable + Default,
+    T: DeserializeOwned + Identifiable,
 {
-    let conn = CONNECTION.lock().unwrap();
-    let stmt = format!("SELECT id, data FROM {table};");
-    let stmt = conn.prepare(&stmt).unwrap();
-    map_data(stmt, [])
+    query_from_table_with(&DATABASE.lock().unwrap(), table)
+}
+
+/// Queries and deserializes all rows in `table`.
+///
+/// Rows that fail to deserialize are moved to the quarantine table instead of being returned as
+/// default values.
+fn query_from_table_with<T>(conn: &Connection, table: &str) -> Result<Vec<T>>
+where
+    T: DeserializeOwned + Identifiable,
+{
+    let rows = conn
+        .prepare(&format!("SELECT id, data FROM {table};"))?
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut values = Vec::with_capacity(rows.len());
+
+    for (id, data) in rows {
+        match serde_json::from_str::<T>(&data) {
+            Ok(mut value) => {
+                value.set_id(id);
+                values.push(value);
+            }
+            Err(err) => {
+                let row = quarantine_row(conn, table, id, data, err.to_string())?;
+                let _ = EVENT.send(DatabaseEvent::RowQuarantined(row));
+            }
+        }
+    }
+
+    Ok(values)
+}
+
+fn quarantine_row(
+    conn: &Connection,
+    table: &str,
+    data_id: i64,
+    data: String,
+    error: String,
+) -> Result<QuarantinedRow> {
+    error!(target: "database", "quarantining row {data_id} of {table}: {error}");
+    let timestamp_millis = now_millis();
+    // Inserts before deleting so the row is never lost
+    conn.execute(
+        &format!(
+            "INSERT INTO {QUARANTINE} (table_name, data_id, data, error, timestamp_millis) VALUES (?1, ?2, ?3, ?4, ?5);"
+        ),
+        (table, data_id, &data, &error, timestamp_millis),
+    )?;
+    let id = conn.last_insert_rowid();
+    conn.execute(&format!("DELETE FROM {table} WHERE id = ?1;"), [data_id])?;
+
+    Ok(QuarantinedRow {
+        id,
+        table: table.to_string(),
+        data_id,
+        name: quarantined_row_name(&data),
+        data,
+        error,
+        timestamp_millis,
+    })
+}
+
+fn quarantined_row_name(data: &str) -> Option<String> {
+    serde_json::from_str::<Value>(data)
+        .ok()?
+        .get("name")?
+        .as_str()
+        .map(|name| name.to_string())
+}
+
+fn query_exp_sessions_with(conn: &Connection) -> Result<Vec<ExpSession>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, character_id, minimap_id, preset, start_millis, end_millis, gained FROM {EXP_SESSIONS} ORDER BY start_millis DESC, id DESC;"
+    ))?;
+    let sessions = stmt
+        .query_map([], |row| {
+            Ok(ExpSession {
+                id: row.get(0)?,
+                character_id: row.get(1)?,
+                minimap_id: row.get(2)?,
+                preset: row.get(3)?,
+                start_millis: row.get(4)?,
+                end_millis: row.get(5)?,
+                gained: row.get(6)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(sessions)
+}
+
+fn upsert_exp_session_with(conn: &Connection, session: &mut ExpSession) -> Result<()> {
+    let params = (
+        session.character_id,
+        session.minimap_id,
+        session.preset.clone(),
+        session.start_millis,
+        session.end_millis,
+        session.gained,
+    );
+    match session.id {
+        Some(id) => {
+            let updated = conn.execute(
+                &format!(
+                    "UPDATE {EXP_SESSIONS} SET character_id = ?1, minimap_id = ?2, preset = ?3, start_millis = ?4, end_millis = ?5, gained = ?6 WHERE id = ?7;"
+                ),
+                (
+                    params.0, params.1, params.2, params.3, params.4, params.5, id,
+                ),
+            )?;
+            if updated == 0 {
+                bail!("no row was updated")
+            }
+        }
+        None => {
+            conn.execute(
+                &format!(
+                    "INSERT INTO {EXP_SESSIONS} (character_id, minimap_id, preset, start_millis, end_millis, gained) VALUES (?1, ?2, ?3, ?4, ?5, ?6);"
+                ),
+                params,
+            )?;
+            session.id = Some(conn.last_insert_rowid());
+        }
+    }
+    Ok(())
+}
+
+#[inline]
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64
 }
 
 fn upsert_to_table<T>(table: &str, data: &mut T) -> Result<()>
+where
+    T: Serialize + Identifiable,
+{
+    upsert_to_table_with(&DATABASE.lock().unwrap(), table, data)
+}
+
+fn upsert_to_table_with<T>(conn: &Connection, table: &str, data: &mut T) -> Result<()>
 where
     T: Serialize + Identifiable,
 {
     let json = serde_json::to_string(&data).unwrap();
-    let conn = CONNECTION.lock().unwrap();
     let stmt = format!(
         "INSERT INTO {table} (id, data) VALUES (?1, ?2) ON CONFLICT (id) DO UPDATE SET data = ?2;",
     );
@@ -1131,10 +2089,124 @@ where
     }
 }
 
+fn upsert_to_table_with_revision<T>(table: &str, data: &mut T) -> Result<()>
+where
+    T: Serialize + Identifiable,
+{
+    let mut conn = DATABASE.lock().unwrap();
+    let tx = conn.transaction()?;
+    upsert_to_table_with(&tx, table, data)?;
+    insert_revision(&tx, table, data)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Records `data` as the newest revision and prunes the oldest ones.
+///
+/// Does nothing if `data` is the same as the newest revision.
+fn insert_revision<T>(conn: &Connection, table: &str, data: &T) -> Result<()>
+where
+    T: Serialize + Identifiable,
+{
+    let id = data.id().expect("valid id if upserted");
+    let json = serde_json::to_string(data)?;
+    let newest = conn
+        .query_row(
+            &format!(
+                "SELECT data FROM {REVISIONS} WHERE table_name = ?1 AND data_id = ?2 ORDER BY id DESC LIMIT 1;"
+            ),
+            (table, id),
+            |row| row.get::<_, String>(0),
+        )
+        .optional()?;
+    if newest.as_ref() == Some(&json) {
+        return Ok(());
+    }
+
+    let timestamp_millis = now_millis();
+    conn.execute(
+        &format!(
+            "INSERT INTO {REVISIONS} (table_name, data_id, timestamp_millis, data) VALUES (?1, ?2, ?3, ?4);"
+        ),
+        (table, id, timestamp_millis, &json),
+    )?;
+    conn.execute(
+        &format!(
+            "DELETE FROM {REVISIONS} WHERE table_name = ?1 AND data_id = ?2 AND id NOT IN (
+                SELECT id FROM {REVISIONS} WHERE table_name = ?1 AND data_id = ?2 ORDER BY id DESC LIMIT ?3
+            );"
+        ),
+        (table, id, MAX_REVISIONS_PER_DATA),
+    )?;
+    Ok(())
+}
+
+fn delete_revisions(table: &str, data_id: i64) -> Result<()> {
+    let conn = DATABASE.lock().unwrap();
+    conn.execute(
+        &format!("DELETE FROM {REVISIONS} WHERE table_name = ?1 AND data_id = ?2;"),
+        (table, data_id),
+    )?;
+    Ok(())
+}
+
+fn restore_revision<T>(kind: RevisionKind, id: i64) -> Result<T>
+where
+    T: DeserializeOwned + Serialize + Identifiable,
+{
+    let mut conn = DATABASE.lock().unwrap();
+    let tx = conn.transaction()?;
+    let (data_id, data) = tx.query_row(
+        &format!("SELECT data_id, data FROM {REVISIONS} WHERE id = ?1 AND table_name = ?2;"),
+        (id, kind.table()),
+        |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)),
+    )?;
+    let mut value = serde_json::from_str::<T>(&data)?;
+    value.set_id(data_id);
+    upsert_to_table_with(&tx, kind.table(), &mut value)?;
+    insert_revision(&tx, kind.table(), &value)?;
+    tx.commit()?;
+    Ok(value)
+}
+
+fn diff_values(
+    path: String,
+    from: Option<&Value>,
+    to: Option<&Value>,
+    changes: &mut Vec<RevisionChange>,
+) {
+    match (from, to) {
+        (Some(Value::Object(from)), Some(Value::Object(to))) => {
+            let mut keys = from.keys().chain(to.keys()).collect::<Vec<_>>();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+                diff_values(path, from.get(key), to.get(key), changes);
+            }
+        }
+        (Some(Value::Array(from)), Some(Value::Array(to))) => {
+            for i in 0..from.len().max(to.len()) {
+                diff_values(format!("{path}[{i}]"), from.get(i), to.get(i), changes);
+            }
+        }
+        (from, to) if from != to => changes.push(RevisionChange {
+            path,
+            from: from.map(Value::to_string),
+            to: to.map(Value::to_string),
+        }),
+        _ => (),
+    }
+}
+
 fn delete_from_table<T: Identifiable>(table: &str, data: &T) -> Result<()> {
     fn inner(table: &str, id: Option<i64>) -> Result<()> {
         if id.is_some() {
-            let conn = CONNECTION.lock().unwrap();
+            let conn = DATABASE.lock().unwrap();
             let stmt = format!("DELETE FROM {table} WHERE id = ?1;");
             let deleted = conn.execute(&stmt, [id.unwrap()])?;
 
@@ -1147,3 +2219,321 @@ fn delete_from_table<T: Identifiable>(table: &str, data: &T) -> Result<()> {
 
     inner(table, data.id())
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn connection_with_version(version: u32) -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        initialize(&mut conn).unwrap();
+        conn.execute(
+            "UPDATE schema_version SET version = ?1 WHERE id = 1;",
+            [version],
+        )
+        .unwrap();
+        conn
+    }
+
+    fn query_data(conn: &Connection, table: &str) -> Value {
+        let data = conn
+            .query_row(&format!("SELECT data FROM {table};"), [], |row| {
+                row.get::<_, String>(0)
+            })
+            .unwrap();
+        serde_json::from_str(&data).unwrap()
+    }
+
+    #[test]
+    fn initialize_sets_current_version() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        initialize(&mut conn).unwrap();
+
+        let version = conn
+            .query_row(
+                "SELECT version FROM schema_version WHERE id = 1;",
+                [],
+                |row| row.get::<_, u32>(0),
+            )
+            .unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn migrate_refuses_newer_version() {
+        let mut conn = connection_with_version(SCHEMA_VERSION + 1);
+
+        assert!(migrate(&mut conn).is_err());
+    }
+
+    #[test]
+    fn migrate_v0_to_v1_moves_actions_into_preset() {
+        let mut conn = connection_with_version(0);
+        let actions = json!([{ "Move": {
+            "position": { "x": 1, "y": 2, "x_random_range": 0, "allow_adjusting": false },
+            "condition": "Any",
+            "wait_after_move_millis": 0
+        }}]);
+        conn.execute(
+            "INSERT INTO maps (data) VALUES (?1);",
+            [
+                json!({ "name": "map", "actions": actions, "rotation_mode": "Removed" })
+                    .to_string(),
+            ],
+        )
+        .unwrap();
+
+        migrate(&mut conn).unwrap();
+
+        let minimap = query_data(&conn, MAPS);
+        assert_eq!(minimap["actions"], json!({ LEGACY_PRESET: actions }));
+        assert_eq!(minimap["rotation_mode"], json!(RotationMode::default()));
+    }
+
+    #[test]
+    fn migrate_v0_to_v1_resets_invalid_settings_fields() {
+        let mut conn = connection_with_version(0);
+        conn.execute(
+            "INSERT INTO settings (data) VALUES (?1);",
+            [json!({ "cycle_run_stop": true }).to_string()],
+        )
+        .unwrap();
+
+        migrate(&mut conn).unwrap();
+
+        assert_eq!(
+            query_data(&conn, SETTINGS)["cycle_run_stop"],
+            json!(CycleRunStopMode::default())
+        );
+    }
+
+    #[test]
+    fn import_bundle_remaps_navigation_paths_ids() {
+        let conn = connection_with_version(SCHEMA_VERSION);
+        // Occupies the ids used in the bundle so the remapping is observable
+        upsert_to_table_with(&conn, NAVIGATION_PATHS, &mut NavigationPaths::default()).unwrap();
+        let point = |next_paths_id_index| NavigationPoint {
+            next_paths_id_index,
+            ..NavigationPoint::default()
+        };
+        let bundle = Bundle {
+            version: BUNDLE_VERSION,
+            minimaps: vec![BundleEntry {
+                id: 1,
+                data: Minimap {
+                    paths_id_index: Some((1, 0)),
+                    ..Minimap::default()
+                },
+            }],
+            navigation_paths: vec![BundleEntry {
+                id: 1,
+                data: NavigationPaths {
+                    paths: vec![NavigationPath {
+                        points: vec![point(Some((1, 0))), point(Some((5, 0)))],
+                        ..NavigationPath::default()
+                    }],
+                    ..NavigationPaths::default()
+                },
+            }],
+            ..Bundle::default()
+        };
+
+        let imported = import_bundle_with(&conn, bundle).unwrap();
+
+        let paths_id = imported.navigation_paths[0].id.unwrap();
+        assert_ne!(paths_id, 1);
+        assert_eq!(imported.minimaps[0].paths_id_index, Some((paths_id, 0)));
+        assert_eq!(
+            imported.navigation_paths[0].paths[0]
+                .points
+                .iter()
+                .map(|point| point.next_paths_id_index)
+                .collect::<Vec<_>>(),
+            vec![Some((paths_id, 0)), None]
+        );
+    }
+
+    #[test]
+    fn import_bundle_keeps_current_secrets() {
+        let conn = connection_with_version(SCHEMA_VERSION);
+        let mut current = Settings {
+            discord_bot_access_token: "token".to_string(),
+            ..Settings::default()
+        };
+        upsert_to_table_with(&conn, SETTINGS, &mut current).unwrap();
+        let bundle = Bundle {
+            version: BUNDLE_VERSION,
+            settings: Some(Settings {
+                enable_panic_mode: true,
+                ..Settings::default()
+            }),
+            ..Bundle::default()
+        };
+
+        let settings = import_bundle_with(&conn, bundle).unwrap().settings.unwrap();
+
+        assert_eq!(settings.id, current.id);
+        assert_eq!(settings.discord_bot_access_token, "token");
+        assert!(settings.enable_panic_mode);
+    }
+
+    #[test]
+    fn is_valid_profile_name_rejects_paths() {
+        assert!(is_valid_profile_name("main account"));
+        assert!(is_valid_profile_name("test-1_2"));
+        assert!(!is_valid_profile_name(""));
+        assert!(!is_valid_profile_name("../local"));
+        assert!(!is_valid_profile_name("a/b"));
+    }
+
+    #[test]
+    fn insert_revision_skips_unchanged_and_prunes_oldest() {
+        let conn = connection_with_version(SCHEMA_VERSION);
+        let mut minimap = Minimap::default();
+        upsert_to_table_with(&conn, MAPS, &mut minimap).unwrap();
+
+        insert_revision(&conn, MAPS, &minimap).unwrap();
+        insert_revision(&conn, MAPS, &minimap).unwrap();
+        for width in 0..MAX_REVISIONS_PER_DATA as i32 + 5 {
+            minimap.width = width;
+            insert_revision(&conn, MAPS, &minimap).unwrap();
+        }
+
+        let count = conn
+            .query_row("SELECT COUNT(*) FROM revisions;", [], |row| {
+                row.get::<_, u32>(0)
+            })
+            .unwrap();
+        assert_eq!(count, MAX_REVISIONS_PER_DATA);
+    }
+
+    #[test]
+    fn migrate_v1_to_v2_creates_exp_sessions() {
+        let mut conn = connection_with_version(1);
+        conn.execute_batch("DROP TABLE exp_sessions;").unwrap();
+
+        migrate(&mut conn).unwrap();
+
+        assert_eq!(query_exp_sessions_with(&conn).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn dedup_skill_ids_keeps_first_id() {
+        let skill = |id| SkillIcon {
+            id,
+            ..SkillIcon::default()
+        };
+        let mut character = Character {
+            skills: vec![skill(0), skill(0), skill(3), skill(0)],
+            next_skill_id: 2,
+            ..Character::default()
+        };
+
+        dedup_skill_ids(&mut character);
+
+        assert_eq!(
+            character
+                .skills
+                .iter()
+                .map(|skill| skill.id)
+                .collect::<Vec<_>>(),
+            vec![0, 4, 3, 5]
+        );
+        assert_eq!(character.next_skill_id, 6);
+    }
+
+    #[test]
+    fn dedup_skill_ids_keeps_next_skill_id_of_deleted_skills() {
+        let mut character = Character {
+            skills: vec![SkillIcon::default()],
+            next_skill_id: 7,
+            ..Character::default()
+        };
+
+        dedup_skill_ids(&mut character);
+
+        assert_eq!(character.next_skill_id, 7);
+    }
+
+    #[test]
+    fn upsert_exp_session_inserts_then_updates() {
+        let conn = connection_with_version(SCHEMA_VERSION);
+        let mut session = ExpSession {
+            character_id: 1,
+            minimap_id: 2,
+            preset: Some("Preset".to_string()),
+            start_millis: 0,
+            end_millis: 1_800_000,
+            gained: 1.5,
+            ..ExpSession::default()
+        };
+
+        upsert_exp_session_with(&conn, &mut session).unwrap();
+        assert!(session.id.is_some());
+        session.end_millis = 3_600_000;
+        session.gained = 3.0;
+        upsert_exp_session_with(&conn, &mut session).unwrap();
+
+        let sessions = query_exp_sessions_with(&conn).unwrap();
+        assert_eq!(sessions, vec![session]);
+        assert_eq!(sessions[0].exp_per_hour(), Some(3.0));
+    }
+
+    #[test]
+    fn diff_values_reports_nested_changes() {
+        let from = json!({ "name": "a", "platforms": [{ "y": 1 }], "removed": true });
+        let to = json!({ "name": "a", "platforms": [{ "y": 2 }, { "y": 3 }] });
+        let mut changes = vec![];
+
+        diff_values(String::new(), Some(&from), Some(&to), &mut changes);
+
+        assert_eq!(
+            changes,
+            vec![
+                RevisionChange {
+                    path: "platforms[0].y".to_string(),
+                    from: Some("1".to_string()),
+                    to: Some("2".to_string()),
+                },
+                RevisionChange {
+                    path: "platforms[1]".to_string(),
+                    from: None,
+                    to: Some(r#"{"y":3}"#.to_string()),
+                },
+                RevisionChange {
+                    path: "removed".to_string(),
+                    from: Some("true".to_string()),
+                    to: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn query_from_table_with_quarantines_invalid_row() {
+        let conn = connection_with_version(SCHEMA_VERSION);
+        conn.execute(
+            "INSERT INTO maps (id, data) VALUES (3, ?1);",
+            [json!({ "name": "broken" }).to_string()],
+        )
+        .unwrap();
+
+        let minimaps = query_from_table_with::<Minimap>(&conn, MAPS).unwrap();
+
+        assert!(minimaps.is_empty());
+        let (data_id, data) = conn
+            .query_row("SELECT data_id, data FROM quarantine;", [], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })
+            .unwrap();
+        assert_eq!(data_id, 3);
+        assert_eq!(quarantined_row_name(&data), Some("broken".to_string()));
+        assert_eq!(
+            conn.query_row("SELECT COUNT(*) FROM maps;", [], |row| row.get::<_, u32>(0))
+                .unwrap(),
+            0
+        );
+    }
+}
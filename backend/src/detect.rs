@@ -3,6 +3,7 @@ use std::{
     collections::HashMap,
     env,
     fmt::Debug,
+    ops::RangeInclusive,
     sync::{
         Arc, LazyLock, Mutex,
         atomic::{AtomicBool, Ordering},
@@ -17,11 +18,11 @@ use mockall::mock;
 use opencv::{
     boxed_ref::BoxedRef,
     core::{
-        BORDER_CONSTANT, CMP_EQ, CMP_GT, CV_8U, CV_32FC3, CV_32S, Mat, MatExprTraitConst, MatTrait,
-        MatTraitConst, MatTraitConstManual, ModifyInplace, Point, Range, Rect, Scalar, Size,
-        ToInputArray, Vec4b, Vector, add, add_weighted_def, bitwise_and_def, compare,
-        copy_make_border, divide2_def, extract_channel, find_non_zero, min_max_loc, no_array,
-        subtract_def, transpose_nd,
+        _InputArrayTraitConst, BORDER_CONSTANT, CMP_EQ, CMP_GT, CV_8U, CV_32FC3, CV_32S, Mat,
+        MatExprTraitConst, MatTrait, MatTraitConst, MatTraitConstManual, ModifyInplace, Point,
+        Range, Rect, Scalar, Size, ToInputArray, Vec4b, Vector, add, add_weighted_def,
+        bitwise_and_def, compare, copy_make_border, count_non_zero, divide2_def, extract_channel,
+        find_non_zero, min_max_loc, no_array, subtract_def, transpose_nd,
     },
     dnn::{
         ModelTrait, TextRecognitionModel, TextRecognitionModelTrait,
@@ -31,14 +32,14 @@ use opencv::{
     imgproc::{
         CC_STAT_AREA, CC_STAT_HEIGHT, CC_STAT_LEFT, CC_STAT_TOP, CC_STAT_WIDTH,
         CHAIN_APPROX_SIMPLE, COLOR_BGR2HSV_FULL, COLOR_BGRA2BGR, COLOR_BGRA2GRAY, COLOR_BGRA2RGB,
-        INTER_CUBIC, INTER_LINEAR, MORPH_RECT, RETR_EXTERNAL, THRESH_BINARY, TM_CCOEFF_NORMED,
-        TM_SQDIFF_NORMED, bounding_rect, connected_components_with_stats, contour_area,
-        cvt_color_def, dilate_def, find_contours_def, get_structuring_element_def, match_template,
-        min_area_rect, min_enclosing_triangle, resize, threshold,
+        INTER_AREA, INTER_CUBIC, INTER_LINEAR, INTER_NEAREST, MORPH_RECT, RETR_EXTERNAL,
+        THRESH_BINARY, THRESH_BINARY_INV, TM_CCOEFF_NORMED, TM_SQDIFF_NORMED, bounding_rect,
+        connected_components_with_stats, contour_area, cvt_color_def, dilate_def,
+        find_contours_def, get_structuring_element_def, match_template, min_area_rect,
+        min_enclosing_triangle, resize, threshold,
     },
 };
 use ort::{
-    execution_providers::CUDAExecutionProvider,
     session::{Session, SessionInputValue, SessionOutputs},
     value::TensorRef,
 };
@@ -46,7 +47,12 @@ use ort::{
 use crate::bridge::KeyKind;
 #[cfg(debug_assertions)]
 use crate::debug::{debug_mat, debug_spinning_arrows};
-use crate::{array::Array, buff::BuffKind, mat::OwnedMat};
+use crate::{
+    array::Array,
+    buff::BuffKind,
+    mat::OwnedMat,
+    models::{ModelKind, build_session, model_bytes},
+};
 
 const MAX_ARROWS: usize = 4;
 const MAX_SPIN_ARROWS: usize = 2; // PRAY
@@ -142,6 +148,9 @@ pub trait Detector: 'static + Send + DynClone + Debug {
     /// Detects the minimap name rectangle.
     fn detect_minimap_name(&self, minimap: Rect) -> Result<Rect>;
 
+    /// Detects the minimap name as text by recognizing the minimap name rectangle.
+    fn detect_minimap_name_text(&self, minimap: Rect) -> Result<String>;
+
     /// Detects whether the given `minimap_snapshot` and `minimap_name_snapshot` matches the one
     /// cropped by `minimap_name_bbox` and `minimap_bbox` rectangles.
     fn detect_minimap_match(
@@ -171,6 +180,12 @@ pub trait Detector: 'static + Send + DynClone + Debug {
     /// Detects whether a player of `kind` is in the minimap.
     fn detect_player_kind(&self, minimap: Rect, kind: OtherPlayerKind) -> bool;
 
+    /// Detects the player character on the whole frame.
+    ///
+    /// The character is found above the name tag closest to the frame middle. Returns `Rect`
+    /// relative to the frame.
+    fn detect_player_on_screen(&self) -> Result<Rect>;
+
     /// Detects whether the player is dead.
     fn detect_player_is_dead(&self) -> bool;
 
@@ -190,9 +205,28 @@ pub trait Detector: 'static + Send + DynClone + Debug {
     /// Detects the player current health and max health.
     fn detect_player_health(&self, current_bar: Rect, max_bar: Rect) -> Result<(u32, u32)>;
 
+    /// Detects the player mana bar.
+    ///
+    /// This is the blue mana bar right below `health_bar`.
+    fn detect_player_mana_bar(&self, health_bar: Rect) -> Result<Rect>;
+
+    /// Detects the player current and max mana bars.
+    ///
+    /// These are the two smaller bars extracted from `mana_bar`.
+    fn detect_player_current_max_mana_bars(&self, mana_bar: Rect) -> Result<(Rect, Rect)>;
+
+    /// Detects the player current mana and max mana.
+    fn detect_player_mana(&self, current_bar: Rect, max_bar: Rect) -> Result<(u32, u32)>;
+
+    /// Detects the player EXP percentage from the EXP bar at the bottom of the frame.
+    fn detect_player_exp(&self) -> Result<f32>;
+
     /// Detects whether the player has a buff specified by `kind`.
     fn detect_player_buff(&self, kind: BuffKind) -> bool;
 
+    /// Detects whether the player has a user-defined buff from its grayscale `icon`.
+    fn detect_player_custom_buff(&self, icon: &Mat) -> bool;
+
     /// Detects arrows from the given RGBA `Mat` image.
     ///
     /// `calibrating` represents the previous calibrating state returned by
@@ -202,6 +236,9 @@ pub trait Detector: 'static + Send + DynClone + Debug {
     /// Detects the Erda Shower skill from the given BGRA `Mat` image.
     fn detect_erda_shower(&self) -> Result<Rect>;
 
+    /// Detects a user-registered skill from its grayscale quick slot `icon`.
+    fn detect_skill(&self, icon: &Mat) -> Result<Rect>;
+
     /// Detects familiar menu save button.
     fn detect_familiar_save_button(&self) -> Result<Rect>;
 
@@ -239,6 +276,12 @@ pub trait Detector: 'static + Send + DynClone + Debug {
 
     /// Detects whether the chat menu is opened.
     fn detect_chat_menu_opened(&self) -> bool;
+
+    /// Detects the scale to resize templates by to match the current game resolution.
+    ///
+    /// The scale is detected by matching the health bar at multiple scales so it fails while the
+    /// health bar is not visible.
+    fn detect_template_scale(&self) -> Result<f64>;
 }
 
 #[cfg(test)]
@@ -255,6 +298,7 @@ mock! {
         fn detect_elite_boss_bar(&self) -> bool;
         fn detect_minimap(&self, border_threshold: u8) -> Result<Rect>;
         fn detect_minimap_name(&self, minimap: Rect) -> Result<Rect>;
+        fn detect_minimap_name_text(&self, minimap: Rect) -> Result<String>;
         fn detect_minimap_match(
             &self,
             minimap_snapshot: &Mat,
@@ -267,17 +311,24 @@ mock! {
         fn detect_minimap_rune(&self, minimap: Rect) -> Result<Rect>;
         fn detect_player(&self, minimap: Rect) -> Result<Rect>;
         fn detect_player_kind(&self, minimap: Rect, kind: OtherPlayerKind) -> bool;
+        fn detect_player_on_screen(&self) -> Result<Rect>;
         fn detect_player_is_dead(&self) -> bool;
         fn detect_player_in_cash_shop(&self) -> bool;
         fn detect_player_health_bar(&self) -> Result<Rect>;
         fn detect_player_current_max_health_bars(&self, health_bar: Rect) -> Result<(Rect, Rect)>;
         fn detect_player_health(&self, current_bar: Rect, max_bar: Rect) -> Result<(u32, u32)>;
+        fn detect_player_mana_bar(&self, health_bar: Rect) -> Result<Rect>;
+        fn detect_player_current_max_mana_bars(&self, mana_bar: Rect) -> Result<(Rect, Rect)>;
+        fn detect_player_mana(&self, current_bar: Rect, max_bar: Rect) -> Result<(u32, u32)>;
+        fn detect_player_exp(&self) -> Result<f32>;
         fn detect_player_buff(&self, kind: BuffKind) -> bool;
+        fn detect_player_custom_buff(&self, icon: &Mat) -> bool;
         fn detect_rune_arrows<'a>(
             &self,
             calibrating: ArrowsCalibrating,
         ) -> Result<ArrowsState>;
         fn detect_erda_shower(&self) -> Result<Rect>;
+        fn detect_skill(&self, icon: &Mat) -> Result<Rect>;
         fn detect_familiar_save_button(&self) -> Result<Rect>;
         fn detect_familiar_setup_button(&self) -> Result<Rect>;
         fn detect_familiar_level_button(&self) -> Result<Rect>;
@@ -290,6 +341,7 @@ mock! {
         fn detect_familiar_essence_depleted(&self) -> bool;
         fn detect_change_channel_menu_opened(&self) -> bool;
         fn detect_chat_menu_opened(&self) -> bool;
+        fn detect_template_scale(&self) -> Result<f64>;
     }
 
     impl Debug for Detector {
@@ -303,6 +355,60 @@ mock! {
 
 type MatFn = Box<dyn FnOnce() -> Mat + Send>;
 
+type ScaleFn = Box<dyn FnOnce() -> f64 + Send>;
+
+/// The scales to try when detecting the template scale.
+///
+/// Covers resolutions from around 1280x720 up to 1920x1080 relative to the `Ideal Ratio`
+/// resolution the templates are cut from.
+const TEMPLATE_SCALES: RangeInclusive<u32> = 14..=30;
+
+/// The step between each of [`TEMPLATE_SCALES`].
+const TEMPLATE_SCALE_STEP: f64 = 0.05;
+
+/// The minimum score for a scale to be detected.
+const TEMPLATE_SCALE_THRESHOLD: f64 = 0.8;
+
+/// The scale for converting dx on the screen to dx on the minimap.
+///
+/// This and [`MINIMAP_Y_SCALE`] are approximated in 1280x720 resolution by going from one point
+/// to another point from the middle of the screen with both points visible on screen before
+/// traveling. Take the distance traveled on the minimap and divide it by half of the resolution
+/// (e.g. tralveled minimap x / 640). Whether it is correct or not, time will tell.
+const MINIMAP_X_SCALE: f32 = 0.059_375;
+
+/// The scale for converting dy on the screen to dy on the minimap.
+const MINIMAP_Y_SCALE: f32 = 0.036_111;
+
+/// The maximum grayscale value of the player name tag background.
+const NAME_TAG_BACKGROUND_THRESHOLD: f64 = 20.0;
+
+/// The minimum grayscale value of the player name tag text.
+const NAME_TAG_TEXT_THRESHOLD: f64 = 200.0;
+
+/// The minimum ratio of background pixels in the player name tag rectangle.
+const NAME_TAG_MIN_BACKGROUND_RATIO: f32 = 0.6;
+
+/// The minimum ratio of text pixels in the player name tag rectangle.
+const NAME_TAG_MIN_TEXT_RATIO: f32 = 0.05;
+
+const NAME_TAG_MIN_WIDTH: i32 = 24;
+
+const NAME_TAG_MAX_WIDTH: i32 = 160;
+
+const NAME_TAG_MIN_HEIGHT: i32 = 12;
+
+const NAME_TAG_MAX_HEIGHT: i32 = 20;
+
+/// The height of the EXP bar at the bottom of the frame.
+const EXP_BAR_HEIGHT: i32 = 16;
+
+/// The approximated width of the player character above the name tag.
+const PLAYER_ON_SCREEN_WIDTH: i32 = 40;
+
+/// The approximated height of the player character above the name tag.
+const PLAYER_ON_SCREEN_HEIGHT: i32 = 65;
+
 /// A detector that temporary caches the transformed `Mat`.
 ///
 /// It is useful when there are multiple detections in a single tick that
@@ -314,10 +420,32 @@ pub struct CachedDetector {
     mat: Arc<OwnedMat>,
     grayscale: Arc<LazyLock<Mat, MatFn>>,
     buffs_grayscale: Arc<LazyLock<Mat, MatFn>>,
+    scale: Arc<LazyLock<f64, ScaleFn>>,
 }
 
 impl CachedDetector {
+    /// Creates a detector with the template scale detected from `mat` on first use.
+    ///
+    /// Templates are matched at their original size if the template scale cannot be detected.
+    #[inline]
     pub fn new(mat: OwnedMat) -> CachedDetector {
+        Self::new_with_scale_fn(mat, |grayscale| {
+            detect_template_scale(grayscale).unwrap_or(1.0)
+        })
+    }
+
+    /// Creates a detector resizing templates by `scale` for matching.
+    ///
+    /// See [`Detector::detect_template_scale`].
+    #[inline]
+    pub fn new_with_scale(mat: OwnedMat, scale: f64) -> CachedDetector {
+        Self::new_with_scale_fn(mat, move |_| scale)
+    }
+
+    fn new_with_scale_fn(
+        mat: OwnedMat,
+        scale_fn: impl FnOnce(&Mat) -> f64 + Send + 'static,
+    ) -> CachedDetector {
         let mat = Arc::new(mat);
         let grayscale = mat.clone();
         let grayscale = Arc::new(LazyLock::<Mat, MatFn>::new(Box::new(move || {
@@ -327,12 +455,22 @@ impl CachedDetector {
         let buffs_grayscale = Arc::new(LazyLock::<Mat, MatFn>::new(Box::new(move || {
             to_buffs_region(&**buffs_grayscale).clone_pointee()
         })));
+        let scale_grayscale = grayscale.clone();
+        let scale = Arc::new(LazyLock::<f64, ScaleFn>::new(Box::new(move || {
+            scale_fn(&scale_grayscale)
+        })));
         Self {
             mat,
             grayscale,
             buffs_grayscale,
+            scale,
         }
     }
+
+    #[inline]
+    fn scale(&self) -> f64 {
+        **self.scale
+    }
 }
 
 impl Detector for CachedDetector {
@@ -345,23 +483,30 @@ impl Detector for CachedDetector {
     }
 
     fn detect_mobs(&self, minimap: Rect, bound: Rect, player: Point) -> Result<Vec<Point>> {
-        detect_mobs(&*self.mat, minimap, bound, player)
+        detect_mobs(
+            &*self.mat,
+            &**self.grayscale,
+            minimap,
+            bound,
+            player,
+            self.scale(),
+        )
     }
 
     fn detect_esc_settings(&self) -> bool {
-        detect_esc_settings(&**self.grayscale)
+        detect_esc_settings(&**self.grayscale, self.scale())
     }
 
     fn detect_esc_confirm_button(&self) -> Result<Rect> {
-        detect_esc_confirm_button(&**self.grayscale)
+        detect_esc_confirm_button(&**self.grayscale, self.scale())
     }
 
     fn detect_tomb_ok_button(&self) -> Result<Rect> {
-        detect_tomb_ok_button(&**self.grayscale)
+        detect_tomb_ok_button(&**self.grayscale, self.scale())
     }
 
     fn detect_elite_boss_bar(&self) -> bool {
-        detect_elite_boss_bar(&**self.grayscale)
+        detect_elite_boss_bar(&**self.grayscale, self.scale())
     }
 
     fn detect_minimap(&self, border_threshold: u8) -> Result<Rect> {
@@ -372,6 +517,11 @@ impl Detector for CachedDetector {
         detect_minimap_name(&**self.grayscale, minimap)
     }
 
+    fn detect_minimap_name_text(&self, minimap: Rect) -> Result<String> {
+        let name = detect_minimap_name(&**self.grayscale, minimap)?;
+        detect_minimap_name_text(&*self.mat, name)
+    }
+
     fn detect_minimap_match(
         &self,
         minimap_snapshot: &Mat,
@@ -411,24 +561,60 @@ impl Detector for CachedDetector {
         detect_player_kind(&minimap_color, kind)
     }
 
+    fn detect_player_on_screen(&self) -> Result<Rect> {
+        let size = self.mat.size()?;
+        let middle = Point::new(size.width / 2, size.height / 2);
+        detect_player_on_screen(&**self.grayscale, middle, self.scale())
+    }
+
     fn detect_player_is_dead(&self) -> bool {
-        detect_player_is_dead(&**self.grayscale)
+        detect_player_is_dead(&**self.grayscale, self.scale())
     }
 
     fn detect_player_in_cash_shop(&self) -> bool {
-        detect_player_in_cash_shop(&**self.grayscale)
+        detect_player_in_cash_shop(&**self.grayscale, self.scale())
     }
 
     fn detect_player_health_bar(&self) -> Result<Rect> {
-        detect_player_health_bar(&**self.grayscale)
+        detect_player_health_bar(&**self.grayscale, self.scale())
     }
 
     fn detect_player_current_max_health_bars(&self, health_bar: Rect) -> Result<(Rect, Rect)> {
-        detect_player_current_max_health_bars(&*self.mat, &**self.grayscale, health_bar)
+        detect_player_current_max_bars(
+            &*self.mat,
+            &**self.grayscale,
+            health_bar,
+            Some(&*HP_SHIELD),
+            &HP_SEPARATOR_TYPE_1,
+            self.scale(),
+        )
     }
 
     fn detect_player_health(&self, current_bar: Rect, max_bar: Rect) -> Result<(u32, u32)> {
-        detect_player_health(&*self.mat, current_bar, max_bar)
+        detect_player_current_max(&*self.mat, current_bar, max_bar)
+    }
+
+    fn detect_player_mana_bar(&self, health_bar: Rect) -> Result<Rect> {
+        detect_player_mana_bar(&*self.mat, &**self.grayscale, health_bar, self.scale())
+    }
+
+    fn detect_player_current_max_mana_bars(&self, mana_bar: Rect) -> Result<(Rect, Rect)> {
+        detect_player_current_max_bars(
+            &*self.mat,
+            &**self.grayscale,
+            mana_bar,
+            None,
+            &MP_SEPARATOR_TYPE_1,
+            self.scale(),
+        )
+    }
+
+    fn detect_player_mana(&self, current_bar: Rect, max_bar: Rect) -> Result<(u32, u32)> {
+        detect_player_current_max(&*self.mat, current_bar, max_bar)
+    }
+
+    fn detect_player_exp(&self) -> Result<f32> {
+        detect_player_exp(&*self.mat, self.scale())
     }
 
     fn detect_player_buff(&self, kind: BuffKind) -> bool {
@@ -452,8 +638,14 @@ impl Detector for CachedDetector {
             | BuffKind::ExtremeBluePotion
             | BuffKind::ExtremeGreenPotion
             | BuffKind::ExtremeGoldPotion => &to_bgr(&to_buffs_region(&*self.mat)),
+            BuffKind::Custom(_) => return false,
         };
-        detect_player_buff(mat, kind)
+        detect_player_buff(mat, kind, self.scale())
+    }
+
+    fn detect_player_custom_buff(&self, icon: &Mat) -> bool {
+        // User icons are cropped at the current resolution so no scaling is needed
+        detect_template(&**self.buffs_grayscale, icon, Point::default(), 0.75, 1.0).is_ok()
     }
 
     fn detect_rune_arrows(&self, calibrating: ArrowsCalibrating) -> Result<ArrowsState> {
@@ -461,69 +653,76 @@ impl Detector for CachedDetector {
     }
 
     fn detect_erda_shower(&self) -> Result<Rect> {
-        detect_erda_shower(&**self.grayscale)
+        detect_erda_shower(&**self.grayscale, self.scale())
+    }
+
+    fn detect_skill(&self, icon: &Mat) -> Result<Rect> {
+        detect_skill(&**self.grayscale, icon)
     }
 
     fn detect_familiar_save_button(&self) -> Result<Rect> {
-        detect_familiar_save_button(&to_bgr(&*self.mat))
+        detect_familiar_save_button(&to_bgr(&*self.mat), self.scale())
     }
 
     fn detect_familiar_setup_button(&self) -> Result<Rect> {
-        detect_familiar_setup_button(&to_bgr(&*self.mat))
+        detect_familiar_setup_button(&to_bgr(&*self.mat), self.scale())
     }
 
     fn detect_familiar_level_button(&self) -> Result<Rect> {
-        detect_familiar_level_button(&to_bgr(&*self.mat))
+        detect_familiar_level_button(&to_bgr(&*self.mat), self.scale())
     }
 
     fn detect_familiar_slots(&self) -> Vec<(Rect, bool)> {
-        detect_familiar_slots(&to_bgr(&*self.mat))
+        detect_familiar_slots(&to_bgr(&*self.mat), self.scale())
     }
 
     fn detect_familiar_slot_is_free(&self, slot: Rect) -> bool {
-        detect_familiar_slot_is_free(&to_bgr(&self.mat.roi(slot).unwrap()))
+        detect_familiar_slot_is_free(&to_bgr(&self.mat.roi(slot).unwrap()), self.scale())
     }
 
     fn detect_familiar_hover_level(&self) -> Result<FamiliarLevel> {
-        detect_familiar_hover_level(&to_bgr(&*self.mat))
+        detect_familiar_hover_level(&to_bgr(&*self.mat), self.scale())
     }
 
     fn detect_familiar_cards(&self) -> Vec<(Rect, FamiliarRank)> {
-        detect_familiar_cards(&to_bgr(&*self.mat))
+        detect_familiar_cards(&to_bgr(&*self.mat), self.scale())
     }
 
     fn detect_familiar_scrollbar(&self) -> Result<Rect> {
-        detect_familiar_scrollbar(&to_grayscale(&*self.mat, false))
+        detect_familiar_scrollbar(&to_grayscale(&*self.mat, false), self.scale())
     }
 
     fn detect_familiar_menu_opened(&self) -> bool {
-        detect_familiar_menu_opened(&**self.grayscale)
+        detect_familiar_menu_opened(&**self.grayscale, self.scale())
     }
 
     fn detect_familiar_essence_depleted(&self) -> bool {
-        detect_familiar_essence_depleted(&**self.buffs_grayscale)
+        detect_familiar_essence_depleted(&**self.buffs_grayscale, self.scale())
     }
 
     fn detect_change_channel_menu_opened(&self) -> bool {
-        detect_change_channel_menu_opened(&**self.grayscale)
+        detect_change_channel_menu_opened(&**self.grayscale, self.scale())
     }
 
     fn detect_chat_menu_opened(&self) -> bool {
-        detect_chat_menu_opened(&**self.grayscale)
+        detect_chat_menu_opened(&**self.grayscale, self.scale())
+    }
+
+    fn detect_template_scale(&self) -> Result<f64> {
+        detect_template_scale(&**self.grayscale)
     }
 }
 
 fn detect_mobs(
     mat: &impl MatTraitConst,
+    grayscale: &impl MatTraitConst,
     minimap: Rect,
     bound: Rect,
     player: Point,
+    scale: f64,
 ) -> Result<Vec<Point>> {
     static MOB_MODEL: LazyLock<Mutex<Session>> = LazyLock::new(|| {
-        Mutex::new(
-            build_session(include_bytes!(env!("MOB_MODEL")))
-                .expect("build mob detection session successfully"),
-        )
+        Mutex::new(build_session(ModelKind::Mob).expect("build mob detection session successfully"))
     });
 
     /// Approximates the mob coordinate on screen to mob coordinate on minimap.
@@ -540,42 +739,31 @@ fn detect_mobs(
         minimap_bbox: Rect,
         mobbing_bound: Rect,
         player: Point,
-        mat_size: Size,
+        player_on_screen: Point,
     ) -> Option<Point> {
-        // These numbers are for scaling dx/dy on the screen to dx/dy on the minimap.
-        // They are approximated in 1280x720 resolution by going from one point to another point
-        // from the middle of the screen with both points visible on screen before traveling. Take
-        // the distance traveled on the minimap and divide it by half of the resolution
-        // (e.g. tralveled minimap x / 640). Whether it is correct or not, time will tell.
-        const X_SCALE: f32 = 0.059_375;
-        const Y_SCALE: f32 = 0.036_111;
-
-        // The main idea is to calculate the offset of the detected mob from the middle of screen
-        // and use that distance as dx/dy to move the player. This assumes the player will
-        // most of the time be near or very close to the middle of the screen. This is already
-        // not accurate in the sense that the camera will have a bit of lag before
-        // it is centered again on the player. And when the player is near edges of the map,
-        // this function is just plain wrong. For better accuracy, detecting where the player is
-        // on the screen and use that as the basis is required.
-        let x_screen_mid = mat_size.width / 2;
+        // The main idea is to calculate the offset of the detected mob from the player on
+        // the screen and use that distance as dx/dy to move the player. The player on the screen
+        // is detected from its name tag and falls back to the middle of the screen, which is
+        // not accurate when the player is near edges of the map.
+        let x_player = player_on_screen.x;
         let x_mob_mid = mob_bbox.x + mob_bbox.width / 2;
-        let x_screen_delta = x_screen_mid - x_mob_mid;
-        let x_minimap_delta = (x_screen_delta as f32 * X_SCALE) as i32;
-
-        // For dy, if the whole mob bounding box is above the screen mid point, then the
-        // box top edge is used to increase the dy distance as to help the player move up. The same
-        // goes for moving down. If the bounding box overlaps with the screen mid point, the box
-        // mid point is used as to to help the player stay in place.
-        let y_screen_mid = mat_size.height / 2;
-        let y_mob = if mob_bbox.y + mob_bbox.height < y_screen_mid {
+        let x_screen_delta = x_player - x_mob_mid;
+        let x_minimap_delta = (x_screen_delta as f32 * MINIMAP_X_SCALE) as i32;
+
+        // For dy, if the whole mob bounding box is above the player, then the box top edge is
+        // used to increase the dy distance as to help the player move up. The same goes for
+        // moving down. If the bounding box overlaps with the player, the box mid point is used as
+        // to to help the player stay in place.
+        let y_player = player_on_screen.y;
+        let y_mob = if mob_bbox.y + mob_bbox.height < y_player {
             mob_bbox.y
-        } else if mob_bbox.y > y_screen_mid {
+        } else if mob_bbox.y > y_player {
             mob_bbox.y + mob_bbox.height
         } else {
             mob_bbox.y + mob_bbox.height / 2
         };
-        let y_screen_delta = y_screen_mid - y_mob;
-        let y_minimap_delta = (y_screen_delta as f32 * Y_SCALE) as i32;
+        let y_screen_delta = y_player - y_mob;
+        let y_minimap_delta = (y_screen_delta as f32 * MINIMAP_Y_SCALE) as i32;
 
         let point_x = if x_minimap_delta > 0 {
             (player.x - x_minimap_delta).max(0)
@@ -597,6 +785,13 @@ fn detect_mobs(
     }
 
     let size = mat.size().unwrap();
+    let player_on_screen = detect_player_on_screen(
+        grayscale,
+        estimate_player_on_screen(size, minimap, player),
+        scale,
+    )
+    .map(|bbox| Point::new(bbox.x + bbox.width / 2, bbox.y + bbox.height / 2))
+    .unwrap_or(Point::new(size.width / 2, size.height / 2));
     let (mat_in, w_ratio, h_ratio, left, top) = preprocess_for_yolo(mat);
     let mut model = MOB_MODEL.lock().unwrap();
     let result = model.run([to_input_value(&mat_in)]).unwrap();
@@ -606,7 +801,7 @@ fn detect_mobs(
         .map(|i| unsafe { result.at_row_unchecked::<f32>(i).unwrap() })
         .filter(|pred| pred[4] >= 0.5)
         .map(|pred| remap_from_yolo(pred, size, w_ratio, h_ratio, left, top))
-        .filter_map(|bbox| to_minimap_coordinate(bbox, minimap, bound, player, size))
+        .filter_map(|bbox| to_minimap_coordinate(bbox, minimap, bound, player, player_on_screen))
         .collect::<Vec<_>>();
     Ok(points)
 }
@@ -656,20 +851,20 @@ static ESC_SETTINGS: LazyLock<[Mat; 11]> = LazyLock::new(|| {
     ]
 });
 
-fn detect_esc_settings(mat: &impl ToInputArray) -> bool {
+fn detect_esc_settings(mat: &impl ToInputArray, scale: f64) -> bool {
     for template in &*ESC_SETTINGS {
-        if detect_template(mat, template, Point::default(), 0.75).is_ok() {
+        if detect_template(mat, template, Point::default(), 0.75, scale).is_ok() {
             return true;
         }
     }
     false
 }
 
-fn detect_esc_confirm_button(mat: &impl ToInputArray) -> Result<Rect> {
-    detect_template(mat, &ESC_SETTINGS[6], Point::default(), 0.75)
+fn detect_esc_confirm_button(mat: &impl ToInputArray, scale: f64) -> Result<Rect> {
+    detect_template(mat, &ESC_SETTINGS[6], Point::default(), 0.75, scale)
 }
 
-fn detect_tomb_ok_button(mat: &impl ToInputArray) -> Result<Rect> {
+fn detect_tomb_ok_button(mat: &impl ToInputArray, scale: f64) -> Result<Rect> {
     static TEMPLATE: LazyLock<Mat> = LazyLock::new(|| {
         imgcodecs::imdecode(
             include_bytes!(env!("TOMB_BUTTON_OK_TEMPLATE")),
@@ -678,10 +873,10 @@ fn detect_tomb_ok_button(mat: &impl ToInputArray) -> Result<Rect> {
         .unwrap()
     });
 
-    detect_template(mat, &*TEMPLATE, Point::default(), 0.75)
+    detect_template(mat, &*TEMPLATE, Point::default(), 0.75, scale)
 }
 
-fn detect_elite_boss_bar(mat: &impl MatTraitConst) -> bool {
+fn detect_elite_boss_bar(mat: &impl MatTraitConst, scale: f64) -> bool {
     /// TODO: Support default ratio
     static TEMPLATE_1: LazyLock<Mat> = LazyLock::new(|| {
         imgcodecs::imdecode(
@@ -705,14 +900,14 @@ fn detect_elite_boss_bar(mat: &impl MatTraitConst) -> bool {
     let boss_bar = mat.roi(crop_bbox).unwrap();
     let template_1 = &*TEMPLATE_1;
     let template_2 = &*TEMPLATE_2;
-    detect_template(&boss_bar, template_1, Point::default(), 0.9).is_ok()
-        || detect_template(&boss_bar, template_2, Point::default(), 0.9).is_ok()
+    detect_template(&boss_bar, template_1, Point::default(), 0.9, scale).is_ok()
+        || detect_template(&boss_bar, template_2, Point::default(), 0.9, scale).is_ok()
 }
 
 fn detect_minimap(mat: &impl MatTraitConst, border_threshold: u8) -> Result<Rect> {
     static MINIMAP_MODEL: LazyLock<Mutex<Session>> = LazyLock::new(|| {
         Mutex::new(
-            build_session(include_bytes!(env!("MINIMAP_MODEL")))
+            build_session(ModelKind::Minimap)
                 .expect("build minimap detection session successfully"),
         )
     });
@@ -873,6 +1068,28 @@ fn detect_minimap_name(mat: &impl MatTraitConst, minimap: Rect) -> Result<Rect>
     Ok(name_bbox)
 }
 
+/// Recognizes the minimap name text inside the `name` rectangle.
+///
+/// The `name` rectangle is the one returned from [`detect_minimap_name`].
+fn detect_minimap_name_text(mat: &impl MatTraitConst, name: Rect) -> Result<String> {
+    let (name_in, w_ratio, h_ratio) = preprocess_for_text_bboxes(&mat.roi(name)?);
+    let mut bboxes = extract_text_bboxes(&name_in, w_ratio, h_ratio, name.x, name.y);
+    bboxes.sort_by_key(|bbox| bbox.x);
+
+    join_minimap_name(&extract_texts(mat, &bboxes)).ok_or(anyhow!("cannot recognize minimap name"))
+}
+
+/// Joins the recognized words of a minimap name from left to right.
+fn join_minimap_name(texts: &[String]) -> Option<String> {
+    let name = texts
+        .iter()
+        .map(|text| text.trim())
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!name.is_empty()).then_some(name)
+}
+
 fn detect_minimap_match<T: ToInputArray + MatTraitConst>(
     mat: &impl MatTraitConst,
     grayscale: &impl MatTraitConst,
@@ -908,6 +1125,7 @@ fn detect_minimap_match<T: ToInputArray + MatTraitConst>(
         no_array(),
         Point::default(),
         0.8,
+        1.0,
     )
     .map(|(_, score)| score)?;
     let minimap_score = detect_template_single(
@@ -916,6 +1134,7 @@ fn detect_minimap_match<T: ToInputArray + MatTraitConst>(
         no_array(),
         Point::default(),
         0.6,
+        1.0,
     )
     .map(|(_, score)| score)?;
 
@@ -929,17 +1148,25 @@ fn detect_minimap_portals<T: MatTraitConst + ToInputArray>(minimap: T) -> Vec<Re
     });
     const PORTAL_EXPAND_SIZE: i32 = 5;
 
-    detect_template_multiple(&minimap, &*TEMPLATE, no_array(), Point::default(), 16, 0.7)
-        .into_iter()
-        .filter_map(|result| result.ok())
-        .map(|(bbox, _)| {
-            expand_bbox(
-                Some(minimap.size().expect("size available")),
-                bbox,
-                PORTAL_EXPAND_SIZE,
-            )
-        })
-        .collect::<Vec<_>>()
+    detect_template_multiple(
+        &minimap,
+        &*TEMPLATE,
+        no_array(),
+        Point::default(),
+        16,
+        0.7,
+        1.0,
+    )
+    .into_iter()
+    .filter_map(|result| result.ok())
+    .map(|(bbox, _)| {
+        expand_bbox(
+            Some(minimap.size().expect("size available")),
+            bbox,
+            PORTAL_EXPAND_SIZE,
+        )
+    })
+    .collect::<Vec<_>>()
 }
 
 fn detect_minimap_rune(minimap: &impl ToInputArray) -> Result<Rect> {
@@ -953,8 +1180,15 @@ fn detect_minimap_rune(minimap: &impl ToInputArray) -> Result<Rect> {
 
     // Expands by 2 pixels to preserve previous position calculation. Previous template is 11x11
     // while the current template is 9x9.
-    detect_template_single(minimap, &*TEMPLATE, &*TEMPLATE_MASK, Point::default(), 0.75)
-        .map(|(bbox, _)| expand_bbox(None, bbox, 1))
+    detect_template_single(
+        minimap,
+        &*TEMPLATE,
+        &*TEMPLATE_MASK,
+        Point::default(),
+        0.75,
+        1.0,
+    )
+    .map(|(bbox, _)| expand_bbox(None, bbox, 1))
 }
 
 fn detect_player(mat: &impl ToInputArray) -> Result<Rect> {
@@ -1040,7 +1274,7 @@ fn detect_player(mat: &impl ToInputArray) -> Result<Rect> {
 
     // Detect and offset as needed to get a 10x10 for preserving previous behavior.
     for offsets in &TEMPLATE_OFFSETS {
-        if let Ok(rect) = detect_template(mat, &**offsets.template, Point::default(), 0.75) {
+        if let Ok(rect) = detect_template(mat, &**offsets.template, Point::default(), 0.75, 1.0) {
             let x = rect.x + offsets.x;
             let y = rect.y + offsets.y;
             let width = rect.width + offsets.width;
@@ -1075,48 +1309,161 @@ fn detect_player_kind(mat: &impl ToInputArray, kind: OtherPlayerKind) -> bool {
 
     match kind {
         OtherPlayerKind::Stranger => {
-            detect_template(mat, &*STRANGER_TEMPLATE, Point::default(), 0.85).is_ok()
+            detect_template(mat, &*STRANGER_TEMPLATE, Point::default(), 0.85, 1.0).is_ok()
         }
         OtherPlayerKind::Guildie => {
-            detect_template(mat, &*GUILDIE_TEMPLATE, Point::default(), 0.85).is_ok()
+            detect_template(mat, &*GUILDIE_TEMPLATE, Point::default(), 0.85, 1.0).is_ok()
         }
         OtherPlayerKind::Friend => {
-            detect_template(mat, &*FRIEND_TEMPLATE, Point::default(), 0.85).is_ok()
+            detect_template(mat, &*FRIEND_TEMPLATE, Point::default(), 0.85, 1.0).is_ok()
+        }
+    }
+}
+
+/// Detects the player character above the name tag closest to `expected` in `grayscale`.
+///
+/// The name tag is a dark rectangle containing white text right below the character.
+fn detect_player_on_screen(
+    grayscale: &impl MatTraitConst,
+    expected: Point,
+    scale: f64,
+) -> Result<Rect> {
+    let scaled = |value: i32| (value as f64 * scale).round() as i32;
+    let min_width = scaled(NAME_TAG_MIN_WIDTH);
+    let max_width = scaled(NAME_TAG_MAX_WIDTH);
+    let min_height = scaled(NAME_TAG_MIN_HEIGHT);
+    let max_height = scaled(NAME_TAG_MAX_HEIGHT);
+
+    let mut background = Mat::default();
+    threshold(
+        grayscale,
+        &mut background,
+        NAME_TAG_BACKGROUND_THRESHOLD,
+        255.0,
+        THRESH_BINARY_INV,
+    )?;
+    let mut stats = Mat::default();
+    let labels_count = connected_components_with_stats(
+        &background,
+        &mut Mat::default(),
+        &mut stats,
+        &mut Mat::default(),
+        8,
+        CV_32S,
+    )?;
+
+    let mut name_tag = None::<(Rect, i32)>;
+    for i in 1..labels_count {
+        let x = *stats.at_2d::<i32>(i, CC_STAT_LEFT)?;
+        let y = *stats.at_2d::<i32>(i, CC_STAT_TOP)?;
+        let w = *stats.at_2d::<i32>(i, CC_STAT_WIDTH)?;
+        let h = *stats.at_2d::<i32>(i, CC_STAT_HEIGHT)?;
+        let area = *stats.at_2d::<i32>(i, CC_STAT_AREA)?;
+        if w < min_width || w > max_width || h < min_height || h > max_height {
+            continue;
+        }
+        // The background surrounds the text so it must fill most of the rectangle
+        if (area as f32) < (w * h) as f32 * NAME_TAG_MIN_BACKGROUND_RATIO {
+            continue;
+        }
+
+        let bbox = Rect::new(x, y, w, h);
+        let mut text = Mat::default();
+        threshold(
+            &grayscale.roi(bbox)?,
+            &mut text,
+            NAME_TAG_TEXT_THRESHOLD,
+            255.0,
+            THRESH_BINARY,
+        )?;
+        let text_area = count_non_zero(&text)?;
+        if (text_area as f32) < (w * h) as f32 * NAME_TAG_MIN_TEXT_RATIO {
+            continue;
+        }
+
+        let distance = (x + w / 2 - expected.x).abs() + (y - expected.y).abs();
+        if name_tag.is_none_or(|(_, best)| distance < best) {
+            name_tag = Some((bbox, distance));
         }
     }
+
+    let (name_tag, _) = name_tag.ok_or(anyhow!("player name tag not found"))?;
+    let width = scaled(PLAYER_ON_SCREEN_WIDTH);
+    let height = scaled(PLAYER_ON_SCREEN_HEIGHT);
+    let x = name_tag.x + name_tag.width / 2 - width / 2;
+    let y = (name_tag.y - height).max(0);
+    Ok(Rect::new(x, y, width, name_tag.y - y))
+}
+
+/// Estimates the player position on the screen of `size` from the `player` position on
+/// the `minimap`.
+///
+/// The camera follows the player in the middle of the screen but stops at the map edges, leaving
+/// the player off the middle when near the edges.
+fn estimate_player_on_screen(size: Size, minimap: Rect, player: Point) -> Point {
+    #[inline]
+    fn estimate(screen: i32, from_start: i32, from_end: i32, scale: f32) -> i32 {
+        let half = screen / 2;
+        let from_start = (from_start as f32 / scale) as i32;
+        let from_end = (from_end as f32 / scale) as i32;
+        if from_start < half {
+            from_start
+        } else if from_end < half {
+            screen - from_end
+        } else {
+            half
+        }
+    }
+
+    // Minimap y is from the bottom edge
+    Point::new(
+        estimate(
+            size.width,
+            player.x,
+            minimap.width - player.x,
+            MINIMAP_X_SCALE,
+        ),
+        estimate(
+            size.height,
+            minimap.height - player.y,
+            player.y,
+            MINIMAP_Y_SCALE,
+        ),
+    )
 }
 
-fn detect_player_is_dead(mat: &impl ToInputArray) -> bool {
+fn detect_player_is_dead(mat: &impl ToInputArray, scale: f64) -> bool {
     /// TODO: Support default ratio
     static TEMPLATE: LazyLock<Mat> = LazyLock::new(|| {
         imgcodecs::imdecode(include_bytes!(env!("TOMB_TEMPLATE")), IMREAD_GRAYSCALE).unwrap()
     });
 
-    detect_template(mat, &*TEMPLATE, Point::default(), 0.8).is_ok()
+    detect_template(mat, &*TEMPLATE, Point::default(), 0.8, scale).is_ok()
 }
 
-fn detect_player_in_cash_shop(mat: &impl ToInputArray) -> bool {
+fn detect_player_in_cash_shop(mat: &impl ToInputArray, scale: f64) -> bool {
     /// TODO: Support default ratio
     static CASH_SHOP: LazyLock<Mat> = LazyLock::new(|| {
         imgcodecs::imdecode(include_bytes!(env!("CASH_SHOP_TEMPLATE")), IMREAD_GRAYSCALE).unwrap()
     });
 
-    detect_template(mat, &*CASH_SHOP, Point::default(), 0.7).is_ok()
+    detect_template(mat, &*CASH_SHOP, Point::default(), 0.7, scale).is_ok()
 }
 
+/// TODO: Support default ratio
+static HP_START: LazyLock<Mat> = LazyLock::new(|| {
+    imgcodecs::imdecode(include_bytes!(env!("HP_START_TEMPLATE")), IMREAD_GRAYSCALE).unwrap()
+});
+
 // TODO: Update detection resource when UI changes
-fn detect_player_health_bar(mat: &impl ToInputArray) -> Result<Rect> {
-    /// TODO: Support default ratio
-    static HP_START: LazyLock<Mat> = LazyLock::new(|| {
-        imgcodecs::imdecode(include_bytes!(env!("HP_START_TEMPLATE")), IMREAD_GRAYSCALE).unwrap()
-    });
+fn detect_player_health_bar(mat: &impl ToInputArray, scale: f64) -> Result<Rect> {
     static HP_END: LazyLock<Mat> = LazyLock::new(|| {
         imgcodecs::imdecode(include_bytes!(env!("HP_END_TEMPLATE")), IMREAD_GRAYSCALE).unwrap()
     });
 
-    let hp_start = detect_template(mat, &*HP_START, Point::default(), 0.8)?;
+    let hp_start = detect_template(mat, &*HP_START, Point::default(), 0.8, scale)?;
     let hp_start_to_edge_x = hp_start.x + hp_start.width;
-    let hp_end = detect_template(mat, &*HP_END, Point::default(), 0.8)?;
+    let hp_end = detect_template(mat, &*HP_END, Point::default(), 0.8, scale)?;
     Ok(Rect::new(
         hp_start_to_edge_x,
         hp_start.y,
@@ -1125,82 +1472,140 @@ fn detect_player_health_bar(mat: &impl ToInputArray) -> Result<Rect> {
     ))
 }
 
+/// Detects the player mana bar below `health_bar`.
+///
+/// The mana bar has the same size as the health bar and is located by the separator between
+/// the current and max mana. The separator is the same as the health bar one so the bar must
+/// also contain blue pixels to not mistake other bars for it.
 // TODO: Update detection resource when UI changes
-fn detect_player_current_max_health_bars(
+fn detect_player_mana_bar(
     mat: &impl MatTraitConst,
     grayscale: &impl MatTraitConst,
-    hp_bar: Rect,
-) -> Result<(Rect, Rect)> {
-    /// TODO: Support default ratio
-    static HP_SEPARATOR_1: LazyLock<Mat> = LazyLock::new(|| {
-        imgcodecs::imdecode(
-            include_bytes!(env!("HP_SEPARATOR_1_TEMPLATE")),
-            IMREAD_GRAYSCALE,
-        )
-        .unwrap()
-    });
-    static HP_SEPARATOR_2: LazyLock<Mat> = LazyLock::new(|| {
-        imgcodecs::imdecode(
-            include_bytes!(env!("HP_SEPARATOR_2_TEMPLATE")),
-            IMREAD_GRAYSCALE,
-        )
-        .unwrap()
-    });
-    static HP_SHIELD: LazyLock<Mat> = LazyLock::new(|| {
-        imgcodecs::imdecode(include_bytes!(env!("HP_SHIELD_TEMPLATE")), IMREAD_GRAYSCALE).unwrap()
-    });
-    static HP_SEPARATOR_TYPE_1: AtomicBool = AtomicBool::new(true);
+    health_bar: Rect,
+    scale: f64,
+) -> Result<Rect> {
+    const MIN_BLUE_RED_DIFFERENCE: f64 = 40.0;
+
+    let size = grayscale.size()?;
+    let y = health_bar.y + health_bar.height;
+    let height = (health_bar.height * 2).min(size.height - y);
+    if height < health_bar.height {
+        bail!("mana bar is out of bound");
+    }
+
+    let region = Rect::new(health_bar.x, y, health_bar.width, height);
+    let region_mat = grayscale.roi(region)?;
+    let separator = detect_template(&region_mat, &*HP_SEPARATOR_1, region.tl(), 0.7, scale)
+        .or_else(|_| detect_template(&region_mat, &*HP_SEPARATOR_2, region.tl(), 0.7, scale))?;
+    let y = (separator.y + separator.height / 2 - health_bar.height / 2)
+        .clamp(region.y, region.y + region.height - health_bar.height);
+    let bar = Rect::new(health_bar.x, y, health_bar.width, health_bar.height);
+
+    let bar_mat = mat.roi(bar)?;
+    let mut blue = Mat::default();
+    let mut red = Mat::default();
+    let mut difference = Mat::default();
+    let mut mask = Mat::default();
+    extract_channel(&bar_mat, &mut blue, 0)?;
+    extract_channel(&bar_mat, &mut red, 2)?;
+    subtract_def(&blue, &red, &mut difference)?;
+    threshold(
+        &difference,
+        &mut mask,
+        MIN_BLUE_RED_DIFFERENCE,
+        255.0,
+        THRESH_BINARY,
+    )?;
+    if count_non_zero(&mask)? == 0 {
+        bail!("mana bar is not blue");
+    }
 
-    let hp_separator_type_1 = HP_SEPARATOR_TYPE_1.load(Ordering::Relaxed);
-    let hp_separator_template = if hp_separator_type_1 {
+    Ok(bar)
+}
+
+/// TODO: Support default ratio
+static HP_SEPARATOR_1: LazyLock<Mat> = LazyLock::new(|| {
+    imgcodecs::imdecode(
+        include_bytes!(env!("HP_SEPARATOR_1_TEMPLATE")),
+        IMREAD_GRAYSCALE,
+    )
+    .unwrap()
+});
+
+static HP_SEPARATOR_2: LazyLock<Mat> = LazyLock::new(|| {
+    imgcodecs::imdecode(
+        include_bytes!(env!("HP_SEPARATOR_2_TEMPLATE")),
+        IMREAD_GRAYSCALE,
+    )
+    .unwrap()
+});
+
+/// TODO: Support default ratio
+static HP_SHIELD: LazyLock<Mat> = LazyLock::new(|| {
+    imgcodecs::imdecode(include_bytes!(env!("HP_SHIELD_TEMPLATE")), IMREAD_GRAYSCALE).unwrap()
+});
+
+/// Whether the health bar last used [`HP_SEPARATOR_1`].
+static HP_SEPARATOR_TYPE_1: AtomicBool = AtomicBool::new(true);
+
+/// Whether the mana bar last used [`HP_SEPARATOR_1`].
+static MP_SEPARATOR_TYPE_1: AtomicBool = AtomicBool::new(true);
+
+/// Detects the current and max bars from the health or mana `bar`.
+///
+/// `shield` is the template of the shield that can appear before the current value.
+/// `separator_type_1` is the separator template last used by `bar` and is swapped on failure.
+// TODO: Update detection resource when UI changes
+fn detect_player_current_max_bars(
+    mat: &impl MatTraitConst,
+    grayscale: &impl MatTraitConst,
+    bar: Rect,
+    shield: Option<&Mat>,
+    separator_type_1: &AtomicBool,
+    scale: f64,
+) -> Result<(Rect, Rect)> {
+    let separator_type = separator_type_1.load(Ordering::Relaxed);
+    let separator_template = if separator_type {
         &*HP_SEPARATOR_1
     } else {
         &*HP_SEPARATOR_2
     };
-    let hp_separator = detect_template(
-        &grayscale.roi(hp_bar).unwrap(),
-        hp_separator_template,
-        hp_bar.tl(),
+    let separator = detect_template(
+        &grayscale.roi(bar).unwrap(),
+        separator_template,
+        bar.tl(),
         0.7,
+        scale,
     )
     .inspect_err(|_| {
-        HP_SEPARATOR_TYPE_1.store(!hp_separator_type_1, Ordering::Release);
+        separator_type_1.store(!separator_type, Ordering::Release);
     })?;
-    let hp_shield = detect_template(
-        &grayscale.roi(hp_bar).unwrap(),
-        &*HP_SHIELD,
-        hp_bar.tl(),
-        0.8,
-    )
-    .ok();
+    let shield = shield.and_then(|shield| {
+        detect_template(&grayscale.roi(bar).unwrap(), shield, bar.tl(), 0.8, scale).ok()
+    });
     let left = mat
-        .roi(Rect::new(
-            hp_bar.x,
-            hp_bar.y,
-            hp_separator.x - hp_bar.x,
-            hp_bar.height,
-        ))
+        .roi(Rect::new(bar.x, bar.y, separator.x - bar.x, bar.height))
         .unwrap();
     let (left_in, left_w_ratio, left_h_ratio) = preprocess_for_text_bboxes(&left);
-    let left_bbox = extract_text_bboxes(&left_in, left_w_ratio, left_h_ratio, hp_bar.x, hp_bar.y)
+    let left_bbox = extract_text_bboxes(&left_in, left_w_ratio, left_h_ratio, bar.x, bar.y)
         .into_iter()
-        .min_by_key(|bbox| ((bbox.x + bbox.width) - hp_separator.x).abs())
-        .ok_or(anyhow!("failed to detect current health bar"))?;
-    let left_bbox_x = hp_shield
+        .min_by_key(|bbox| ((bbox.x + bbox.width) - separator.x).abs())
+        .ok_or(anyhow!("failed to detect current bar"))?;
+    let left_bbox_x = shield
         .map(|bbox| bbox.x + bbox.width)
         .unwrap_or(left_bbox.x); // When there is shield, skips past it
     let left_bbox = Rect::new(
         left_bbox_x,
-        left_bbox.y - 1, // Add some space so the bound is not too tight
-        hp_separator.x - left_bbox_x + 1, // Help thin character like '1' detectable
+        left_bbox.y - 1,               // Add some space so the bound is not too tight
+        separator.x - left_bbox_x + 1, // Help thin character like '1' detectable
         left_bbox.height + 2,
     );
     let right = mat
         .roi(Rect::new(
-            hp_separator.x + hp_separator.width,
-            hp_bar.y,
-            (hp_bar.x + hp_bar.width) - (hp_separator.x + hp_separator.width),
-            hp_bar.height,
+            separator.x + separator.width,
+            bar.y,
+            (bar.x + bar.width) - (separator.x + separator.width),
+            bar.height,
         ))
         .unwrap();
     let (right_in, right_w_ratio, right_h_ratio) = preprocess_for_text_bboxes(&right);
@@ -1208,34 +1613,69 @@ fn detect_player_current_max_health_bars(
         &right_in,
         right_w_ratio,
         right_h_ratio,
-        hp_separator.x + hp_separator.width,
-        hp_bar.y,
+        separator.x + separator.width,
+        bar.y,
     )
     .into_iter()
     .reduce(|acc, cur| acc | cur)
-    .ok_or(anyhow!("failed to detect max health bar"))?;
+    .ok_or(anyhow!("failed to detect max bar"))?;
     Ok((left_bbox, right_bbox))
 }
 
-fn detect_player_health(
+/// Detects the current and max values of health or mana from `current_bar` and `max_bar`.
+fn detect_player_current_max(
     mat: &impl MatTraitConst,
     current_bar: Rect,
     max_bar: Rect,
 ) -> Result<(u32, u32)> {
-    let current_health = extract_texts(mat, &[current_bar]);
-    let current_health = current_health
+    let current = extract_texts(mat, &[current_bar]);
+    let current = current
         .first()
         .and_then(|value| value.parse::<u32>().ok())
-        .ok_or(anyhow!("cannot detect current health"))?;
-    let max_health = extract_texts(mat, &[max_bar]);
-    let max_health = max_health
+        .ok_or(anyhow!("cannot detect current value"))?;
+    let max = extract_texts(mat, &[max_bar]);
+    let max = max
         .first()
         .and_then(|value| value.parse::<u32>().ok())
-        .ok_or(anyhow!("cannot detect max health"))?;
-    Ok((current_health.min(max_health), max_health))
+        .ok_or(anyhow!("cannot detect max value"))?;
+    Ok((current.min(max), max))
 }
 
-fn detect_player_buff<T: MatTraitConst + ToInputArray>(mat: &T, kind: BuffKind) -> bool {
+/// Detects the player EXP percentage from the EXP bar text at the bottom of `mat`.
+///
+/// The EXP text is in the form of `<exp>[<percentage>%]` in the middle of the EXP bar.
+// TODO: Update detection resource when UI changes
+fn detect_player_exp(mat: &impl MatTraitConst, scale: f64) -> Result<f32> {
+    let size = mat.size()?;
+    let height = ((EXP_BAR_HEIGHT as f64 * scale).round() as i32).min(size.height);
+    let bar = Rect::new(size.width / 3, size.height - height, size.width / 3, height);
+    let (bar_in, w_ratio, h_ratio) = preprocess_for_text_bboxes(&mat.roi(bar)?);
+    let bboxes = extract_text_bboxes(&bar_in, w_ratio, h_ratio, bar.x, bar.y);
+
+    extract_texts(mat, &bboxes)
+        .iter()
+        .find_map(|text| parse_exp_percentage(text))
+        .ok_or(anyhow!("cannot detect exp percentage"))
+}
+
+/// Parses the EXP percentage right before the `%` sign in `text`.
+fn parse_exp_percentage(text: &str) -> Option<f32> {
+    let (text, _) = text.split_once('%')?;
+    let start = text
+        .rfind(|char: char| !char.is_ascii_digit() && char != '.')
+        .map(|index| index + 1)
+        .unwrap_or(0);
+    text[start..]
+        .parse::<f32>()
+        .ok()
+        .filter(|percentage| (0.0..=100.0).contains(percentage))
+}
+
+fn detect_player_buff<T: MatTraitConst + ToInputArray>(
+    mat: &T,
+    kind: BuffKind,
+    scale: f64,
+) -> bool {
     /// TODO: Support default ratio
     static RUNE_BUFF: LazyLock<Mat> = LazyLock::new(|| {
         imgcodecs::imdecode(include_bytes!(env!("RUNE_BUFF_TEMPLATE")), IMREAD_GRAYSCALE).unwrap()
@@ -1426,6 +1866,7 @@ fn detect_player_buff<T: MatTraitConst + ToInputArray>(mat: &T, kind: BuffKind)
         | BuffKind::ExtremeBluePotion
         | BuffKind::ExtremeGreenPotion
         | BuffKind::ExtremeGoldPotion => 0.75,
+        BuffKind::Custom(_) => return false,
     };
     let template = match kind {
         BuffKind::Rune => &*RUNE_BUFF,
@@ -1447,6 +1888,7 @@ fn detect_player_buff<T: MatTraitConst + ToInputArray>(mat: &T, kind: BuffKind)
         BuffKind::ExtremeBluePotion => &*EXTREME_BLUE_POTION_BUFF,
         BuffKind::ExtremeGreenPotion => &*EXTREME_GREEN_POTION_BUFF,
         BuffKind::ExtremeGoldPotion => &*EXTREME_GOLD_POTION_BUFF,
+        BuffKind::Custom(_) => return false,
     };
 
     match kind {
@@ -1465,11 +1907,18 @@ fn detect_player_buff<T: MatTraitConst + ToInputArray>(mat: &T, kind: BuffKind)
                 }
                 _ => unreachable!(),
             };
-            let matches =
-                detect_template_multiple(mat, template, mask, Point::default(), 2, threshold)
-                    .into_iter()
-                    .filter_map(|result| result.ok())
-                    .collect::<Vec<_>>();
+            let matches = detect_template_multiple(
+                mat,
+                template,
+                mask,
+                Point::default(),
+                2,
+                threshold,
+                scale,
+            )
+            .into_iter()
+            .filter_map(|result| result.ok())
+            .collect::<Vec<_>>();
             if matches.is_empty() {
                 return false;
             }
@@ -1486,8 +1935,14 @@ fn detect_player_buff<T: MatTraitConst + ToInputArray>(mat: &T, kind: BuffKind)
                 _ => unreachable!(),
             };
             let match_current = matches.into_iter().next().unwrap();
-            let match_other =
-                detect_template_single(mat, template_other, mask, Point::default(), threshold);
+            let match_other = detect_template_single(
+                mat,
+                template_other,
+                mask,
+                Point::default(),
+                threshold,
+                scale,
+            );
 
             match_other.is_err()
                 || match_other.as_ref().copied().unwrap().0 != match_current.0
@@ -1499,30 +1954,36 @@ fn detect_player_buff<T: MatTraitConst + ToInputArray>(mat: &T, kind: BuffKind)
             &*LEGION_LUCK_BUFF_MASK,
             Point::default(),
             threshold,
+            scale,
         )
         .is_ok(),
-        BuffKind::LegionWealth => {
-            detect_template_single(mat, template, no_array(), Point::default(), threshold)
-                .or_else(|_| {
-                    detect_template_single(
-                        mat,
-                        &*LEGION_WEALTH_BUFF_2,
-                        no_array(),
-                        Point::default(),
-                        threshold,
-                    )
-                })
-                .is_ok()
-        }
-        _ => detect_template(mat, template, Point::default(), threshold).is_ok(),
+        BuffKind::LegionWealth => detect_template_single(
+            mat,
+            template,
+            no_array(),
+            Point::default(),
+            threshold,
+            scale,
+        )
+        .or_else(|_| {
+            detect_template_single(
+                mat,
+                &*LEGION_WEALTH_BUFF_2,
+                no_array(),
+                Point::default(),
+                threshold,
+                scale,
+            )
+        })
+        .is_ok(),
+        _ => detect_template(mat, template, Point::default(), threshold, scale).is_ok(),
     }
 }
 
 fn detect_rune_arrows_with_scores_regions(mat: &impl MatTraitConst) -> Vec<(Rect, KeyKind, f32)> {
     static RUNE_MODEL: LazyLock<Mutex<Session>> = LazyLock::new(|| {
         Mutex::new(
-            build_session(include_bytes!(env!("RUNE_MODEL")))
-                .expect("build rune detection session successfully"),
+            build_session(ModelKind::Rune).expect("build rune detection session successfully"),
         )
     });
 
@@ -1919,7 +2380,7 @@ fn extract_rune_arrows_to_slice(vec: Vec<(Rect, KeyKind)>) -> [(Rect, KeyKind);
     [first, second, third, fourth]
 }
 
-fn detect_erda_shower(mat: &impl MatTraitConst) -> Result<Rect> {
+fn detect_erda_shower(mat: &impl MatTraitConst, scale: f64) -> Result<Rect> {
     /// TODO: Support default ratio
     static ERDA_SHOWER: LazyLock<Mat> = LazyLock::new(|| {
         imgcodecs::imdecode(
@@ -1929,16 +2390,27 @@ fn detect_erda_shower(mat: &impl MatTraitConst) -> Result<Rect> {
         .unwrap()
     });
 
-    let size = mat.size().unwrap();
-    // crop to bottom right of the image for skill bar
+    let crop_bbox = skill_bar_bbox(mat.size().unwrap());
+    let skill_bar = mat.roi(crop_bbox).unwrap();
+    detect_template(&skill_bar, &*ERDA_SHOWER, crop_bbox.tl(), 0.8, scale)
+}
+
+fn detect_skill(mat: &impl MatTraitConst, icon: &Mat) -> Result<Rect> {
+    let crop_bbox = skill_bar_bbox(mat.size().unwrap());
+    let skill_bar = mat.roi(crop_bbox).unwrap();
+    // User icons are cropped at the current resolution so no scaling is needed
+    detect_template(&skill_bar, icon, crop_bbox.tl(), 0.8, 1.0)
+}
+
+/// The bottom right of the image where the skill bar is.
+#[inline]
+fn skill_bar_bbox(size: Size) -> Rect {
     let crop_x = size.width / 2;
     let crop_y = size.height / 5;
-    let crop_bbox = Rect::new(size.width - crop_x, size.height - crop_y, crop_x, crop_y);
-    let skill_bar = mat.roi(crop_bbox).unwrap();
-    detect_template(&skill_bar, &*ERDA_SHOWER, crop_bbox.tl(), 0.8)
+    Rect::new(size.width - crop_x, size.height - crop_y, crop_x, crop_y)
 }
 
-fn detect_familiar_save_button(mat: &impl ToInputArray) -> Result<Rect> {
+fn detect_familiar_save_button(mat: &impl ToInputArray, scale: f64) -> Result<Rect> {
     static TEMPLATE: LazyLock<Mat> = LazyLock::new(|| {
         imgcodecs::imdecode(
             include_bytes!(env!("FAMILIAR_BUTTON_SAVE_TEMPLATE")),
@@ -1947,10 +2419,10 @@ fn detect_familiar_save_button(mat: &impl ToInputArray) -> Result<Rect> {
         .unwrap()
     });
 
-    detect_template(mat, &*TEMPLATE, Point::default(), 0.75)
+    detect_template(mat, &*TEMPLATE, Point::default(), 0.75, scale)
 }
 
-fn detect_familiar_setup_button(mat: &impl ToInputArray) -> Result<Rect> {
+fn detect_familiar_setup_button(mat: &impl ToInputArray, scale: f64) -> Result<Rect> {
     static TEMPLATE: LazyLock<Mat> = LazyLock::new(|| {
         imgcodecs::imdecode(
             include_bytes!(env!("FAMILIAR_BUTTON_SETUP_TEMPLATE")),
@@ -1959,10 +2431,10 @@ fn detect_familiar_setup_button(mat: &impl ToInputArray) -> Result<Rect> {
         .unwrap()
     });
 
-    detect_template(mat, &*TEMPLATE, Point::default(), 0.75)
+    detect_template(mat, &*TEMPLATE, Point::default(), 0.75, scale)
 }
 
-fn detect_familiar_level_button(mat: &impl ToInputArray) -> Result<Rect> {
+fn detect_familiar_level_button(mat: &impl ToInputArray, scale: f64) -> Result<Rect> {
     static TEMPLATE: LazyLock<Mat> = LazyLock::new(|| {
         imgcodecs::imdecode(
             include_bytes!(env!("FAMILIAR_BUTTON_LEVEL_TEMPLATE")),
@@ -1971,7 +2443,7 @@ fn detect_familiar_level_button(mat: &impl ToInputArray) -> Result<Rect> {
         .unwrap()
     });
 
-    detect_template(mat, &*TEMPLATE, Point::default(), 0.75)
+    detect_template(mat, &*TEMPLATE, Point::default(), 0.75, scale)
 }
 
 static FAMILIAR_SLOT_FREE: LazyLock<Mat> = LazyLock::new(|| {
@@ -1996,7 +2468,7 @@ static FAMILIAR_SLOT_OCCUPIED_MASK: LazyLock<Mat> = LazyLock::new(|| {
     .unwrap()
 });
 
-fn detect_familiar_slots(mat: &impl ToInputArray) -> Vec<(Rect, bool)> {
+fn detect_familiar_slots(mat: &impl ToInputArray, scale: f64) -> Vec<(Rect, bool)> {
     let first = detect_template_multiple(
         mat,
         &*FAMILIAR_SLOT_FREE,
@@ -2004,6 +2476,7 @@ fn detect_familiar_slots(mat: &impl ToInputArray) -> Vec<(Rect, bool)> {
         Point::default(),
         3,
         0.75,
+        scale,
     );
     let second = detect_template_multiple(
         mat,
@@ -2012,6 +2485,7 @@ fn detect_familiar_slots(mat: &impl ToInputArray) -> Vec<(Rect, bool)> {
         Point::default(),
         3,
         0.75,
+        scale,
     );
     let mut vec = first
         .into_iter()
@@ -2026,11 +2500,14 @@ fn detect_familiar_slots(mat: &impl ToInputArray) -> Vec<(Rect, bool)> {
     vec
 }
 
-fn detect_familiar_slot_is_free(mat: &impl ToInputArray) -> bool {
-    detect_template(mat, &*FAMILIAR_SLOT_FREE, Point::default(), 0.75).is_ok()
+fn detect_familiar_slot_is_free(mat: &impl ToInputArray, scale: f64) -> bool {
+    detect_template(mat, &*FAMILIAR_SLOT_FREE, Point::default(), 0.75, scale).is_ok()
 }
 
-fn detect_familiar_hover_level<T: ToInputArray + MatTraitConst>(mat: &T) -> Result<FamiliarLevel> {
+fn detect_familiar_hover_level<T: ToInputArray + MatTraitConst>(
+    mat: &T,
+    scale: f64,
+) -> Result<FamiliarLevel> {
     static TEMPLATE: LazyLock<Mat> = LazyLock::new(|| {
         imgcodecs::imdecode(
             include_bytes!(env!("FAMILIAR_LEVEL_5_TEMPLATE")),
@@ -2046,16 +2523,24 @@ fn detect_familiar_hover_level<T: ToInputArray + MatTraitConst>(mat: &T) -> Resu
         .unwrap()
     });
 
-    let level_bbox = detect_template(mat, &*TEMPLATE, Point::default(), 0.75)?;
+    let level_bbox = detect_template(mat, &*TEMPLATE, Point::default(), 0.75, scale)?;
     let level = mat.roi(level_bbox)?;
-    Ok(
-        detect_template_single(&level, &*TEMPLATE, &*TEMPLATE_MASK, Point::default(), 0.70)
-            .map(|_| FamiliarLevel::Level5)
-            .unwrap_or(FamiliarLevel::LevelOther),
+    Ok(detect_template_single(
+        &level,
+        &*TEMPLATE,
+        &*TEMPLATE_MASK,
+        Point::default(),
+        0.70,
+        scale,
     )
+    .map(|_| FamiliarLevel::Level5)
+    .unwrap_or(FamiliarLevel::LevelOther))
 }
 
-fn detect_familiar_cards<T: MatTraitConst + ToInputArray>(mat: &T) -> Vec<(Rect, FamiliarRank)> {
+fn detect_familiar_cards<T: MatTraitConst + ToInputArray>(
+    mat: &T,
+    scale: f64,
+) -> Vec<(Rect, FamiliarRank)> {
     static TEMPLATE_RARE: LazyLock<Mat> = LazyLock::new(|| {
         imgcodecs::imdecode(
             include_bytes!(env!("FAMILIAR_CARD_RARE_TEMPLATE")),
@@ -2099,6 +2584,7 @@ fn detect_familiar_cards<T: MatTraitConst + ToInputArray>(mat: &T) -> Vec<(Rect,
         Point::default(),
         64,
         0.75,
+        scale,
     )
     .into_iter()
     .filter_map(|result| result.ok().map(|(bbox, _)| bbox))
@@ -2129,7 +2615,7 @@ fn detect_familiar_cards<T: MatTraitConst + ToInputArray>(mat: &T) -> Vec<(Rect,
     filtered
 }
 
-fn detect_familiar_scrollbar(mat: &impl ToInputArray) -> Result<Rect> {
+fn detect_familiar_scrollbar(mat: &impl ToInputArray, scale: f64) -> Result<Rect> {
     static TEMPLATE: LazyLock<Mat> = LazyLock::new(|| {
         imgcodecs::imdecode(
             include_bytes!(env!("FAMILIAR_SCROLLBAR_TEMPLATE")),
@@ -2138,10 +2624,10 @@ fn detect_familiar_scrollbar(mat: &impl ToInputArray) -> Result<Rect> {
         .unwrap()
     });
 
-    detect_template(mat, &*TEMPLATE, Point::default(), 0.6)
+    detect_template(mat, &*TEMPLATE, Point::default(), 0.6, scale)
 }
 
-fn detect_familiar_menu_opened(mat: &impl ToInputArray) -> bool {
+fn detect_familiar_menu_opened(mat: &impl ToInputArray, scale: f64) -> bool {
     static TEMPLATE: LazyLock<Mat> = LazyLock::new(|| {
         imgcodecs::imdecode(
             include_bytes!(env!("FAMILIAR_MENU_TEMPLATE")),
@@ -2150,10 +2636,10 @@ fn detect_familiar_menu_opened(mat: &impl ToInputArray) -> bool {
         .unwrap()
     });
 
-    detect_template(mat, &*TEMPLATE, Point::default(), 0.75).is_ok()
+    detect_template(mat, &*TEMPLATE, Point::default(), 0.75, scale).is_ok()
 }
 
-fn detect_familiar_essence_depleted(mat: &impl ToInputArray) -> bool {
+fn detect_familiar_essence_depleted(mat: &impl ToInputArray, scale: f64) -> bool {
     static TEMPLATE: LazyLock<Mat> = LazyLock::new(|| {
         imgcodecs::imdecode(
             include_bytes!(env!("FAMILIAR_ESSENCE_DEPLETE_TEMPLATE")),
@@ -2162,10 +2648,10 @@ fn detect_familiar_essence_depleted(mat: &impl ToInputArray) -> bool {
         .unwrap()
     });
 
-    detect_template(mat, &*TEMPLATE, Point::default(), 0.8).is_ok()
+    detect_template(mat, &*TEMPLATE, Point::default(), 0.8, scale).is_ok()
 }
 
-fn detect_change_channel_menu_opened(mat: &impl ToInputArray) -> bool {
+fn detect_change_channel_menu_opened(mat: &impl ToInputArray, scale: f64) -> bool {
     static TEMPLATE: LazyLock<Mat> = LazyLock::new(|| {
         imgcodecs::imdecode(
             include_bytes!(env!("CHANGE_CHANNEL_MENU_TEMPLATE")),
@@ -2174,26 +2660,30 @@ fn detect_change_channel_menu_opened(mat: &impl ToInputArray) -> bool {
         .unwrap()
     });
 
-    detect_template(mat, &*TEMPLATE, Point::default(), 0.75).is_ok()
+    detect_template(mat, &*TEMPLATE, Point::default(), 0.75, scale).is_ok()
 }
 
-fn detect_chat_menu_opened(mat: &impl ToInputArray) -> bool {
+fn detect_chat_menu_opened(mat: &impl ToInputArray, scale: f64) -> bool {
     static TEMPLATE: LazyLock<Mat> = LazyLock::new(|| {
         imgcodecs::imdecode(include_bytes!(env!("CHAT_MENU_TEMPLATE")), IMREAD_GRAYSCALE).unwrap()
     });
 
-    detect_template(mat, &*TEMPLATE, Point::default(), 0.75).is_ok()
+    detect_template(mat, &*TEMPLATE, Point::default(), 0.75, scale).is_ok()
 }
 
 /// Detects a single match from `template` with the given BGR image `Mat`.
+///
+/// `template` is resized by `scale` before matching. See [`Detector::detect_template_scale`].
 #[inline]
 fn detect_template<T: ToInputArray + MatTraitConst>(
     mat: &impl ToInputArray,
     template: &T,
     offset: Point,
     threshold: f64,
+    scale: f64,
 ) -> Result<Rect> {
-    detect_template_single(mat, template, no_array(), offset, threshold).map(|(bbox, _)| bbox)
+    detect_template_single(mat, template, no_array(), offset, threshold, scale)
+        .map(|(bbox, _)| bbox)
 }
 
 /// Detects a single match with `mask` from `template` with the given BGR image `Mat`.
@@ -2204,8 +2694,9 @@ fn detect_template_single<T: ToInputArray + MatTraitConst>(
     mask: impl ToInputArray,
     offset: Point,
     threshold: f64,
+    scale: f64,
 ) -> Result<(Rect, f64)> {
-    detect_template_multiple(mat, template, mask, offset, 1, threshold)
+    detect_template_multiple(mat, template, mask, offset, 1, threshold, scale)
         .into_iter()
         .next()
         .ok_or(anyhow!("no match"))
@@ -2214,6 +2705,9 @@ fn detect_template_single<T: ToInputArray + MatTraitConst>(
 
 /// Detects multiple matches from `template` from the given BGR image `Mat` and returns up to
 /// `max_matches` best results.
+///
+/// When `scale` is not `1.0`, both `template` and the non-empty `mask` are resized by `scale`
+/// before matching and the returned bounding boxes have the resized template size.
 #[inline]
 fn detect_template_multiple<T: ToInputArray + MatTraitConst>(
     mat: &impl ToInputArray,
@@ -2222,6 +2716,7 @@ fn detect_template_multiple<T: ToInputArray + MatTraitConst>(
     offset: Point,
     max_matches: usize,
     threshold: f64,
+    scale: f64,
 ) -> Vec<Result<(Rect, f64)>> {
     #[inline]
     fn clear_result(result: &mut Mat, rect: Rect, offset: Point) -> Result<()> {
@@ -2276,12 +2771,32 @@ fn detect_template_multiple<T: ToInputArray + MatTraitConst>(
     }
 
     let mut result = Mat::default();
-    if let Err(err) = match_template(mat, template, &mut result, TM_CCOEFF_NORMED, &mask) {
+    let template_size;
+    let match_result = if is_native_scale(scale) {
+        template_size = template.size().unwrap();
+        match_template(mat, template, &mut result, TM_CCOEFF_NORMED, &mask)
+    } else {
+        let scaled = match scaled_template(template, &mask, scale) {
+            Ok(scaled) => scaled,
+            Err(err) => {
+                error!(target: "detect", "template scaling error {err}");
+                return vec![];
+            }
+        };
+        template_size = scaled.template.size().unwrap();
+        match_template(
+            mat,
+            &scaled.template,
+            &mut result,
+            TM_CCOEFF_NORMED,
+            &scaled.mask,
+        )
+    };
+    if let Err(err) = match_result {
         error!(target: "detect", "template detection error {err}");
         return vec![];
     }
 
-    let template_size = template.size().unwrap();
     let max_matches = max_matches.max(1);
     if max_matches == 1 {
         // Weird INFINITY values when match template with mask
@@ -2324,13 +2839,128 @@ fn detect_template_multiple<T: ToInputArray + MatTraitConst>(
     filter
 }
 
+/// Detects the scale for templates by matching the health bar start template in `grayscale`
+/// across [`TEMPLATE_SCALES`].
+///
+/// Returns the scale with the best score or an error if no scale scores above
+/// [`TEMPLATE_SCALE_THRESHOLD`] (e.g. the health bar is not visible).
+fn detect_template_scale(grayscale: &Mat) -> Result<f64> {
+    TEMPLATE_SCALES
+        .map(|step| step as f64 * TEMPLATE_SCALE_STEP)
+        .filter_map(|scale| {
+            detect_template_single(
+                grayscale,
+                &*HP_START,
+                no_array(),
+                Point::default(),
+                TEMPLATE_SCALE_THRESHOLD,
+                scale,
+            )
+            .ok()
+            .map(|(_, score)| (scale, score))
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(scale, _)| scale)
+        .ok_or(anyhow!("template scale not found"))
+}
+
+/// A template and its non-empty mask resized by [`scale_template`].
+struct ScaledTemplate {
+    template: Mat,
+    mask: Mat,
+    /// The original template and mask kept alive so that their data addresses used as the cache
+    /// key are not reused by other templates.
+    _original: (Mat, Mat),
+}
+
+/// Resizes `template` and the non-empty `mask` by `scale` once and reuses the result.
+///
+/// Templates are cached by their data addresses and `scale`.
+fn scaled_template(
+    template: &impl ToInputArray,
+    mask: &impl ToInputArray,
+    scale: f64,
+) -> Result<Arc<ScaledTemplate>> {
+    static SCALED_TEMPLATES: LazyLock<Mutex<HashMap<(usize, usize, u64), Arc<ScaledTemplate>>>> =
+        LazyLock::new(Mutex::default);
+
+    let template = template.input_array()?.get_mat_def()?;
+    let mask = mask.input_array()?;
+    let mask = if mask.empty()? {
+        Mat::default()
+    } else {
+        mask.get_mat_def()?
+    };
+    let key = (
+        template.data() as usize,
+        mask.data() as usize,
+        scale.to_bits(),
+    );
+    if let Some(scaled) = SCALED_TEMPLATES.lock().unwrap().get(&key) {
+        return Ok(scaled.clone());
+    }
+
+    let (scaled_template, scaled_mask) = scale_template(&template, &mask, scale)?;
+    let scaled = Arc::new(ScaledTemplate {
+        template: scaled_template,
+        mask: scaled_mask,
+        _original: (template, mask),
+    });
+    SCALED_TEMPLATES.lock().unwrap().insert(key, scaled.clone());
+    Ok(scaled)
+}
+
+/// Resizes `template` and the non-empty `mask` by `scale`.
+///
+/// The mask is resized with nearest neighbor interpolation to keep it binary.
+fn scale_template(
+    template: &impl ToInputArray,
+    mask: &impl ToInputArray,
+    scale: f64,
+) -> Result<(Mat, Mat)> {
+    let interpolation = if scale < 1.0 {
+        INTER_AREA
+    } else {
+        INTER_LINEAR
+    };
+    let mut scaled_template = Mat::default();
+    let mut scaled_mask = Mat::default();
+    resize(
+        template,
+        &mut scaled_template,
+        Size::default(),
+        scale,
+        scale,
+        interpolation,
+    )?;
+    if scaled_template.empty() {
+        bail!("template is empty after scaling by {scale}");
+    }
+    if !mask.input_array()?.empty()? {
+        resize(
+            mask,
+            &mut scaled_mask,
+            Size::default(),
+            scale,
+            scale,
+            INTER_NEAREST,
+        )?;
+    }
+
+    Ok((scaled_template, scaled_mask))
+}
+
+#[inline]
+fn is_native_scale(scale: f64) -> bool {
+    (scale - 1.0).abs() < f64::EPSILON
+}
+
 /// Extracts texts from the non-preprocessed `Mat` and detected text bounding boxes.
 fn extract_texts(mat: &impl MatTraitConst, bboxes: &[Rect]) -> Vec<String> {
     static TEXT_RECOGNITION_MODEL: LazyLock<Mutex<TextRecognitionModel>> = LazyLock::new(|| {
-        let model = read_net_from_onnx_buffer(&Vector::from_slice(include_bytes!(env!(
-            "TEXT_RECOGNITION_MODEL"
-        ))))
-        .unwrap();
+        let model =
+            read_net_from_onnx_buffer(&Vector::from_slice(model_bytes(ModelKind::TextRecognition)))
+                .unwrap();
         Mutex::new(
             TextRecognitionModel::new(&model)
                 .and_then(|mut m| {
@@ -2382,8 +3012,7 @@ fn extract_text_bboxes(
     const LINK_SCORE_THRESHOLD: f64 = 0.4;
     static TEXT_DETECTION_MODEL: LazyLock<Mutex<Session>> = LazyLock::new(|| {
         Mutex::new(
-            build_session(include_bytes!(env!("TEXT_DETECTION_MODEL")))
-                .expect("build text detection session normally"),
+            build_session(ModelKind::TextDetection).expect("build text detection session normally"),
         )
     });
 
@@ -2773,15 +3402,155 @@ fn to_input_value(mat: &impl MatTraitConst) -> SessionInputValue<'_> {
     SessionInputValue::Owned(tensor.clone().into_dyn())
 }
 
-#[inline]
-fn build_session(model: &[u8]) -> Result<Session> {
-    // TODO: ort supports fallback to CPU if GPU is not found. Check if missing GPU-related
-    // TODO: onnxruntime dlls affect this.
-    if cfg!(feature = "gpu") {
-        Ok(Session::builder()?
-            .with_execution_providers([CUDAExecutionProvider::default().build()])?
-            .commit_from_memory(model)?)
-    } else {
-        Ok(Session::builder()?.commit_from_memory(model)?)
+#[cfg(test)]
+mod tests {
+    use opencv::{
+        core::{CV_8UC4, randu},
+        imgproc::COLOR_GRAY2BGRA,
+    };
+
+    use super::*;
+
+    #[test]
+    fn detect_template_scaled() {
+        let mut template = Mat::zeros(8, 8, CV_8U).unwrap().to_mat().unwrap();
+        template
+            .roi_mut(Rect::new(2, 2, 4, 3))
+            .unwrap()
+            .set_scalar(Scalar::all(255.0))
+            .unwrap();
+        let mut mat = Mat::zeros(64, 64, CV_8U).unwrap().to_mat().unwrap();
+        randu(&mut mat, &Scalar::all(0.0), &Scalar::all(64.0)).unwrap();
+        let mut scaled_template = Mat::default();
+        resize(
+            &template,
+            &mut scaled_template,
+            Size::default(),
+            2.0,
+            2.0,
+            INTER_LINEAR,
+        )
+        .unwrap();
+        scaled_template
+            .copy_to(&mut mat.roi_mut(Rect::new(10, 20, 16, 16)).unwrap())
+            .unwrap();
+
+        assert_eq!(
+            detect_template(&mat, &template, Point::default(), 0.9, 2.0).unwrap(),
+            Rect::new(10, 20, 16, 16)
+        );
+    }
+
+    #[test]
+    fn scaled_template_cached_per_scale() {
+        let template = Mat::zeros(8, 8, CV_8U).unwrap().to_mat().unwrap();
+
+        let scaled = scaled_template(&template, &no_array(), 2.0).unwrap();
+
+        assert_eq!(scaled.template.size().unwrap(), Size::new(16, 16));
+        assert!(scaled.mask.empty());
+        assert!(Arc::ptr_eq(
+            &scaled,
+            &scaled_template(&template, &no_array(), 2.0).unwrap()
+        ));
+        assert!(!Arc::ptr_eq(
+            &scaled,
+            &scaled_template(&template, &no_array(), 0.5).unwrap()
+        ));
+    }
+
+    #[test]
+    fn join_minimap_name_from_texts() {
+        assert_eq!(
+            join_minimap_name(&["Arcana:".to_string(), " Cavern ".to_string()]),
+            Some("Arcana: Cavern".to_string())
+        );
+        assert_eq!(join_minimap_name(&[" ".to_string()]), None);
+        assert_eq!(join_minimap_name(&[]), None);
+    }
+
+    #[test]
+    fn parse_exp_percentage_from_text() {
+        assert_eq!(parse_exp_percentage("123456789[45.123%]"), Some(45.123));
+        assert_eq!(parse_exp_percentage("0.5%"), Some(0.5));
+        assert_eq!(parse_exp_percentage("[100%]"), Some(100.0));
+        assert_eq!(parse_exp_percentage("123456789"), None);
+        assert_eq!(parse_exp_percentage("[%]"), None);
+        assert_eq!(parse_exp_percentage("[123.4%]"), None);
+    }
+
+    #[test]
+    fn detect_player_on_screen_above_name_tag() {
+        let mut mat = Mat::new_rows_cols_with_default(200, 300, CV_8U, Scalar::all(128.0)).unwrap();
+        mat.roi_mut(Rect::new(100, 150, 60, 15))
+            .unwrap()
+            .set_scalar(Scalar::all(0.0))
+            .unwrap();
+        for x in [105, 120, 135] {
+            mat.roi_mut(Rect::new(x, 154, 4, 8))
+                .unwrap()
+                .set_scalar(Scalar::all(255.0))
+                .unwrap();
+        }
+
+        assert_eq!(
+            detect_player_on_screen(&mat, Point::new(150, 100), 1.0).unwrap(),
+            Rect::new(110, 85, 40, 65)
+        );
+        assert!(detect_player_on_screen(&mat, Point::new(150, 100), 3.0).is_err());
+    }
+
+    #[test]
+    fn detect_player_mana_bar_only_blue() {
+        let health_bar = Rect::new(10, 10, 100, 12);
+        let mana_bar = Rect::new(10, 22, 100, 12);
+        let mat_with_fill = |fill: Scalar| {
+            let mut mat = Mat::new_rows_cols_with_default(
+                40,
+                120,
+                CV_8UC4,
+                Scalar::new(40.0, 40.0, 40.0, 255.0),
+            )
+            .unwrap();
+            mat.roi_mut(mana_bar).unwrap().set_scalar(fill).unwrap();
+            let mut separator = Mat::default();
+            cvt_color_def(&*HP_SEPARATOR_1, &mut separator, COLOR_GRAY2BGRA).unwrap();
+            separator
+                .copy_to(&mut mat.roi_mut(Rect::new(55, 24, 7, 7)).unwrap())
+                .unwrap();
+            let mut grayscale = Mat::default();
+            cvt_color_def(&mat, &mut grayscale, COLOR_BGRA2GRAY).unwrap();
+            (mat, grayscale)
+        };
+
+        let (mat, grayscale) = mat_with_fill(Scalar::new(200.0, 60.0, 30.0, 255.0));
+        assert_eq!(
+            detect_player_mana_bar(&mat, &grayscale, health_bar, 1.0).unwrap(),
+            mana_bar
+        );
+
+        let (mat, grayscale) = mat_with_fill(Scalar::new(30.0, 40.0, 200.0, 255.0));
+        assert!(detect_player_mana_bar(&mat, &grayscale, health_bar, 1.0).is_err());
+    }
+
+    #[test]
+    fn detect_player_current_max_bars_swaps_given_separator_type() {
+        let mat = Mat::new_rows_cols_with_default(40, 120, CV_8UC4, Scalar::all(40.0)).unwrap();
+        let mut grayscale = Mat::default();
+        cvt_color_def(&mat, &mut grayscale, COLOR_BGRA2GRAY).unwrap();
+        let separator_type_1 = AtomicBool::new(true);
+
+        assert!(
+            detect_player_current_max_bars(
+                &mat,
+                &grayscale,
+                Rect::new(10, 10, 100, 12),
+                None,
+                &separator_type_1,
+                1.0
+            )
+            .is_err()
+        );
+        assert!(!separator_type_1.load(Ordering::Relaxed));
     }
 }
@@ -23,6 +23,7 @@ use tokio::{
 };
 
 mod array;
+mod benchmark;
 mod bot;
 mod bridge;
 mod buff;
@@ -33,10 +34,13 @@ mod debug;
 mod detect;
 mod mat;
 mod minimap;
+mod models;
 mod navigator;
 mod notification;
 mod pathing;
+mod playback;
 mod player;
+mod replay;
 mod rng;
 mod rotator;
 mod rpc;
@@ -45,16 +49,21 @@ mod skill;
 mod task;
 
 pub use {
+    benchmark::{DetectionReport, DetectionStats, benchmark_detection},
     context::init,
     database::{
         Action, ActionCondition, ActionConfiguration, ActionConfigurationCondition, ActionKey,
-        ActionKeyDirection, ActionKeyWith, ActionMove, Bound, CaptureMode, Character, Class,
-        CycleRunStopMode, DatabaseEvent, EliteBossBehavior, FamiliarRarity, Familiars, InputMethod,
-        KeyBinding, KeyBindingConfiguration, LinkKeyBinding, Minimap, MobbingKey, NavigationPath,
-        NavigationPaths, NavigationPoint, NavigationTransition, Notifications, Platform, Position,
-        PotionMode, RotationMode, Settings, SwappableFamiliars, database_event_receiver,
+        ActionKeyDirection, ActionKeyWith, ActionMove, Bound, BuffIcon, Bundle, CaptureMode,
+        CapturePlayback, Character, Class, CycleRunStopMode, DatabaseEvent, EliteBossBehavior,
+        ExpSession, FamiliarRarity, Familiars, InputMethod, KeyBinding, KeyBindingConfiguration,
+        LinkKeyBinding, Minimap, MinimapAutoSelectPolicy, MinimapFingerprint, MobbingKey,
+        NavigationPath, NavigationPaths, NavigationPoint, NavigationTransition, Notifications,
+        Platform, Position, PotionMode, QuarantinedRow, Revision, RevisionChange, RevisionKind,
+        RotationMode, Settings, SkillIcon, SwappableFamiliars, database_event_receiver,
     },
+    models::{ModelInfo, ModelKind},
     pathing::MAX_PLATFORMS_COUNT,
+    replay::{ReplayOptions, replay},
     rotator::RotatorMode,
     strum::{EnumMessage, IntoEnumIterator, ParseError},
 };
@@ -108,6 +117,7 @@ enum Request {
     RedetectMinimap,
     GameStateReceiver,
     KeyReceiver,
+    MinimapAutoSelectedReceiver,
     RefreshCaptureHandles,
     QueryCaptureHandles,
     SelectCaptureHandle(Option<usize>),
@@ -125,6 +135,8 @@ enum Request {
     RecordImages(bool),
     #[cfg(debug_assertions)]
     TestSpinRune,
+    #[cfg(debug_assertions)]
+    CompareInputLatency(Vec<String>),
 }
 
 /// Represents response to UI [`Request`].
@@ -143,6 +155,7 @@ enum Response {
     RedetectMinimap,
     GameStateReceiver(broadcast::Receiver<GameState>),
     KeyReceiver(broadcast::Receiver<KeyBinding>),
+    MinimapAutoSelectedReceiver(broadcast::Receiver<(Minimap, Option<String>)>),
     RefreshCaptureHandles,
     QueryCaptureHandles((Vec<String>, Option<usize>)),
     SelectCaptureHandle,
@@ -160,6 +173,8 @@ enum Response {
     RecordImages,
     #[cfg(debug_assertions)]
     TestSpinRune,
+    #[cfg(debug_assertions)]
+    CompareInputLatency,
 }
 
 /// Request handler of incoming requests from UI.
@@ -184,6 +199,8 @@ pub(crate) trait RequestHandler {
 
     fn on_key_receiver(&self) -> broadcast::Receiver<KeyBinding>;
 
+    fn on_minimap_auto_selected_receiver(&self) -> broadcast::Receiver<(Minimap, Option<String>)>;
+
     fn on_refresh_capture_handles(&mut self);
 
     fn on_query_capture_handles(&self) -> (Vec<String>, Option<usize>);
@@ -210,6 +227,9 @@ pub(crate) trait RequestHandler {
 
     #[cfg(debug_assertions)]
     fn on_test_spin_rune(&self);
+
+    #[cfg(debug_assertions)]
+    fn on_compare_input_latency(&mut self, urls: Vec<String>);
 }
 
 /// The four quads of a bound.
@@ -227,6 +247,16 @@ pub enum BoundQuadrant {
 pub struct DebugState {
     pub is_recording: bool,
     pub is_rune_auto_saving: bool,
+    pub input_latencies: Vec<InputLatency>,
+}
+
+/// Round-trip latency to an input server measured from debug.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg(debug_assertions)]
+pub struct InputLatency {
+    pub url: String,
+    /// The mean, min and max latencies in milliseconds or the error message if failed.
+    pub latency: Result<(f32, f32, f32), String>,
 }
 
 /// A struct for storing game information.
@@ -234,6 +264,14 @@ pub struct DebugState {
 pub struct GameState {
     pub position: Option<(i32, i32)>,
     pub health: Option<(u32, u32)>,
+    pub mana: Option<(u32, u32)>,
+    /// The current EXP percentage.
+    pub exp: Option<f32>,
+    /// The EXP percentage gained in the current running session.
+    pub exp_gained: Option<f32>,
+    pub exp_per_hour: Option<f32>,
+    /// The map name recognized from the minimap title.
+    pub map_name: Option<String>,
     pub state: String,
     pub normal_action: Option<String>,
     pub priority_action: Option<String>,
@@ -413,6 +451,126 @@ pub async fn delete_character(character: Character) -> bool {
         .unwrap()
 }
 
+/// Queries EXP sessions from newest to oldest.
+pub async fn query_exp_sessions() -> Vec<ExpSession> {
+    spawn_blocking(database::query_exp_sessions)
+        .await
+        .unwrap()
+        .unwrap_or_default()
+}
+
+/// Queries rows that failed to deserialize and were moved out of their tables.
+pub async fn query_quarantined_rows() -> Vec<QuarantinedRow> {
+    spawn_blocking(database::query_quarantined_rows)
+        .await
+        .unwrap()
+        .unwrap_or_default()
+}
+
+/// Deletes the quarantined row with `id`.
+///
+/// Returns `true` if the row was deleted.
+pub async fn delete_quarantined_row(id: i64) -> bool {
+    spawn_blocking(move || database::delete_quarantined_row(id).is_ok())
+        .await
+        .unwrap()
+}
+
+/// Queries revisions of the minimap or character with `id` from newest to oldest.
+pub async fn query_revisions(kind: RevisionKind, id: i64) -> Vec<Revision> {
+    spawn_blocking(move || database::query_revisions(kind, id))
+        .await
+        .unwrap()
+        .unwrap_or_default()
+}
+
+/// Computes the changed values from revision `from_id` to revision `to_id`.
+pub async fn diff_revisions(from_id: i64, to_id: i64) -> Option<Vec<RevisionChange>> {
+    spawn_blocking(move || database::diff_revisions(from_id, to_id))
+        .await
+        .unwrap()
+        .ok()
+}
+
+/// Restores a minimap to the revision `id`.
+///
+/// Returns the restored [`Minimap`] on success.
+pub async fn restore_minimap_revision(id: i64) -> Option<Minimap> {
+    spawn_blocking(move || database::restore_minimap_revision(id))
+        .await
+        .unwrap()
+        .ok()
+}
+
+/// Restores a character to the revision `id`.
+///
+/// Returns the restored [`Character`] on success.
+pub async fn restore_character_revision(id: i64) -> Option<Character> {
+    spawn_blocking(move || database::restore_character_revision(id))
+        .await
+        .unwrap()
+        .ok()
+}
+
+/// Exports characters, minimaps, navigation paths and non-secret settings as a single [`Bundle`].
+pub async fn export_bundle() -> Option<Bundle> {
+    spawn_blocking(database::export_bundle).await.unwrap().ok()
+}
+
+/// Imports `bundle` into the database.
+///
+/// Every entry is inserted as new and references between them are remapped to the new `id`s.
+///
+/// Returns `true` if the bundle was imported.
+pub async fn import_bundle(bundle: Bundle) -> bool {
+    spawn_blocking(move || database::import_bundle(bundle).is_ok())
+        .await
+        .unwrap()
+}
+
+/// Queries the names of all profiles.
+///
+/// The default profile is not included.
+pub async fn query_profiles() -> Vec<String> {
+    spawn_blocking(database::query_profiles)
+        .await
+        .unwrap()
+        .unwrap_or_default()
+}
+
+/// Gets the currently opened profile or `None` for the default profile.
+pub async fn current_profile() -> Option<String> {
+    spawn_blocking(database::current_profile).await.unwrap()
+}
+
+/// Switches the database to `profile` or the default profile if `None`.
+///
+/// The profile will be created if it does not exist. The current minimap and character used by
+/// the main game loop are cleared after switching.
+///
+/// Returns `true` if the profile was switched.
+pub async fn switch_profile(profile: Option<String>) -> bool {
+    spawn_blocking(move || database::switch_profile(profile).is_ok())
+        .await
+        .unwrap()
+}
+
+/// Deletes `profile` and its database.
+///
+/// Returns `true` if the profile was deleted. The currently opened profile cannot be deleted.
+pub async fn delete_profile(profile: String) -> bool {
+    spawn_blocking(move || database::delete_profile(&profile).is_ok())
+        .await
+        .unwrap()
+}
+
+/// Queries the active detection models.
+///
+/// A model is either loaded from the models directory or embedded if there is no valid override.
+pub async fn query_models() -> Vec<ModelInfo> {
+    spawn_blocking(models::query_models).await.unwrap()
+}
+
 pub async fn redetect_minimap() {
     send_request!(RedetectMinimap)
 }
@@ -425,6 +583,11 @@ pub async fn key_receiver() -> broadcast::Receiver<KeyBinding> {
     send_request!(KeyReceiver => (receiver))
 }
 
+/// Receives the minimap and preset automatically selected on map entry.
+pub async fn minimap_auto_selected_receiver() -> broadcast::Receiver<(Minimap, Option<String>)> {
+    send_request!(MinimapAutoSelectedReceiver => (receiver))
+}
+
 pub async fn refresh_capture_handles() {
     send_request!(RefreshCaptureHandles)
 }
@@ -472,6 +635,11 @@ pub async fn test_spin_rune() {
     send_request!(TestSpinRune)
 }
 
+#[cfg(debug_assertions)]
+pub async fn compare_input_latency(urls: Vec<String>) {
+    send_request!(CompareInputLatency(urls))
+}
+
 pub(crate) fn poll_request(handler: &mut dyn RequestHandler) {
     if let Ok((request, sender)) = LazyLock::force(&REQUESTS).1.lock().unwrap().try_recv() {
         let result = match request {
@@ -509,6 +677,9 @@ pub(crate) fn poll_request(handler: &mut dyn RequestHandler) {
                 Response::GameStateReceiver(handler.on_game_state_receiver())
             }
             Request::KeyReceiver => Response::KeyReceiver(handler.on_key_receiver()),
+            Request::MinimapAutoSelectedReceiver => {
+                Response::MinimapAutoSelectedReceiver(handler.on_minimap_auto_selected_receiver())
+            }
             Request::RefreshCaptureHandles => {
                 handler.on_refresh_capture_handles();
                 Response::RefreshCaptureHandles
@@ -554,6 +725,11 @@ pub(crate) fn poll_request(handler: &mut dyn RequestHandler) {
                 handler.on_test_spin_rune();
                 Response::TestSpinRune
             }
+            #[cfg(debug_assertions)]
+            Request::CompareInputLatency(urls) => {
+                handler.on_compare_input_latency(urls);
+                Response::CompareInputLatency
+            }
         };
         let _ = sender.send(result);
     }
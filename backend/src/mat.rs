@@ -1,10 +1,12 @@
 use std::ffi::c_void;
 
+use anyhow::{Result, bail};
 use opencv::{
     boxed_ref::BoxedRef,
-    core::{_InputArray, CV_8UC4, Mat, MatTraitConst, ToInputArray},
+    core::{_InputArray, CV_8UC1, CV_8UC3, CV_8UC4, Mat, MatTraitConst, ToInputArray},
+    imgproc::{COLOR_RGB2BGRA, COLOR_RGBA2BGRA, COLOR_YUV2BGRA_NV12, cvt_color_def},
 };
-use platforms::capture::Frame;
+use platforms::capture::{Frame, PixelFormat};
 
 // A Mat that owns the external buffer.
 #[derive(Debug)]
@@ -15,9 +17,45 @@ pub struct OwnedMat {
 }
 
 impl OwnedMat {
-    #[inline]
-    pub fn new_from_frame(frame: Frame) -> Self {
-        Self::new_from_bytes(frame.data, frame.width, frame.height, CV_8UC4)
+    /// Creates a BGRA [`OwnedMat`] from `frame`.
+    ///
+    /// A [`PixelFormat::Bgra`] frame is used as-is without copying. Other formats are converted
+    /// to BGRA.
+    pub fn new_from_frame(frame: Frame) -> Result<Self> {
+        let Frame {
+            width,
+            height,
+            data,
+            format,
+        } = frame;
+        if width <= 0 || height <= 0 {
+            bail!("invalid frame size {width}x{height}");
+        }
+        if format == PixelFormat::Nv12 && (width % 2 != 0 || height % 2 != 0) {
+            bail!("invalid NV12 frame size {width}x{height}, must be even");
+        }
+        let expected_len = format.data_len(width, height);
+        if data.len() != expected_len {
+            bail!(
+                "frame data length {} mismatches expected length {expected_len} of {format:?}",
+                data.len()
+            );
+        }
+
+        let (rows, cv_type, code) = match format {
+            PixelFormat::Bgra => return Ok(Self::new_from_bytes(data, width, height, CV_8UC4)),
+            PixelFormat::Rgba => (height, CV_8UC4, COLOR_RGBA2BGRA),
+            PixelFormat::Rgb => (height, CV_8UC3, COLOR_RGB2BGRA),
+            PixelFormat::Nv12 => (height * 3 / 2, CV_8UC1, COLOR_YUV2BGRA_NV12),
+        };
+        let source = Self::new_from_bytes(data, width, rows, cv_type);
+        let mut bgra = Mat::default();
+        cvt_color_def(&source, &mut bgra, code)?;
+
+        Ok(Self {
+            mat: BoxedRef::from(bgra),
+            data: vec![],
+        })
     }
 
     #[inline]
@@ -56,3 +94,67 @@ impl MatTraitConst for OwnedMat {
         self.mat.as_raw_Mat()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use opencv::core::Vec4b;
+
+    use super::*;
+
+    fn frame(format: PixelFormat, data: Vec<u8>) -> Frame {
+        Frame {
+            width: 2,
+            height: 2,
+            data,
+            format,
+        }
+    }
+
+    #[test]
+    fn new_from_frame_converts_to_bgra() {
+        let bgra =
+            OwnedMat::new_from_frame(frame(PixelFormat::Bgra, [1, 2, 3, 255].repeat(4))).unwrap();
+        let rgba =
+            OwnedMat::new_from_frame(frame(PixelFormat::Rgba, [3, 2, 1, 255].repeat(4))).unwrap();
+        let rgb = OwnedMat::new_from_frame(frame(PixelFormat::Rgb, [3, 2, 1].repeat(4))).unwrap();
+
+        for mat in [bgra, rgba, rgb] {
+            assert_eq!(mat.typ(), CV_8UC4);
+            assert_eq!(
+                *mat.at_2d::<Vec4b>(1, 1).unwrap(),
+                Vec4b::from([1, 2, 3, 255])
+            );
+        }
+    }
+
+    #[test]
+    fn new_from_frame_converts_nv12_to_bgra() {
+        // Luma plane of 4 pixels followed by a single neutral chroma pair
+        let mat =
+            OwnedMat::new_from_frame(frame(PixelFormat::Nv12, vec![128, 128, 128, 128, 128, 128]))
+                .unwrap();
+
+        assert_eq!(mat.typ(), CV_8UC4);
+        assert_eq!((mat.cols(), mat.rows()), (2, 2));
+        let pixel = *mat.at_2d::<Vec4b>(0, 0).unwrap();
+        assert!(pixel[0].abs_diff(pixel[2]) <= 1);
+    }
+
+    #[test]
+    fn new_from_frame_invalid_data_length() {
+        assert!(OwnedMat::new_from_frame(frame(PixelFormat::Rgba, vec![0; 12])).is_err());
+        assert!(OwnedMat::new_from_frame(frame(PixelFormat::Nv12, vec![0; 4])).is_err());
+    }
+
+    #[test]
+    fn new_from_frame_odd_nv12_size() {
+        let frame = Frame {
+            width: 3,
+            height: 2,
+            data: vec![0; 9],
+            format: PixelFormat::Nv12,
+        };
+
+        assert!(OwnedMat::new_from_frame(frame).is_err());
+    }
+}
@@ -20,7 +20,7 @@ use crate::{
     task::{Task, Update, update_detection_task},
 };
 
-const MINIMAP_BORDER_WHITENESS_THRESHOLD: u8 = 160;
+pub const MINIMAP_BORDER_WHITENESS_THRESHOLD: u8 = 160;
 const MAX_PORTALS_COUNT: usize = 16;
 
 /// A wrapper struct for [`Rect`] that implements [`Hash`].
@@ -60,6 +60,12 @@ pub struct MinimapState {
     has_stranger_player_task: Option<Task<Result<()>>>,
     /// Task to detect firend player(s) in the minimap.
     has_friend_player_task: Option<Task<Result<()>>>,
+    /// Task to recognize the current minimap's name.
+    name_task: Option<Task<Result<String>>>,
+    /// The recognized name of the current minimap.
+    name: Option<String>,
+    /// The recognized name of the minimap before the last time minimap is re-detected.
+    previous_name: Option<String>,
 
     platforms: Vec<Platform>,
     /// Whether to update the [`MinimapIdle::platforms`].
@@ -78,6 +84,26 @@ impl MinimapState {
         self.platforms = platforms;
         self.platforms_dirty = true;
     }
+
+    /// Gets the recognized name of the current minimap.
+    #[inline]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    #[cfg(test)]
+    pub fn set_name(&mut self, name: String) {
+        self.name = Some(name);
+    }
+
+    /// Gets the recognized name of the minimap before the current one.
+    ///
+    /// This can be the same as [`Self::name`] if the minimap was re-detected without changing map
+    /// (e.g. the minimap UI was moved).
+    #[inline]
+    pub fn previous_name(&self) -> Option<&str> {
+        self.previous_name.as_deref()
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -241,6 +267,10 @@ fn update_detecting_context(context: &Context, state: &mut MinimapState) -> Mini
     state.has_guildie_player_task = None;
     state.has_stranger_player_task = None;
     state.has_friend_player_task = None;
+    state.name_task = None;
+    if state.name.is_some() {
+        state.previous_name = state.name.take();
+    }
 
     Minimap::Idle(MinimapIdle {
         anchors,
@@ -325,6 +355,9 @@ fn update_idle_context(
         portals,
         bbox,
     );
+    if !partially_overlapping {
+        update_name_task(context, state, bbox);
+    }
 
     if state.platforms_dirty {
         let (updated_platforms, updated_bound) = platforms_and_bound(bbox, &state.platforms);
@@ -358,6 +391,21 @@ fn anchor_match(anchor: Vec4b, pixel: Vec4b) -> bool {
     avg <= ANCHOR_ACCEPTABLE_ERROR_RANGE
 }
 
+#[inline]
+fn update_name_task(context: &Context, state: &mut MinimapState, minimap: Rect) {
+    if state.name.is_some() {
+        return;
+    }
+
+    let update = update_detection_task(context, 3000, &mut state.name_task, move |detector| {
+        detector.detect_minimap_name_text(minimap)
+    });
+    if let Update::Ok(name) = update {
+        info!(target: "minimap", "minimap name recognized as {name}");
+        state.name = Some(name);
+    }
+}
+
 #[inline]
 fn update_rune_task(
     context: &Context,
@@ -642,6 +690,10 @@ mod tests {
             .expect_detect_minimap_rune()
             .withf(move |b| *b == bbox)
             .returning(move |_| Ok(rune_bbox));
+        detector
+            .expect_detect_minimap_name_text()
+            .withf(move |b| *b == bbox)
+            .returning(|_| Ok("Minimap".to_string()));
         detector
             .expect_clone()
             .returning(|| create_mock_detector().0);
@@ -733,6 +785,30 @@ mod tests {
         }
     }
 
+    #[tokio::test(start_paused = true)]
+    async fn minimap_idle_name_recognition() {
+        let mut state = MinimapState {
+            name: Some("Previous".to_string()),
+            ..MinimapState::default()
+        };
+        let (detector, _, _, _) = create_mock_detector();
+        let context = Context::new(None, Some(detector));
+
+        let mut minimap = update_context(Minimap::Detecting, &context, &mut state);
+        while !matches!(minimap, Minimap::Idle(_)) {
+            time::advance(Duration::from_millis(1000)).await;
+            minimap = update_context(minimap, &context, &mut state);
+        }
+        assert_eq!(state.name(), None);
+        assert_eq!(state.previous_name(), Some("Previous"));
+
+        while state.name().is_none() {
+            time::advance(Duration::from_millis(1000)).await;
+            minimap = update_context(minimap, &context, &mut state);
+        }
+        assert_eq!(state.name(), Some("Minimap"));
+    }
+
     fn rect(x: i32, y: i32, w: i32, h: i32) -> Rect {
         Rect::new(x, y, w, h)
     }
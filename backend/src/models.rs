@@ -0,0 +1,356 @@
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+use anyhow::{Result, anyhow, bail};
+use log::{error, info};
+use opencv::{core::Vector, dnn::read_net_from_onnx_buffer};
+use ort::{
+    execution_providers::CUDAExecutionProvider, session::Session, tensor::TensorElementType,
+    value::ValueType,
+};
+use sha2::{Digest, Sha256};
+use strum::{Display, EnumIter, IntoEnumIterator};
+
+/// The directory next to the executable to look for override models.
+const MODELS_DIR: &str = "models";
+
+/// The environment variable to override [`MODELS_DIR`] location.
+const MODELS_ENV: &str = "KOMARI_MODELS";
+
+/// The input size of YOLO models as expected by `preprocess_for_yolo`.
+const YOLO_INPUT_SIZE: i64 = 640;
+
+/// The minimum prediction length of YOLO models as expected by `remap_from_yolo`.
+///
+/// A prediction has shape `[bbox(4) + score(1) + class(1)]`.
+const YOLO_PREDICTION_LEN: i64 = 6;
+
+/// The number of channels of the text detection output (text score and link score).
+const TEXT_DETECTION_OUTPUT_CHANNELS: i64 = 2;
+
+/// The input width of the text recognition model as expected by `extract_texts`.
+const TEXT_RECOGNITION_INPUT_WIDTH: i64 = 100;
+
+/// The input height of the text recognition model as expected by `extract_texts`.
+const TEXT_RECOGNITION_INPUT_HEIGHT: i64 = 32;
+
+/// Models chosen at startup, either override models from [`MODELS_DIR`] or the embedded ones.
+static MODELS: LazyLock<HashMap<ModelKind, Model>> = LazyLock::new(|| {
+    let dir = models_dir();
+    ModelKind::iter()
+        .map(|kind| (kind, Model::load(kind, &dir)))
+        .collect()
+});
+
+/// The kind of detection model.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display, EnumIter)]
+pub enum ModelKind {
+    Mob,
+    Minimap,
+    Rune,
+    TextDetection,
+    TextRecognition,
+}
+
+impl ModelKind {
+    #[inline]
+    fn file_name(&self) -> &'static str {
+        match self {
+            ModelKind::Mob => "mob_nms.onnx",
+            ModelKind::Minimap => "minimap_nms.onnx",
+            ModelKind::Rune => "rune_nms.onnx",
+            ModelKind::TextDetection => "text_detection.onnx",
+            ModelKind::TextRecognition => "text_recognition.onnx",
+        }
+    }
+
+    #[inline]
+    fn embedded_bytes(&self) -> &'static [u8] {
+        match self {
+            ModelKind::Mob => include_bytes!(env!("MOB_MODEL")),
+            ModelKind::Minimap => include_bytes!(env!("MINIMAP_MODEL")),
+            ModelKind::Rune => include_bytes!(env!("RUNE_MODEL")),
+            ModelKind::TextDetection => include_bytes!(env!("TEXT_DETECTION_MODEL")),
+            ModelKind::TextRecognition => include_bytes!(env!("TEXT_RECOGNITION_MODEL")),
+        }
+    }
+}
+
+/// Information about an active model.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ModelInfo {
+    pub kind: ModelKind,
+    /// The path of the override model or [`None`] if the embedded model is used.
+    pub path: Option<PathBuf>,
+    /// A short hash of the model content identifying its version.
+    pub version: String,
+    /// The reason the override model was rejected in favor of the embedded model.
+    pub error: Option<String>,
+}
+
+#[derive(Debug)]
+struct Model {
+    bytes: &'static [u8],
+    info: ModelInfo,
+}
+
+impl Model {
+    fn load(kind: ModelKind, dir: &Path) -> Model {
+        let path = dir.join(kind.file_name());
+        let mut error = None;
+
+        if path.exists() {
+            match fs::read(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|bytes| validate_model(kind, &bytes).map(|_| bytes))
+            {
+                Ok(bytes) => {
+                    info!(target: "models", "using override {kind} model {}", path.display());
+                    let bytes = Vec::leak(bytes);
+                    return Model {
+                        bytes,
+                        info: ModelInfo {
+                            kind,
+                            path: Some(path),
+                            version: version_from(bytes),
+                            error: None,
+                        },
+                    };
+                }
+                Err(err) => {
+                    error!(target: "models", "rejected override {kind} model {}: {err}", path.display());
+                    error = Some(err.to_string());
+                }
+            }
+        }
+
+        let bytes = kind.embedded_bytes();
+        Model {
+            bytes,
+            info: ModelInfo {
+                kind,
+                path: None,
+                version: version_from(bytes),
+                error,
+            },
+        }
+    }
+}
+
+/// Loads the models and validates the override ones if not already.
+#[inline]
+pub fn init() {
+    LazyLock::force(&MODELS);
+}
+
+/// Retrieves information about all active models.
+pub fn query_models() -> Vec<ModelInfo> {
+    ModelKind::iter()
+        .map(|kind| MODELS.get(&kind).unwrap().info.clone())
+        .collect()
+}
+
+/// Retrieves the active model bytes of `kind`.
+#[inline]
+pub fn model_bytes(kind: ModelKind) -> &'static [u8] {
+    MODELS.get(&kind).unwrap().bytes
+}
+
+/// Builds an ONNX session for the active model of `kind`.
+#[inline]
+pub fn build_session(kind: ModelKind) -> Result<Session> {
+    build_session_from(model_bytes(kind))
+}
+
+fn build_session_from(model: &[u8]) -> Result<Session> {
+    // TODO: ort supports fallback to CPU if GPU is not found. Check if missing GPU-related
+    // TODO: onnxruntime dlls affect this.
+    if cfg!(feature = "gpu") {
+        Ok(Session::builder()?
+            .with_execution_providers([CUDAExecutionProvider::default().build()])?
+            .commit_from_memory(model)?)
+    } else {
+        Ok(Session::builder()?.commit_from_memory(model)?)
+    }
+}
+
+/// Validates `bytes` of model `kind` can be loaded and has the input and output shapes expected
+/// by detection.
+fn validate_model(kind: ModelKind, bytes: &[u8]) -> Result<()> {
+    if matches!(kind, ModelKind::TextRecognition) {
+        // Loaded through OpenCV instead of ONNX Runtime but shapes are still checked below
+        read_net_from_onnx_buffer(&Vector::from_slice(bytes))?;
+    }
+
+    let session = build_session_from(bytes)?;
+    let input = session
+        .inputs
+        .first()
+        .ok_or(anyhow!("model has no input"))?;
+    let input_shape = float_tensor_shape(&input.input_type)?;
+    let output = if matches!(kind, ModelKind::TextRecognition) {
+        session
+            .outputs
+            .first()
+            .ok_or(anyhow!("model has no output"))?
+    } else {
+        session
+            .outputs
+            .iter()
+            .find(|output| output.name == "output0")
+            .ok_or(anyhow!("model has no output0 output"))?
+    };
+    let output_shape = float_tensor_shape(&output.output_type)?;
+
+    // Input is [1, 3, H, W] as converted by `to_input_value` or OpenCV `TextRecognitionModel`
+    if input_shape.len() != 4 || !dim_matches(input_shape[1], 3) {
+        bail!("expected input shape [1, 3, H, W] but got {input_shape:?}");
+    }
+
+    match kind {
+        ModelKind::Mob | ModelKind::Minimap | ModelKind::Rune => {
+            if !dim_matches(input_shape[2], YOLO_INPUT_SIZE)
+                || !dim_matches(input_shape[3], YOLO_INPUT_SIZE)
+            {
+                bail!(
+                    "expected input size {YOLO_INPUT_SIZE}x{YOLO_INPUT_SIZE} but got {input_shape:?}"
+                );
+            }
+            // Output is [1, N, 6] with NMS applied
+            if output_shape.len() != 3
+                || (output_shape[2] >= 0 && output_shape[2] < YOLO_PREDICTION_LEN)
+            {
+                bail!(
+                    "expected output shape [1, N, {YOLO_PREDICTION_LEN}] but got {output_shape:?}"
+                );
+            }
+        }
+        ModelKind::TextDetection => {
+            // Output is [1, H / 2, W / 2, 2]
+            if output_shape.len() != 4
+                || !dim_matches(output_shape[3], TEXT_DETECTION_OUTPUT_CHANNELS)
+            {
+                bail!(
+                    "expected output shape [1, H, W, {TEXT_DETECTION_OUTPUT_CHANNELS}] but got {output_shape:?}"
+                );
+            }
+        }
+        ModelKind::TextRecognition => {
+            if !dim_matches(input_shape[2], TEXT_RECOGNITION_INPUT_HEIGHT)
+                || !dim_matches(input_shape[3], TEXT_RECOGNITION_INPUT_WIDTH)
+            {
+                bail!(
+                    "expected input size {TEXT_RECOGNITION_INPUT_WIDTH}x{TEXT_RECOGNITION_INPUT_HEIGHT} but got {input_shape:?}"
+                );
+            }
+            // Output is [T, 1, C] for CTC decoding with C being the alphabet plus the blank
+            let classes = text_recognition_classes();
+            if output_shape.len() != 3 || !dim_matches(output_shape[2], classes) {
+                bail!("expected output shape [T, 1, {classes}] but got {output_shape:?}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The number of classes of the text recognition model output.
+#[inline]
+fn text_recognition_classes() -> i64 {
+    include_str!(env!("TEXT_RECOGNITION_ALPHABET"))
+        .lines()
+        .count() as i64
+        + 1
+}
+
+#[inline]
+fn float_tensor_shape(value_type: &ValueType) -> Result<Vec<i64>> {
+    match value_type {
+        ValueType::Tensor {
+            ty: TensorElementType::Float32,
+            shape,
+            ..
+        } => Ok(shape.to_vec()),
+        _ => bail!("expected a float32 tensor but got {value_type:?}"),
+    }
+}
+
+/// Whether dimension `dim` matches `expected`, treating dynamic dimension as matched.
+#[inline]
+fn dim_matches(dim: i64, expected: i64) -> bool {
+    dim < 0 || dim == expected
+}
+
+#[inline]
+fn version_from(bytes: &[u8]) -> String {
+    // A stable digest so that the version stays the same across builds and Rust versions
+    Sha256::digest(bytes)
+        .iter()
+        .take(4)
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+fn models_dir() -> PathBuf {
+    env::var(MODELS_ENV)
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            env::current_exe()
+                .unwrap()
+                .parent()
+                .unwrap()
+                .join(MODELS_DIR)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dim_matches_dynamic() {
+        assert!(dim_matches(-1, 640));
+        assert!(dim_matches(640, 640));
+        assert!(!dim_matches(320, 640));
+    }
+
+    #[test]
+    fn version_from_stable_digest() {
+        assert_eq!(version_from(b"komari"), version_from(b"komari"));
+        assert_eq!(version_from(b""), "e3b0c442");
+    }
+
+    #[test]
+    fn load_rejects_invalid_override() {
+        let dir = env::temp_dir().join("komari-models-invalid");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(ModelKind::Mob.file_name()), b"not a model").unwrap();
+
+        let model = Model::load(ModelKind::Mob, &dir);
+
+        assert_eq!(model.info.path, None);
+        assert!(model.info.error.is_some());
+        assert_eq!(model.bytes, ModelKind::Mob.embedded_bytes());
+    }
+
+    #[test]
+    fn load_embedded_without_override() {
+        let dir = env::temp_dir().join("komari-models-none");
+        let _ = fs::remove_dir_all(&dir);
+
+        let model = Model::load(ModelKind::Minimap, &dir);
+
+        assert_eq!(model.info.path, None);
+        assert_eq!(model.info.error, None);
+        assert_eq!(
+            model.info.version,
+            version_from(ModelKind::Minimap.embedded_bytes())
+        );
+    }
+}
@@ -588,7 +588,7 @@ fn find_current_from_base_path(
         .ok_or(anyhow!("unable to determine current path"))
 }
 
-fn decode_base64_to_mat(base64: &str, grayscale: bool) -> Result<Mat> {
+pub(crate) fn decode_base64_to_mat(base64: &str, grayscale: bool) -> Result<Mat> {
     let flag = if grayscale {
         IMREAD_GRAYSCALE
     } else {
@@ -32,6 +32,8 @@ pub enum NotificationKind {
     PlayerStrangerAppear,
     PlayerFriendAppear,
     PlayerIsDead,
+    InputDisconnected,
+    InputReconnected,
 }
 
 impl From<NotificationKind> for usize {
@@ -99,6 +101,32 @@ impl DiscordNotification {
     }
 
     pub fn schedule_notification(&self, kind: NotificationKind) -> Result<(), Error> {
+        self.schedule_notification_with(kind, None)
+    }
+
+    /// Schedules a [`NotificationKind::FailOrMapChange`] notification reporting the map has
+    /// changed `from` a map name `to` another.
+    ///
+    /// A map name is reported as unknown if it is `None`.
+    pub fn schedule_map_changed_notification(
+        &self,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> Result<(), Error> {
+        let from = from.unwrap_or("an unknown map");
+        let to = to.unwrap_or("an unknown map");
+        self.schedule_notification_with(
+            NotificationKind::FailOrMapChange,
+            Some(format!("the map has changed from {from} to {to}")),
+        )
+    }
+
+    /// Schedules a notification of `kind` with an optional `reason` describing the notification.
+    fn schedule_notification_with(
+        &self,
+        kind: NotificationKind,
+        reason: Option<String>,
+    ) -> Result<(), Error> {
         let settings = self.settings.borrow();
         let is_enabled = match kind {
             NotificationKind::FailOrMapChange => {
@@ -116,6 +144,11 @@ impl DiscordNotification {
             NotificationKind::PlayerFriendAppear => {
                 settings.notifications.notify_on_player_friend_appear
             }
+            NotificationKind::InputDisconnected | NotificationKind::InputReconnected => {
+                settings
+                    .notifications
+                    .notify_on_input_disconnect_or_reconnect
+            }
         };
         if !is_enabled {
             bail!("notification not enabled");
@@ -143,12 +176,14 @@ impl DiscordNotification {
             .unwrap_or_default();
         let content = match kind {
             NotificationKind::FailOrMapChange => {
-                if self.settings.borrow().stop_on_fail_or_change_map {
-                    format!(
+                let stopped = self.settings.borrow().stop_on_fail_or_change_map;
+                match reason {
+                    Some(reason) if stopped => format!("{user_id}Bot stopped because {reason}"),
+                    Some(reason) => format!("{user_id}Bot has detected {reason}"),
+                    None if stopped => format!(
                         "{user_id}Bot stopped because it has failed to detect or the map has changed"
-                    )
-                } else {
-                    format!("{user_id}Bot has failed to detect or the map has changed")
+                    ),
+                    None => format!("{user_id}Bot has failed to detect or the map has changed"),
                 }
             }
             NotificationKind::RuneAppear => {
@@ -169,6 +204,12 @@ impl DiscordNotification {
             NotificationKind::PlayerFriendAppear => {
                 format!("{user_id}Bot has detected friend player(s)")
             }
+            NotificationKind::InputDisconnected => {
+                format!("{user_id}Bot stopped because the input server has disconnected")
+            }
+            NotificationKind::InputReconnected => {
+                format!("{user_id}The input server has reconnected")
+            }
         };
         let frames = match kind {
             NotificationKind::FailOrMapChange => vec![(None, 2), (None, 4)],
@@ -177,6 +218,8 @@ impl DiscordNotification {
             | NotificationKind::PlayerGuildieAppear
             | NotificationKind::PlayerStrangerAppear
             | NotificationKind::PlayerFriendAppear
+            | NotificationKind::InputDisconnected
+            | NotificationKind::InputReconnected
             | NotificationKind::RuneAppear => vec![(None, 2)],
         };
         let delay = match kind {
@@ -186,6 +229,8 @@ impl DiscordNotification {
             | NotificationKind::PlayerGuildieAppear
             | NotificationKind::PlayerStrangerAppear
             | NotificationKind::PlayerFriendAppear
+            | NotificationKind::InputDisconnected
+            | NotificationKind::InputReconnected
             | NotificationKind::RuneAppear => 3,
         };
 
@@ -326,6 +371,28 @@ mod test {
         );
     }
 
+    #[tokio::test(start_paused = true)]
+    async fn schedule_map_changed_with_names() {
+        let noti = DiscordNotification::new(Rc::new(RefCell::new(Settings {
+            notifications: Notifications {
+                discord_webhook_url: "https://discord.com/api/webhooks/foo/bar".to_string(),
+                notify_on_fail_or_change_map: true,
+                ..Default::default()
+            },
+            stop_on_fail_or_change_map: true,
+            ..Default::default()
+        })));
+
+        assert!(
+            noti.schedule_map_changed_notification(Some("Cavern Upper Path"), None)
+                .is_ok()
+        );
+        assert_eq!(
+            noti.scheduled.lock().unwrap().first().unwrap().content,
+            "Bot stopped because the map has changed from Cavern Upper Path to an unknown map"
+        );
+    }
+
     #[tokio::test(start_paused = true)]
     async fn schedule_invalid_url() {
         let noti = DiscordNotification::new(Rc::new(RefCell::new(Settings {
@@ -0,0 +1,232 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, bail};
+use opencv::{
+    core::Mat,
+    videoio::{
+        CAP_ANY, CAP_PROP_FPS, CAP_PROP_POS_FRAMES, VideoCapture, VideoCaptureTrait,
+        VideoCaptureTraitConst,
+    },
+};
+use platforms::{Error, capture::Frame};
+
+use crate::{
+    CapturePlayback,
+    context::MS_PER_TICK_F32,
+    replay::{frame_from, frame_from_bgr, frame_paths_from},
+};
+
+#[derive(Debug)]
+enum PlaybackSource {
+    Frames(Vec<PathBuf>),
+    Video {
+        capture: VideoCapture,
+        /// The index of the next frame to be decoded.
+        next_index: usize,
+        /// The last decoded frame reused while playing back slower than the video.
+        last_frame: Option<Frame>,
+    },
+}
+
+/// Plays back frames from an image directory or a video file as if capturing a window.
+///
+/// Each [`Self::grab`] advances the playback by one tick scaled by [`CapturePlayback::speed`].
+/// For a video, the tick is also scaled by the video's FPS so that it plays back in real time at
+/// speed `1.0`.
+#[derive(Debug)]
+pub struct PlaybackCapture {
+    source: PlaybackSource,
+    position: f64,
+    step: f64,
+    looping: bool,
+}
+
+impl PlaybackCapture {
+    pub fn new(playback: &CapturePlayback) -> Result<Self> {
+        if playback.speed <= 0.0 {
+            bail!("playback speed must be positive");
+        }
+        let path = Path::new(&playback.path);
+        let speed = playback.speed as f64;
+
+        let (source, step) = if path.is_dir() {
+            (PlaybackSource::Frames(frame_paths_from(path)?), speed)
+        } else {
+            let capture = VideoCapture::from_file(&playback.path, CAP_ANY)?;
+            if !capture.is_opened()? {
+                bail!("failed to open video {}", playback.path);
+            }
+            // Some containers do not report FPS, assumes same as tick rate
+            let fps = capture.get(CAP_PROP_FPS)?;
+            let step = if fps > 0.0 {
+                speed * fps * MS_PER_TICK_F32 as f64 / 1000.0
+            } else {
+                speed
+            };
+            let source = PlaybackSource::Video {
+                capture,
+                next_index: 0,
+                last_frame: None,
+            };
+
+            (source, step)
+        };
+
+        Ok(Self {
+            source,
+            position: 0.0,
+            step,
+            looping: playback.looping,
+        })
+    }
+
+    pub fn grab(&mut self) -> Result<Frame, Error> {
+        let frame = match self.next_frame() {
+            Some(frame) => Some(frame),
+            None if self.looping => {
+                self.rewind();
+                self.next_frame()
+            }
+            None => None,
+        };
+
+        frame.ok_or(Error::WindowFrameNotAvailable)
+    }
+
+    fn next_frame(&mut self) -> Option<Frame> {
+        let index = self.position as usize;
+        let frame = match &mut self.source {
+            PlaybackSource::Frames(paths) => frame_from(paths.get(index)?),
+            PlaybackSource::Video {
+                capture,
+                next_index,
+                last_frame,
+            } => {
+                // Reuses the last frame without decoding when playing back slower than the video
+                if index < *next_index {
+                    last_frame.clone()
+                } else {
+                    // Skips frames without decoding when playing back faster than the video
+                    while *next_index < index {
+                        if !capture.grab().ok()? {
+                            return None;
+                        }
+                        *next_index += 1;
+                    }
+
+                    let mut mat = Mat::default();
+                    if !capture.read(&mut mat).ok()? {
+                        return None;
+                    }
+                    *next_index += 1;
+                    *last_frame = frame_from_bgr(&mat);
+                    last_frame.clone()
+                }
+            }
+        }?;
+        self.position += self.step;
+
+        Some(frame)
+    }
+
+    fn rewind(&mut self) {
+        self.position = 0.0;
+        if let PlaybackSource::Video {
+            capture,
+            next_index,
+            last_frame,
+        } = &mut self.source
+        {
+            let _ = capture.set(CAP_PROP_POS_FRAMES, 0.0);
+            *next_index = 0;
+            *last_frame = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use opencv::{
+        core::{CV_8UC3, Scalar, Size, Vector},
+        imgcodecs::imwrite,
+        videoio::{VideoWriter, VideoWriterTrait},
+    };
+
+    use super::*;
+
+    fn playback(name: &str, count: usize, speed: f32, looping: bool) -> CapturePlayback {
+        let dir = env::temp_dir().join(format!("komari-playback-{name}"));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for i in 0..count {
+            let mat =
+                Mat::new_rows_cols_with_default(4, 8, CV_8UC3, Scalar::all(i as f64)).unwrap();
+            let path = dir.join(format!("{i}.png"));
+            imwrite(path.to_str().unwrap(), &mat, &Vector::new()).unwrap();
+        }
+
+        CapturePlayback {
+            path: dir.to_string_lossy().into_owned(),
+            speed,
+            looping,
+        }
+    }
+
+    #[test]
+    fn playback_capture_speed_and_end() {
+        let mut capture = PlaybackCapture::new(&playback("speed", 5, 2.0, false)).unwrap();
+
+        for i in [0u8, 2, 4] {
+            assert_eq!(&capture.grab().unwrap().data[..4], &[i, i, i, 255]);
+        }
+        assert_eq!(capture.grab().unwrap_err(), Error::WindowFrameNotAvailable);
+    }
+
+    #[test]
+    fn playback_capture_video_slower_repeats_frames() {
+        let path = env::temp_dir().join("komari-playback-slow.avi");
+        let mut writer = VideoWriter::new(
+            path.to_str().unwrap(),
+            VideoWriter::fourcc('M', 'J', 'P', 'G').unwrap(),
+            1000.0 / MS_PER_TICK_F32 as f64,
+            Size::new(16, 16),
+            true,
+        )
+        .unwrap();
+        for value in [0.0, 100.0, 200.0] {
+            let mat = Mat::new_rows_cols_with_default(16, 16, CV_8UC3, Scalar::all(value)).unwrap();
+            writer.write(&mat).unwrap();
+        }
+        writer.release().unwrap();
+        let playback = CapturePlayback {
+            path: path.to_string_lossy().into_owned(),
+            speed: 0.5,
+            looping: false,
+        };
+        let mut capture = PlaybackCapture::new(&playback).unwrap();
+
+        // Lossy compression only changes the values slightly
+        for value in [0u8, 0, 100, 100, 200, 200] {
+            let frame = capture.grab().unwrap();
+            assert!(frame.data[0].abs_diff(value) <= 10);
+        }
+        assert_eq!(capture.grab().unwrap_err(), Error::WindowFrameNotAvailable);
+    }
+
+    #[test]
+    fn playback_capture_looping() {
+        let mut capture = PlaybackCapture::new(&playback("looping", 2, 1.0, true)).unwrap();
+
+        for i in [0u8, 1, 0, 1] {
+            assert_eq!(&capture.grab().unwrap().data[..4], &[i, i, i, 255]);
+        }
+    }
+
+    #[test]
+    fn playback_capture_invalid_options() {
+        assert!(PlaybackCapture::new(&playback("invalid-speed", 1, 0.0, true)).is_err());
+        assert!(PlaybackCapture::new(&playback("empty", 0, 1.0, true)).is_err());
+    }
+}
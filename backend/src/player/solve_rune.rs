@@ -4,8 +4,8 @@ use super::{
     timeout::{Lifecycle, next_timeout_lifecycle},
 };
 use crate::{
-    bridge::KeyKind,
-    context::Context,
+    bridge::{InputEvent, KeyKind},
+    context::{Context, MS_PER_TICK},
     detect::{ArrowsCalibrating, ArrowsState},
     player::{on_action_state_mut, timeout::Timeout},
 };
@@ -23,7 +23,7 @@ pub enum RuneStage {
     // Solves for the rune arrows that possibly include spinning arrows.
     Solving(ArrowsCalibrating, Timeout),
     // Presses the keys.
-    PressKeys(Timeout, [KeyKind; 4], usize),
+    PressKeys(Timeout, [KeyKind; 4]),
     // Terminal stage.
     Completed,
 }
@@ -62,14 +62,9 @@ impl SolvingRune {
     }
 
     #[inline]
-    fn stage_press_keys(
-        self,
-        timeout: Timeout,
-        keys: [KeyKind; 4],
-        key_index: usize,
-    ) -> SolvingRune {
+    fn stage_press_keys(self, timeout: Timeout, keys: [KeyKind; 4]) -> SolvingRune {
         SolvingRune {
-            stage: RuneStage::PressKeys(timeout, keys, key_index),
+            stage: RuneStage::PressKeys(timeout, keys),
         }
     }
 
@@ -117,8 +112,8 @@ pub fn update_solving_rune_context(
         RuneStage::Solving(calibrating, timeout) => {
             update_solving(context, solving_rune, calibrating, timeout)
         }
-        RuneStage::PressKeys(timeout, keys, key_index) => {
-            update_press_keys(context, solving_rune, timeout, keys, key_index)
+        RuneStage::PressKeys(timeout, keys) => {
+            update_press_keys(context, solving_rune, timeout, keys)
         }
         RuneStage::Completed => unreachable!(),
     };
@@ -235,7 +230,7 @@ fn update_solving(
                     context
                         .debug
                         .set_last_rune_result(context.detector_cloned_unwrap(), pairs);
-                    solving_rune.stage_press_keys(Timeout::default(), pairs.map(|(_, key)| key), 0)
+                    solving_rune.stage_press_keys(Timeout::default(), pairs.map(|(_, key)| key))
                 }
                 Err(_) => solving_rune.stage_completed(),
             }
@@ -248,23 +243,23 @@ fn update_press_keys(
     solving_rune: SolvingRune,
     timeout: Timeout,
     keys: [KeyKind; 4],
-    key_index: usize,
 ) -> SolvingRune {
     const PRESS_KEY_INTERVAL: u32 = 8;
 
-    match next_timeout_lifecycle(timeout, PRESS_KEY_INTERVAL) {
+    match next_timeout_lifecycle(timeout, PRESS_KEY_INTERVAL * keys.len() as u32) {
         Lifecycle::Started(timeout) => {
-            let _ = context.input.send_key(keys[key_index]);
-            solving_rune.stage_press_keys(timeout, keys, key_index)
-        }
-        Lifecycle::Ended => {
-            if key_index + 1 < keys.len() {
-                solving_rune.stage_press_keys(Timeout::default(), keys, key_index + 1)
-            } else {
-                solving_rune.stage_completed()
-            }
+            // Sends all keys at once as the whole sequence is already known
+            let wait = InputEvent::Wait(PRESS_KEY_INTERVAL as u64 * MS_PER_TICK);
+            let events = keys
+                .into_iter()
+                .flat_map(|key| [wait, InputEvent::Key(key)])
+                .skip(1)
+                .collect::<Vec<_>>();
+            let _ = context.input.send_sequence(&events);
+            solving_rune.stage_press_keys(timeout, keys)
         }
-        Lifecycle::Updated(timeout) => solving_rune.stage_press_keys(timeout, keys, key_index),
+        Lifecycle::Ended => solving_rune.stage_completed(),
+        Lifecycle::Updated(timeout) => solving_rune.stage_press_keys(timeout, keys),
     }
 }
 
@@ -273,7 +268,6 @@ mod tests {
     use std::assert_matches::assert_matches;
 
     use anyhow::{Ok, anyhow};
-    use mockall::predicate::eq;
     use opencv::core::Rect;
 
     use super::*;
@@ -511,60 +505,62 @@ mod tests {
                         current: 0,
                         ..
                     },
-                    [KeyKind::A, KeyKind::S, KeyKind::D, KeyKind::F],
-                    0
+                    [KeyKind::A, KeyKind::S, KeyKind::D, KeyKind::F]
                 )
             }
         );
     }
 
     #[test]
-    fn update_press_keys_to_completed_after_all_keys_sent() {
+    fn update_press_keys_send_sequence_and_completed() {
         let expected_keys = [KeyKind::A, KeyKind::S, KeyKind::D, KeyKind::F];
-        let mut key_index = 0;
-
-        // Simulate 4 rounds of key pressing
-        for _ in 0..4 {
-            let mut keys = MockInput::default();
-            keys.expect_send_key()
-                .with(eq(expected_keys[key_index]))
-                .return_once(|_| Ok(()));
-            let context = Context::new(Some(keys), None);
-
-            // Press the key
-            update_press_keys(
-                &context,
-                SolvingRune::default(),
-                Timeout::default(),
-                expected_keys,
-                key_index,
-            );
-            // Timing out and advance key index or complete
-            let end_result = update_press_keys(
-                &context,
-                SolvingRune::default(),
-                Timeout {
-                    current: 8,
-                    started: true,
-                    ..Default::default()
-                },
-                expected_keys,
-                key_index,
-            );
-
-            if key_index == expected_keys.len() - 1 {
-                assert_matches!(
-                    end_result,
-                    SolvingRune {
-                        stage: RuneStage::Completed
-                    }
-                );
-            } else {
-                key_index = match end_result.stage {
-                    RuneStage::PressKeys(_, _, index) => index,
-                    _ => unreachable!(),
-                }
+        let wait = InputEvent::Wait(8 * MS_PER_TICK);
+        let expected_events = [
+            InputEvent::Key(KeyKind::A),
+            wait,
+            InputEvent::Key(KeyKind::S),
+            wait,
+            InputEvent::Key(KeyKind::D),
+            wait,
+            InputEvent::Key(KeyKind::F),
+        ];
+        let mut keys = MockInput::default();
+        keys.expect_send_sequence()
+            .withf(move |events| events == expected_events)
+            .once()
+            .returning(|_| Ok(()));
+        let context = Context::new(Some(keys), None);
+
+        // Press the keys
+        let result = update_press_keys(
+            &context,
+            SolvingRune::default(),
+            Timeout::default(),
+            expected_keys,
+        );
+        assert_matches!(
+            result,
+            SolvingRune {
+                stage: RuneStage::PressKeys(Timeout { started: true, .. }, _)
             }
-        }
+        );
+
+        // Timing out after all keys are pressed
+        let result = update_press_keys(
+            &context,
+            SolvingRune::default(),
+            Timeout {
+                current: 32,
+                started: true,
+                ..Default::default()
+            },
+            expected_keys,
+        );
+        assert_matches!(
+            result,
+            SolvingRune {
+                stage: RuneStage::Completed
+            }
+        );
     }
 }
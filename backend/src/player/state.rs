@@ -26,6 +26,9 @@ use crate::{
 /// [`Player::CashShopThenExit`].
 const MAX_RUNE_FAILED_COUNT: u32 = 8;
 
+/// The interval in milliseconds to update the player EXP percentage.
+const UPDATE_EXP_MILLIS: u64 = 5000;
+
 /// The maximum number of times horizontal movement can be repeated in non-auto-mobbing action.
 const HORIZONTAL_MOVEMENT_REPEAT_COUNT: u32 = 20;
 
@@ -151,7 +154,13 @@ pub struct PlayerConfiguration {
     /// Uses potion when health is below a percentage.
     pub use_potion_below_percent: Option<f32>,
     /// Milliseconds interval to update current health.
+    ///
+    /// This interval is also used for updating current mana.
     pub update_health_millis: Option<u64>,
+    /// The mana potion key.
+    pub mana_potion_key: KeyKind,
+    /// Uses mana potion when mana is below a percentage.
+    pub use_mana_potion_below_percent: Option<f32>,
 }
 
 impl Default for PlayerConfiguration {
@@ -178,6 +187,8 @@ impl Default for PlayerConfiguration {
             potion_key: KeyKind::A,
             use_potion_below_percent: Default::default(),
             update_health_millis: Default::default(),
+            mana_potion_key: KeyKind::A,
+            use_mana_potion_below_percent: Default::default(),
         }
     }
 }
@@ -211,6 +222,22 @@ pub struct PlayerState {
     /// The task for the health bar.
     health_bar_task: Option<Task<Result<Rect>>>,
 
+    /// The player current mana and max mana.
+    mana: Option<(u32, u32)>,
+    /// The task to update mana.
+    mana_task: Option<Task<Result<(u32, u32)>>>,
+    /// The rectangular mana bar region.
+    mana_bar: Option<Rect>,
+    /// The task for the mana bar.
+    mana_bar_task: Option<Task<Result<Rect>>>,
+
+    /// The player current EXP percentage.
+    exp: Option<f32>,
+    /// The tick [`Self::exp`] was last read at.
+    exp_tick: u64,
+    /// The task to update EXP percentage.
+    exp_task: Option<Task<Result<f32>>>,
+
     /// Track if the player moved within a specified ticks to determine if the player is
     /// stationary.
     is_stationary_timeout: Timeout,
@@ -329,6 +356,22 @@ impl PlayerState {
         self.health
     }
 
+    #[inline]
+    pub fn mana(&self) -> Option<(u32, u32)> {
+        self.mana
+    }
+
+    #[inline]
+    pub fn exp(&self) -> Option<f32> {
+        self.exp
+    }
+
+    /// The EXP percentage if it was read in the current tick of `context`.
+    #[inline]
+    pub fn exp_read_in(&self, context: &Context) -> Option<f32> {
+        self.exp.filter(|_| self.exp_tick == context.tick)
+    }
+
     #[inline]
     pub fn is_dead(&self) -> bool {
         self.is_dead
@@ -1074,6 +1117,8 @@ impl PlayerState {
     pub(super) fn update_state(&mut self, context: &Context) -> bool {
         if self.update_position_state(context) {
             self.update_health_state(context);
+            self.update_mana_state(context);
+            self.update_exp_state(context);
             self.update_rune_validating_state(context);
             self.update_is_dead_state(context);
             true
@@ -1208,7 +1253,9 @@ impl PlayerState {
         if let Player::SolvingRune(_) = context.player {
             return;
         }
-        if self.config.use_potion_below_percent.is_none() {
+        if self.config.use_potion_below_percent.is_none()
+            && self.config.use_mana_potion_below_percent.is_none()
+        {
             {
                 let this = &mut *self;
                 this.health = None;
@@ -1229,6 +1276,12 @@ impl PlayerState {
             }
             return;
         };
+        // The health bar is still needed for locating the mana bar
+        let Some(percentage) = self.config.use_potion_below_percent else {
+            self.health = None;
+            self.health_task = None;
+            return;
+        };
 
         let Update::Ok(health) = update_detection_task(
             context,
@@ -1244,7 +1297,6 @@ impl PlayerState {
             return;
         };
 
-        let percentage = self.config.use_potion_below_percent.unwrap();
         let (current, max) = health;
         let ratio = current as f32 / max as f32;
 
@@ -1254,6 +1306,77 @@ impl PlayerState {
         }
     }
 
+    /// Updates the player current mana.
+    ///
+    /// Similar to [`Self::update_health_state`], the mana bar is detected below the cached health
+    /// bar and is then used to extract the current mana and max mana.
+    #[inline]
+    fn update_mana_state(&mut self, context: &Context) {
+        if let Player::SolvingRune(_) = context.player {
+            return;
+        }
+        let Some(percentage) = self.config.use_mana_potion_below_percent else {
+            self.mana = None;
+            self.mana_task = None;
+            self.mana_bar = None;
+            self.mana_bar_task = None;
+            return;
+        };
+        let Some(health_bar) = self.health_bar else {
+            return;
+        };
+
+        let Some(mana_bar) = self.mana_bar else {
+            let update =
+                update_detection_task(context, 1000, &mut self.mana_bar_task, move |detector| {
+                    detector.detect_player_mana_bar(health_bar)
+                });
+            if let Update::Ok(mana_bar) = update {
+                self.mana_bar = Some(mana_bar);
+            }
+            return;
+        };
+
+        let Update::Ok(mana) = update_detection_task(
+            context,
+            self.config.update_health_millis.unwrap_or(1000),
+            &mut self.mana_task,
+            move |detector| {
+                let (current_bar, max_bar) =
+                    detector.detect_player_current_max_mana_bars(mana_bar)?;
+                let mana = detector.detect_player_mana(current_bar, max_bar)?;
+                Ok(mana)
+            },
+        ) else {
+            return;
+        };
+
+        let (current, max) = mana;
+        let ratio = current as f32 / max as f32;
+
+        self.mana = Some(mana);
+        if ratio <= percentage {
+            let _ = context.input.send_key(self.config.mana_potion_key);
+        }
+    }
+
+    /// Updates the player current EXP percentage.
+    #[inline]
+    fn update_exp_state(&mut self, context: &Context) {
+        if let Player::SolvingRune(_) = context.player {
+            return;
+        }
+
+        if let Update::Ok(exp) =
+            update_detection_task(context, UPDATE_EXP_MILLIS, &mut self.exp_task, |detector| {
+                detector.detect_player_exp()
+            })
+        {
+            self.exp = Some(exp);
+            self.exp_tick = context.tick;
+        }
+    }
+
     /// Updates whether the player is dead.
     ///
     /// Upon being dead, a notification will be scheduled to notify the user.
@@ -10,7 +10,7 @@ use super::{
 };
 use crate::{
     ActionKeyDirection, ActionKeyWith, Class, KeyBinding, LinkKeyBinding, Position,
-    bridge::KeyKind,
+    bridge::{InputEvent, KeyKind},
     context::Context,
     player::{
         AUTO_MOB_USE_KEY_X_THRESHOLD, AUTO_MOB_USE_KEY_Y_THRESHOLD, LastMovement, MOVE_TIMEOUT,
@@ -282,8 +282,10 @@ pub fn update_use_key_context(
                     }
                 }
                 Some(LinkKeyBinding::AtTheSame(key)) => {
-                    let _ = context.input.send_key(key.into());
-                    let _ = context.input.send_key(use_key.key.into());
+                    let _ = context.input.send_sequence(&[
+                        InputEvent::Key(key.into()),
+                        InputEvent::Key(use_key.key.into()),
+                    ]);
                 }
                 Some(LinkKeyBinding::Along(_)) => {
                     if !completed {
@@ -439,9 +441,13 @@ fn update_link_key(
         }
         Lifecycle::Ended => {
             if let LinkKeyBinding::After(key) = link_key {
-                let _ = context.input.send_key(key.into());
-                if matches!(class, Class::Blaster) && KeyKind::from(key) != jump_key {
-                    let _ = context.input.send_key(jump_key);
+                let key = KeyKind::from(key);
+                if matches!(class, Class::Blaster) && key != jump_key {
+                    let _ = context
+                        .input
+                        .send_sequence(&[InputEvent::Key(key), InputEvent::Key(jump_key)]);
+                } else {
+                    let _ = context.input.send_key(key);
                 }
             } else if let LinkKeyBinding::Along(key) = link_key {
                 let _ = context.input.send_key_up(key.into());
@@ -479,7 +485,7 @@ mod tests {
 
     use crate::{
         ActionKeyDirection, ActionKeyWith, KeyBinding, LinkKeyBinding,
-        bridge::{KeyKind, MockInput},
+        bridge::{InputEvent, KeyKind, MockInput},
         context::Context,
         player::{
             Player, PlayerState, Timeout, update_non_positional_context,
@@ -649,6 +655,47 @@ mod tests {
         }
     }
 
+    #[test]
+    fn use_key_link_key_at_the_same_send_sequence() {
+        let mut keys = MockInput::new();
+        keys.expect_send_sequence()
+            .once()
+            .withf(|events| events == [InputEvent::Key(KeyKind::Alt), InputEvent::Key(KeyKind::A)])
+            .returning(|_| Ok(()));
+        let mut state = PlayerState::default();
+        let context = Context::new(Some(keys), None);
+        let use_key = UseKey {
+            key: KeyBinding::A,
+            link_key: Some(LinkKeyBinding::AtTheSame(KeyBinding::Alt)),
+            count: 1,
+            current_count: 0,
+            direction: ActionKeyDirection::Any,
+            with: ActionKeyWith::Any,
+            wait_before_use_ticks: 0,
+            wait_after_use_ticks: 0,
+            action_info: None,
+            stage: UseKeyStage::Precondition,
+        };
+
+        let mut player = Player::UseKey(use_key);
+        player = update_non_positional_context(player, &context, &mut state, false).unwrap();
+        assert_matches!(
+            player,
+            Player::UseKey(UseKey {
+                stage: UseKeyStage::Using(_, _),
+                ..
+            })
+        );
+        player = update_non_positional_context(player, &context, &mut state, false).unwrap();
+        assert_matches!(
+            player,
+            Player::UseKey(UseKey {
+                stage: UseKeyStage::Postcondition,
+                ..
+            })
+        );
+    }
+
     #[test]
     fn use_key_stalling() {
         let mut keys = MockInput::new();
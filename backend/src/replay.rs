@@ -0,0 +1,383 @@
+use std::{
+    cell::RefCell,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use anyhow::{Result, bail};
+use opencv::{
+    core::{Mat, MatTraitConst, MatTraitConstManual},
+    imgcodecs::{IMREAD_COLOR, imread},
+    imgproc::{COLOR_BGR2BGRA, cvt_color_def},
+};
+use platforms::{
+    Error, Window,
+    capture::{Frame, PixelFormat},
+    input::InputKind,
+};
+use tokio::sync::broadcast::channel;
+
+use crate::{
+    CaptureMode, CapturePlayback, Character, RotateKind, Settings,
+    bridge::{Capture, Input, InputEvent, InputMethod, InputReceiver, KeyKind, MouseKind},
+    context::{ContextEvent, UpdateLoop},
+    database::Minimap as MinimapData,
+    rng::Rng,
+    rpc::InputConnectionEvent,
+    services::DefaultService,
+};
+
+/// Image extensions recognized as recorded frames.
+const FRAME_EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+
+/// Options for replaying recorded frames through the update loop.
+#[derive(Debug, Default)]
+pub struct ReplayOptions {
+    /// The directory containing the recorded frames (e.g. from recording images in debug).
+    ///
+    /// Frames are replayed in file name order, one frame per tick.
+    pub frames_dir: PathBuf,
+    /// The file to write the per-tick trace to.
+    pub trace_path: PathBuf,
+    /// The seed for the context's random values.
+    ///
+    /// A fixed seed keeps the trace reproducible across runs.
+    pub seed: [u8; 32],
+    pub settings: Settings,
+    pub character: Option<Character>,
+    pub minimap: Option<MinimapData>,
+    pub preset: Option<String>,
+    /// Whether to start rotating actions on the first tick.
+    ///
+    /// This requires both `character` and `minimap` to be set.
+    pub running: bool,
+}
+
+/// Replays frames from [`ReplayOptions::frames_dir`] through the update loop without a game.
+///
+/// Ticking happens at the same FPS as the normal update loop. On each tick, a line containing the
+/// tick, [`Operation`](crate::context::Operation), [`Minimap`](crate::minimap::Minimap),
+/// [`Player`](crate::player::Player) and the inputs sent during that tick is written to
+/// [`ReplayOptions::trace_path`]. Returns the number of ticks replayed.
+pub fn replay(options: ReplayOptions) -> Result<usize> {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    let _runtime_guard = runtime.enter();
+
+    let mut capture = ReplayCapture::new(&options.frames_dir)?;
+    let ticks = capture.frames_count();
+    let records = Rc::new(RefCell::new(Vec::new()));
+    let mut input = RecordingInput::new(records.clone());
+    let mut trace = BufWriter::new(File::create(&options.trace_path)?);
+
+    let settings = Rc::new(RefCell::new(options.settings));
+    let (event_tx, event_rx) = channel::<ContextEvent>(5);
+    let service = DefaultService::new_headless(
+        settings.clone(),
+        capture.window(),
+        event_tx.subscribe(),
+        &mut input,
+        ReplayInputReceiver,
+        &mut capture,
+    );
+    let mut update_loop = UpdateLoop::new(
+        settings,
+        Rng::new(options.seed),
+        service,
+        Box::new(input),
+        Box::new(capture),
+        event_tx,
+        event_rx,
+    );
+    let (minimap, preset, character) = (options.minimap, options.preset, options.character);
+    update_loop.handle(|handler| {
+        if minimap.is_some() {
+            handler.on_update_minimap(preset, minimap);
+        }
+        if character.is_some() {
+            handler.on_update_character(character);
+        }
+        if options.running {
+            handler.on_rotate_actions(RotateKind::Run);
+        }
+    });
+
+    let mut result = Ok(());
+    update_loop.run(|context| {
+        let inputs = records.take();
+        result = writeln!(
+            trace,
+            "{}\t{:?}\t{:?}\t{:?}\t{:?}",
+            context.tick, context.operation, context.minimap, context.player, inputs
+        );
+
+        result.is_ok() && (context.tick as usize) < ticks
+    });
+    result?;
+    trace.flush()?;
+
+    Ok(ticks)
+}
+
+/// An input sent through [`RecordingInput`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputRecord {
+    Mouse(i32, i32, MouseKind),
+    Key(KeyKind),
+    KeyUp(KeyKind),
+    KeyDown(KeyKind),
+}
+
+/// An [`Input`] that records inputs instead of sending them.
+#[derive(Debug)]
+pub struct RecordingInput {
+    records: Rc<RefCell<Vec<InputRecord>>>,
+}
+
+impl RecordingInput {
+    pub fn new(records: Rc<RefCell<Vec<InputRecord>>>) -> Self {
+        Self { records }
+    }
+
+    #[inline]
+    fn record(&self, record: InputRecord) -> Result<()> {
+        self.records.borrow_mut().push(record);
+        Ok(())
+    }
+}
+
+impl Input for RecordingInput {
+    fn update(&mut self, _tick: u64) -> Option<InputConnectionEvent> {
+        None
+    }
+
+    fn set_method(&mut self, _method: InputMethod) {}
+
+    fn send_mouse(&self, x: i32, y: i32, kind: MouseKind) -> Result<()> {
+        self.record(InputRecord::Mouse(x, y, kind))
+    }
+
+    fn send_key(&self, kind: KeyKind) -> Result<()> {
+        self.record(InputRecord::Key(kind))
+    }
+
+    fn send_key_up(&self, kind: KeyKind) -> Result<()> {
+        self.record(InputRecord::KeyUp(kind))
+    }
+
+    fn send_key_down(&self, kind: KeyKind) -> Result<()> {
+        self.record(InputRecord::KeyDown(kind))
+    }
+
+    /// Records all events in the sequence at once without waiting.
+    fn send_sequence(&self, events: &[InputEvent]) -> Result<()> {
+        for event in events {
+            match *event {
+                InputEvent::Key(kind) => self.record(InputRecord::Key(kind))?,
+                InputEvent::KeyUp(kind) => self.record(InputRecord::KeyUp(kind))?,
+                InputEvent::KeyDown(kind) => self.record(InputRecord::KeyDown(kind))?,
+                InputEvent::Mouse(x, y, kind) => self.record(InputRecord::Mouse(x, y, kind))?,
+                InputEvent::Wait(_) => (),
+            }
+        }
+        Ok(())
+    }
+
+    fn clear_sequence(&self) {}
+
+    #[inline]
+    fn all_keys_cleared(&self) -> bool {
+        true
+    }
+}
+
+/// An [`InputReceiver`] that never receives any key.
+#[derive(Debug)]
+struct ReplayInputReceiver;
+
+impl InputReceiver for ReplayInputReceiver {
+    fn set_window_and_input_kind(&mut self, _window: Window, _kind: InputKind) {}
+
+    fn try_recv(&mut self) -> Result<KeyKind> {
+        bail!("no key event when replaying")
+    }
+}
+
+/// A [`Capture`] that replays frames from a directory, one frame per [`Capture::grab`].
+#[derive(Debug)]
+pub struct ReplayCapture {
+    frames: Vec<PathBuf>,
+    index: usize,
+    window: Window,
+    mode: CaptureMode,
+}
+
+impl ReplayCapture {
+    pub fn new(dir: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self {
+            frames: frame_paths_from(dir)?,
+            index: 0,
+            window: Window::new("Replay"),
+            mode: CaptureMode::default(),
+        })
+    }
+
+    /// The number of frames to replay.
+    #[inline]
+    pub fn frames_count(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+impl Capture for ReplayCapture {
+    fn grab(&mut self) -> Result<Frame, Error> {
+        let path = self
+            .frames
+            .get(self.index)
+            .ok_or(Error::WindowFrameNotAvailable)?;
+        self.index += 1;
+
+        frame_from(path).ok_or(Error::WindowFrameNotAvailable)
+    }
+
+    #[inline]
+    fn window(&self) -> Window {
+        self.window
+    }
+
+    #[inline]
+    fn set_window(&mut self, window: Window) {
+        self.window = window;
+    }
+
+    #[inline]
+    fn mode(&self) -> CaptureMode {
+        self.mode
+    }
+
+    #[inline]
+    fn set_mode(&mut self, mode: CaptureMode) {
+        self.mode = mode;
+    }
+
+    #[inline]
+    fn set_playback(&mut self, _playback: CapturePlayback) {}
+
+    fn set_rpc_server_url(&mut self, _url: String) {}
+}
+
+/// Lists the recorded frames in `dir` in file name order.
+pub(crate) fn frame_paths_from(dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+    let mut frames = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| {
+                    FRAME_EXTENSIONS.contains(&extension.to_lowercase().as_str())
+                })
+        })
+        .collect::<Vec<_>>();
+    if frames.is_empty() {
+        bail!("no frames to replay");
+    }
+    frames.sort();
+
+    Ok(frames)
+}
+
+/// Reads the image at `path` as a BGRA [`Frame`] like the ones from platform capture.
+pub(crate) fn frame_from(path: &Path) -> Option<Frame> {
+    frame_from_bgr(&imread(path.to_str()?, IMREAD_COLOR).ok()?)
+}
+
+/// Converts a BGR `mat` to a BGRA [`Frame`].
+pub(crate) fn frame_from_bgr(mat: &Mat) -> Option<Frame> {
+    if mat.empty() {
+        return None;
+    }
+    let mut bgra = Mat::default();
+    cvt_color_def(mat, &mut bgra, COLOR_BGR2BGRA).ok()?;
+
+    Some(Frame {
+        width: bgra.cols(),
+        height: bgra.rows(),
+        data: bgra.data_bytes().ok()?.to_vec(),
+        format: PixelFormat::Bgra,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use opencv::{
+        core::{CV_8UC3, Scalar, Vector},
+        imgcodecs::imwrite,
+    };
+
+    use super::*;
+
+    fn frames_dir(name: &str, count: usize) -> PathBuf {
+        let dir = env::temp_dir().join(format!("komari-replay-{name}"));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for i in 0..count {
+            let mat =
+                Mat::new_rows_cols_with_default(4, 8, CV_8UC3, Scalar::all(i as f64)).unwrap();
+            let path = dir.join(format!("{i}.png"));
+            imwrite(path.to_str().unwrap(), &mat, &Vector::new()).unwrap();
+        }
+        fs::write(dir.join("notes.txt"), "not a frame").unwrap();
+
+        dir
+    }
+
+    #[test]
+    fn replay_capture_grabs_frames_in_order_as_bgra() {
+        let dir = frames_dir("order", 3);
+        let mut capture = ReplayCapture::new(&dir).unwrap();
+
+        assert_eq!(capture.frames_count(), 3);
+        for i in 0..3u8 {
+            let frame = capture.grab().unwrap();
+            assert_eq!(frame.width, 8);
+            assert_eq!(frame.height, 4);
+            assert_eq!(frame.data.len(), 8 * 4 * 4);
+            assert_eq!(&frame.data[..4], &[i, i, i, 255]);
+        }
+        assert_eq!(capture.grab().unwrap_err(), Error::WindowFrameNotAvailable);
+    }
+
+    #[test]
+    fn replay_capture_empty_dir() {
+        let dir = frames_dir("empty", 0);
+
+        assert!(ReplayCapture::new(&dir).is_err());
+    }
+
+    #[test]
+    fn recording_input_records_in_order() {
+        let records = Rc::new(RefCell::new(Vec::new()));
+        let input = RecordingInput::new(records.clone());
+
+        input.send_key(KeyKind::A).unwrap();
+        input.send_key_down(KeyKind::Left).unwrap();
+        input.send_mouse(1, 2, MouseKind::Click).unwrap();
+        input.send_key_up(KeyKind::Left).unwrap();
+
+        assert_eq!(
+            records.take(),
+            vec![
+                InputRecord::Key(KeyKind::A),
+                InputRecord::KeyDown(KeyKind::Left),
+                InputRecord::Mouse(1, 2, MouseKind::Click),
+                InputRecord::KeyUp(KeyKind::Left),
+            ]
+        );
+        assert!(records.borrow().is_empty());
+    }
+}
@@ -7,7 +7,7 @@ use std::{
 };
 
 use anyhow::Result;
-use log::{debug, info};
+use log::{debug, info, warn};
 #[cfg(test)]
 use mockall::{automock, concretize};
 use opencv::core::{Point, Rect};
@@ -119,6 +119,8 @@ pub enum RotatorMode {
 pub struct RotatorBuildArgs<'a> {
     pub mode: RotatorMode,
     pub actions: &'a [Action],
+    /// The ids of [`Character::skills`] in order.
+    pub skill_ids: &'a [u32],
     pub buffs: &'a [(BuffKind, KeyBinding)],
     pub familiar_essence_key: KeyBinding,
     pub familiar_swappable_slots: SwappableFamiliars,
@@ -273,7 +275,10 @@ impl DefaultRotator {
                 Some(ActionCondition::ErdaShowerOffCooldown) => {
                     has_erda_action || has_linked_action
                 }
-                Some(ActionCondition::Linked) | Some(ActionCondition::EveryMillis(_)) | None => {
+                Some(ActionCondition::Linked)
+                | Some(ActionCondition::EveryMillis(_))
+                | Some(ActionCondition::SkillOffCooldown(_))
+                | None => {
                     player // The player currently executing action
                         .priority_action_id()
                         .is_some_and(|action_id| action_id == id)
@@ -697,6 +702,7 @@ impl Rotator for DefaultRotator {
         let RotatorBuildArgs {
             mode,
             actions,
+            skill_ids,
             buffs,
             familiar_essence_key,
             familiar_swappable_slots,
@@ -729,10 +735,25 @@ impl Rotator for DefaultRotator {
             // infinite loop due to auto mobbing ignoring Any condition
             i += offset;
             match condition {
-                ActionCondition::EveryMillis(_) | ActionCondition::ErdaShowerOffCooldown => {
+                ActionCondition::EveryMillis(_)
+                | ActionCondition::ErdaShowerOffCooldown
+                | ActionCondition::SkillOffCooldown(_) => {
+                    let skill = match condition {
+                        ActionCondition::ErdaShowerOffCooldown => Some(SkillKind::ErdaShower),
+                        ActionCondition::SkillOffCooldown(id) => {
+                            // The skill may have been deleted from the character
+                            let Some(index) = skill_ids.iter().position(|skill| *skill == id)
+                            else {
+                                warn!(target: "rotator", "skipping action of unregistered skill {id}");
+                                continue;
+                            };
+                            Some(SkillKind::Custom(index))
+                        }
+                        _ => None,
+                    };
                     self.priority_actions.insert(
                         self.id_counter.fetch_add(1, Ordering::Relaxed),
-                        priority_action(action, condition, queue_to_front),
+                        priority_action(action, condition, skill, queue_to_front),
                     );
                 }
                 ActionCondition::Any => {
@@ -785,6 +806,7 @@ impl Rotator for DefaultRotator {
                         swappable_rarities: Array::from_iter(familiar_swappable_rarities.clone()),
                     })),
                     ActionCondition::EveryMillis(familiar_swap_check_millis),
+                    None,
                     true,
                 ),
             );
@@ -905,20 +927,26 @@ fn rotator_action(
     (RotatorAction::Linked(head), offset)
 }
 
+/// Creates a priority action queued by `condition`.
+///
+/// `skill` is the skill `condition` waits to be off cooldown if any.
 #[inline]
 fn priority_action(
     action: RotatorAction,
     condition: ActionCondition,
+    skill: Option<SkillKind>,
     queue_to_front: bool,
 ) -> PriorityAction {
     debug_assert_matches!(
         condition,
-        ActionCondition::EveryMillis(_) | ActionCondition::ErdaShowerOffCooldown
+        ActionCondition::EveryMillis(_)
+            | ActionCondition::ErdaShowerOffCooldown
+            | ActionCondition::SkillOffCooldown(_)
     );
     PriorityAction {
         inner: action,
         condition: Condition(Box::new(move |context, _, last_queued_time| {
-            if should_queue_fixed_action(context, last_queued_time, condition) {
+            if should_queue_fixed_action(context, last_queued_time, condition, skill) {
                 ConditionResult::Queue
             } else {
                 ConditionResult::Skip
@@ -1150,17 +1178,20 @@ fn should_queue_fixed_action(
     context: &Context,
     last_queued_time: Option<Instant>,
     condition: ActionCondition,
+    skill: Option<SkillKind>,
 ) -> bool {
     let millis_should_passed = match condition {
         ActionCondition::EveryMillis(millis) => millis as u128,
-        ActionCondition::ErdaShowerOffCooldown => COOLDOWN_BETWEEN_QUEUE_MILLIS,
+        ActionCondition::ErdaShowerOffCooldown | ActionCondition::SkillOffCooldown(_) => {
+            COOLDOWN_BETWEEN_QUEUE_MILLIS
+        }
         ActionCondition::Linked | ActionCondition::Any => unreachable!(),
     };
     if !at_least_millis_passed_since(last_queued_time, millis_should_passed) {
         return false;
     }
-    if matches!(condition, ActionCondition::ErdaShowerOffCooldown)
-        && !matches!(context.skills[SkillKind::ErdaShower], Skill::Idle(_, _))
+    if let Some(skill) = skill
+        && !matches!(context.skills.get(skill.index()), Some(Skill::Idle(_, _)))
     {
         return false;
     }
@@ -1222,12 +1253,14 @@ mod tests {
         assert!(should_queue_fixed_action(
             &context,
             Some(now - Duration::from_millis(3000)),
-            ActionCondition::EveryMillis(2000)
+            ActionCondition::EveryMillis(2000),
+            None
         ));
         assert!(!should_queue_fixed_action(
             &context,
             Some(now - Duration::from_millis(1000)),
-            ActionCondition::EveryMillis(2000)
+            ActionCondition::EveryMillis(2000),
+            None
         ));
     }
 
@@ -1236,23 +1269,60 @@ mod tests {
         let mut context = Context::new(None, None);
         let now = Instant::now();
 
-        context.skills[SkillKind::ErdaShower] = Skill::Idle(Point::default(), Vec4b::default());
+        context.skills[SkillKind::ErdaShower.index()] =
+            Skill::Idle(Point::default(), Vec4b::default());
         assert!(!should_queue_fixed_action(
             &context,
             Some(now - Duration::from_millis(COOLDOWN_BETWEEN_QUEUE_MILLIS as u64 - 1000)),
-            ActionCondition::ErdaShowerOffCooldown
+            ActionCondition::ErdaShowerOffCooldown,
+            Some(SkillKind::ErdaShower)
         ));
         assert!(should_queue_fixed_action(
             &context,
             Some(now - Duration::from_millis(COOLDOWN_BETWEEN_QUEUE_MILLIS as u64)),
-            ActionCondition::ErdaShowerOffCooldown
+            ActionCondition::ErdaShowerOffCooldown,
+            Some(SkillKind::ErdaShower)
         ));
 
-        context.skills[SkillKind::ErdaShower] = Skill::Detecting;
+        context.skills[SkillKind::ErdaShower.index()] = Skill::Detecting;
         assert!(!should_queue_fixed_action(
             &context,
             Some(now - Duration::from_millis(COOLDOWN_BETWEEN_QUEUE_MILLIS as u64)),
-            ActionCondition::ErdaShowerOffCooldown
+            ActionCondition::ErdaShowerOffCooldown,
+            Some(SkillKind::ErdaShower)
+        ));
+    }
+
+    #[test]
+    fn rotator_should_queue_fixed_action_custom_skill() {
+        let mut context = Context::new(None, None);
+        let queued =
+            Some(Instant::now() - Duration::from_millis(COOLDOWN_BETWEEN_QUEUE_MILLIS as u64));
+
+        // Not registered
+        assert!(!should_queue_fixed_action(
+            &context,
+            queued,
+            ActionCondition::SkillOffCooldown(0),
+            Some(SkillKind::Custom(0))
+        ));
+
+        context
+            .skills
+            .push(Skill::Idle(Point::default(), Vec4b::default()));
+        assert!(should_queue_fixed_action(
+            &context,
+            queued,
+            ActionCondition::SkillOffCooldown(0),
+            Some(SkillKind::Custom(0))
+        ));
+
+        context.skills[SkillKind::Custom(0).index()] = Skill::Cooldown;
+        assert!(!should_queue_fixed_action(
+            &context,
+            queued,
+            ActionCondition::SkillOffCooldown(0),
+            Some(SkillKind::Custom(0))
         ));
     }
 
@@ -1264,6 +1334,7 @@ mod tests {
         let args = RotatorBuildArgs {
             mode: RotatorMode::default(),
             actions: &actions,
+            skill_ids: &[],
             buffs: &buffs,
             familiar_essence_key: KeyBinding::default(),
             familiar_swappable_slots: SwappableFamiliars::default(),
@@ -1282,6 +1353,43 @@ mod tests {
         assert_eq!(rotator.normal_actions.len(), 2);
     }
 
+    #[test]
+    fn rotator_build_actions_skips_unregistered_skill() {
+        let mut rotator = DefaultRotator::default();
+        let skill_action = |id| {
+            Action::Move(ActionMove {
+                condition: ActionCondition::SkillOffCooldown(id),
+                ..ActionMove::default()
+            })
+        };
+        let actions = vec![skill_action(5), skill_action(7)];
+        let args = RotatorBuildArgs {
+            mode: RotatorMode::default(),
+            actions: &actions,
+            skill_ids: &[7],
+            buffs: &[],
+            familiar_essence_key: KeyBinding::default(),
+            familiar_swappable_slots: SwappableFamiliars::default(),
+            familiar_swappable_rarities: &HashSet::default(),
+            familiar_swap_check_millis: 0,
+            elite_boss_behavior: EliteBossBehavior::CycleChannel,
+            elite_boss_behavior_key: KeyBinding::default(),
+            enable_panic_mode: false,
+            enable_rune_solving: false,
+            enable_familiars_swapping: false,
+            enable_reset_normal_actions_on_erda: false,
+        };
+
+        rotator.build_actions(args);
+        let skill_conditions = rotator
+            .priority_actions
+            .values()
+            .filter_map(|action| action.condition_kind)
+            .filter(|condition| matches!(condition, ActionCondition::SkillOffCooldown(_)))
+            .collect::<Vec<_>>();
+        assert_eq!(skill_conditions, vec![ActionCondition::SkillOffCooldown(7)]);
+    }
+
     #[test]
     fn rotator_rotate_action_start_to_end_then_reverse() {
         let mut rotator = DefaultRotator::default();
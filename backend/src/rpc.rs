@@ -1,70 +1,267 @@
+#[cfg(unix)]
+use std::io;
+use std::path::PathBuf;
 use std::time::Duration;
 
-use anyhow::{Error, Ok, bail};
+use anyhow::{Error, Ok, anyhow, bail};
 use bit_vec::BitVec;
+use frame::frame_source_client::FrameSourceClient;
+use frame::{FrameFormat, FrameResponse, FrameStreamRequest};
+use hyper_util::rt::TokioIo;
 use input::key_input_client::KeyInputClient;
+use input::key_sequence_event::Event as KeySequenceEventKind;
 pub use input::{Coordinate, Key, KeyState, MouseAction};
-use input::{KeyDownRequest, KeyInitRequest, KeyRequest, KeyUpRequest, MouseRequest};
+use input::{
+    HealthRequest, KeyDownRequest, KeyInitRequest, KeyRequest, KeySequenceEvent,
+    KeySequenceRequest, KeyUpRequest, MouseRequest,
+};
+use log::{error, info};
+use opencv::core::Vector;
+use opencv::imgcodecs::{IMREAD_COLOR, imdecode};
+use platforms::Error as PlatformError;
+use platforms::capture::{Frame, PixelFormat};
+#[cfg(unix)]
+use tokio::net::UnixStream;
+#[cfg(windows)]
+use tokio::net::windows::named_pipe::ClientOptions;
 use tokio::runtime::Handle;
-use tokio::task::block_in_place;
-use tokio::time::timeout;
-use tonic::Request;
-use tonic::transport::{Channel, Endpoint};
+use tokio::sync::watch;
+use tokio::task::{JoinHandle, block_in_place};
+use tokio::time::{Instant, sleep, timeout};
+use tonic::transport::{Channel, Endpoint, Uri};
+use tonic::{Code, Request, Streaming};
+use tower::service_fn;
 
+use crate::replay::frame_from_bgr;
 use crate::rpc::input::KeyStateRequest;
 
 mod input {
     tonic::include_proto!("input");
 }
 
+mod frame {
+    tonic::include_proto!("frame");
+}
+
+/// A placeholder URI for transports other than HTTP.
+///
+/// The URI is required to build an [`Endpoint`] but is not used by the custom connectors.
+const PLACEHOLDER_URI: &str = "http://[::]:50051";
+
+/// The prefix of Windows named pipe paths.
+const NAMED_PIPE_PREFIX: &str = r"\\.\pipe\";
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(1);
+
+const RECONNECT_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+const RECONNECT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// The number of bits in [`InputService`] `key_down` bitmap, enough for every [`Key`].
+const KEY_COUNT: usize = 128;
+
+/// The maximum age of a frame returned by [`FrameService::grab`].
+const FRAME_MAX_AGE: Duration = Duration::from_secs(2);
+
+/// An RPC server address parsed from a server URL.
+#[derive(Debug, Clone)]
+enum RpcEndpoint {
+    /// A `http://` or `https://` URL.
+    Http(Endpoint),
+    /// A Unix domain socket path from a `unix://` URL.
+    Unix(PathBuf),
+    /// A Windows named pipe path from a `pipe://` URL or a `\\.\pipe\` path.
+    NamedPipe(String),
+}
+
+impl RpcEndpoint {
+    fn parse(dest: &str) -> Result<Self, Error> {
+        if let Some(path) = dest.strip_prefix("unix://") {
+            if path.is_empty() {
+                bail!("unix domain socket path is empty");
+            }
+            return Ok(RpcEndpoint::Unix(PathBuf::from(path)));
+        }
+        if let Some(name) = dest.strip_prefix("pipe://") {
+            if name.is_empty() {
+                bail!("named pipe name is empty");
+            }
+            return Ok(RpcEndpoint::NamedPipe(format!("{NAMED_PIPE_PREFIX}{name}")));
+        }
+        if dest
+            .strip_prefix(NAMED_PIPE_PREFIX)
+            .is_some_and(|name| !name.is_empty())
+        {
+            return Ok(RpcEndpoint::NamedPipe(dest.to_string()));
+        }
+
+        Ok(RpcEndpoint::Http(Endpoint::try_from(dest.to_string())?))
+    }
+
+    async fn connect(self) -> Result<Channel, Error> {
+        match self {
+            RpcEndpoint::Http(endpoint) => Ok(endpoint.connect().await?),
+            RpcEndpoint::Unix(path) => connect_unix(path).await,
+            RpcEndpoint::NamedPipe(name) => connect_named_pipe(name).await,
+        }
+    }
+}
+
+/// An event to send with [`InputService::send_sequence`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SequenceEvent {
+    /// Presses a key and holds it down for the milliseconds.
+    Key(Key, f32),
+    KeyDown(Key),
+    KeyUp(Key),
+    Mouse {
+        width: i32,
+        height: i32,
+        x: i32,
+        y: i32,
+        action: MouseAction,
+    },
+}
+
+/// A change of connection to the input server returned by [`InputService::update_connection`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputConnectionEvent {
+    /// The server is connected for the first time.
+    Connected,
+    /// The server has failed a health check.
+    Disconnected,
+    /// The server is connected again after being disconnected.
+    Reconnected,
+}
+
+/// The connection to the input server published by [`monitor_input_connection`].
+#[derive(Debug, Clone)]
+enum InputConnection {
+    Connected {
+        client: KeyInputClient<Channel>,
+        /// Incremented on each reconnect to tell connections apart.
+        generation: u32,
+        /// The mouse coordinate from re-running `Init` on reconnect.
+        mouse_coordinate: Option<Coordinate>,
+    },
+    Disconnected,
+}
+
 #[derive(Debug)]
 pub struct InputService {
-    client: KeyInputClient<Channel>,
+    connection: watch::Receiver<InputConnection>,
+    /// The generation of the connection last returned by [`Self::update_connection`].
+    connected_generation: Option<u32>,
+    /// Whether [`InputConnectionEvent::Connected`] has been returned.
+    reported_connected: bool,
+    /// The seed of the last [`Self::init`] to re-run on reconnect.
+    seed: watch::Sender<Option<Vec<u8>>>,
+    /// Task checking health and reconnecting in the background.
+    task: JoinHandle<()>,
     key_down: BitVec, // TODO: is a bit wrong good?
     mouse_coordinate: Coordinate,
+    /// Whether the server implements `SendSequence`.
+    sequence_supported: bool,
 }
 
 impl InputService {
-    pub fn connect<D>(dest: D) -> Result<Self, Error>
-    where
-        D: TryInto<Endpoint>,
-        D: AsRef<str>,
-        D::Error: std::error::Error + Send + Sync + 'static,
-    {
-        let endpoint = TryInto::<Endpoint>::try_into(dest.as_ref().to_string())?;
-        let client = block_future(async move {
-            timeout(Duration::from_secs(3), KeyInputClient::connect(endpoint)).await
-        })??;
+    /// Connects to the input server at `dest`.
+    ///
+    /// Besides `http://` and `https://` URLs, `dest` can be a Unix domain socket as
+    /// `unix://<path>` or a Windows named pipe as `pipe://<name>` or `\\.\pipe\<name>`.
+    ///
+    /// Only fails if `dest` is not a valid endpoint. If the server cannot be connected, the
+    /// service starts disconnected and retries in the background. [`Self::update_connection`]
+    /// returns [`InputConnectionEvent::Connected`] once connected.
+    pub fn connect(dest: impl AsRef<str>) -> Result<Self, Error> {
+        let endpoint = RpcEndpoint::parse(dest.as_ref())?;
+        let connection = connect_input_client(&endpoint)
+            .map(|client| InputConnection::Connected {
+                client,
+                generation: 0,
+                mouse_coordinate: None,
+            })
+            .unwrap_or(InputConnection::Disconnected);
+        let (sender, connection) = watch::channel(connection);
+        let (seed, seed_receiver) = watch::channel(None);
+        let task =
+            Handle::current().spawn(monitor_input_connection(endpoint, sender, seed_receiver));
+
         Ok(Self {
-            client,
-            key_down: BitVec::from_elem(128, false),
+            connection,
+            connected_generation: None,
+            reported_connected: false,
+            seed,
+            task,
+            key_down: BitVec::from_elem(KEY_COUNT, false),
             mouse_coordinate: Coordinate::Screen,
+            sequence_supported: true,
         })
     }
 
-    fn reset(&mut self) {
-        for i in 0..self.key_down.len() {
-            if Key::try_from(i as i32).is_ok() {
-                let _ = block_future(async {
-                    self.client
-                        .send_up(Request::new(KeyUpRequest { key: i as i32 }))
-                        .await
-                });
+    /// Returns a change of the connection monitored in the background since the last call.
+    ///
+    /// This does not wait for the network. On reconnect, the background task has already re-run
+    /// `Init` with the last seed and released all keys so the `key_down` bitmap is cleared to
+    /// agree with the server.
+    pub fn update_connection(&mut self) -> Option<InputConnectionEvent> {
+        let (generation, mouse_coordinate) = match &*self.connection.borrow_and_update() {
+            InputConnection::Connected {
+                generation,
+                mouse_coordinate,
+                ..
+            } => (*generation, *mouse_coordinate),
+            InputConnection::Disconnected => {
+                return self
+                    .connected_generation
+                    .take()
+                    .map(|_| InputConnectionEvent::Disconnected);
             }
+        };
+        if self.connected_generation == Some(generation) {
+            return None;
+        }
+        self.connected_generation = Some(generation);
+        if let Some(mouse_coordinate) = mouse_coordinate {
+            self.mouse_coordinate = mouse_coordinate;
+        }
+        if !self.reported_connected {
+            self.reported_connected = true;
+            return Some(InputConnectionEvent::Connected);
+        }
+
+        self.key_down.clear();
+        // The server may have been replaced by one without `SendSequence`
+        self.sequence_supported = true;
+        Some(InputConnectionEvent::Reconnected)
+    }
+
+    /// Gets a client of the current connection.
+    ///
+    /// The client is cheap to clone as it shares the underlying channel.
+    #[inline]
+    fn client(&self) -> Result<KeyInputClient<Channel>, Error> {
+        match &*self.connection.borrow() {
+            InputConnection::Connected { client, .. } => Ok(client.clone()),
+            InputConnection::Disconnected => Err(anyhow!("input server disconnected")),
+        }
+    }
+
+    fn reset(&mut self) {
+        if let Some(mut client) = self.client().ok() {
+            block_future(release_keys(&mut client));
         }
         self.key_down.clear();
     }
 
     pub fn init(&mut self, seed: &[u8]) -> Result<(), Error> {
-        let response = block_future(async {
-            self.client
-                .init(KeyInitRequest {
-                    seed: seed.to_vec(),
-                })
-                .await
-        })?
-        .into_inner();
-        self.mouse_coordinate = response.mouse_coordinate();
+        self.seed.send_replace(Some(seed.to_vec()));
+        let mut client = self.client()?;
+        self.mouse_coordinate = block_future(init_client(&mut client, seed.to_vec()))?;
         Ok(())
     }
 
@@ -73,9 +270,10 @@ impl InputService {
     }
 
     pub fn key_state(&mut self, key: Key) -> Result<KeyState, Error> {
+        let mut client = self.client()?;
         block_future(async move {
             let request = Request::new(KeyStateRequest { key: key.into() });
-            let response = self.client.key_state(request).await?.into_inner();
+            let response = client.key_state(request).await?.into_inner();
 
             Ok(KeyState::try_from(response.state)?)
         })
@@ -89,8 +287,9 @@ impl InputService {
         y: i32,
         action: MouseAction,
     ) -> Result<(), Error> {
+        let mut client = self.client()?;
         Ok(block_future(async move {
-            self.client
+            client
                 .send_mouse(Request::new(MouseRequest {
                     width,
                     height,
@@ -104,13 +303,14 @@ impl InputService {
     }
 
     pub fn send_key(&mut self, key: Key, down_ms: f32) -> Result<(), Error> {
+        let mut client = self.client()?;
         Ok(block_future(async move {
             let request = Request::new(KeyRequest {
                 key: key.into(),
                 down_ms,
             });
 
-            self.client.send(request).await?;
+            client.send(request).await?;
             self.key_down.set(i32::from(key) as usize, false);
             Ok(())
         })?)
@@ -120,10 +320,11 @@ impl InputService {
         if !self.can_send_key(key, false) {
             bail!("key not sent");
         }
+        let mut client = self.client()?;
         Ok(block_future(async move {
             let request = Request::new(KeyUpRequest { key: key.into() });
 
-            self.client.send_up(request).await?;
+            client.send_up(request).await?;
             self.key_down.set(i32::from(key) as usize, false);
             Ok(())
         })?)
@@ -133,29 +334,415 @@ impl InputService {
         if !self.can_send_key(key, true) {
             bail!("key not sent");
         }
+        let mut client = self.client()?;
         Ok(block_future(async move {
             let request = Request::new(KeyDownRequest { key: key.into() });
 
-            self.client.send_down(request).await?;
+            client.send_down(request).await?;
             self.key_down.set(i32::from(key) as usize, true);
             Ok(())
         })?)
     }
 
+    pub fn sequence_supported(&self) -> bool {
+        self.sequence_supported
+    }
+
+    /// Sends `events` in order within a single request.
+    ///
+    /// Each event is paired with the milliseconds to wait after the previous event. Key up/down
+    /// events that would not be sent by [`Self::send_key_up`] or [`Self::send_key_down`] are
+    /// skipped with their delay carried over to the next event.
+    pub fn send_sequence(&mut self, events: Vec<(f32, SequenceEvent)>) -> Result<(), Error> {
+        let mut key_down = self.key_down.clone();
+        let mut delay_ms = 0.0;
+        let mut sequence = Vec::with_capacity(events.len());
+        for (delay, event) in events {
+            delay_ms += delay;
+            let event = match event {
+                SequenceEvent::Key(key, down_ms) => {
+                    key_down.set(i32::from(key) as usize, false);
+                    KeySequenceEventKind::Key(KeyRequest {
+                        key: key.into(),
+                        down_ms,
+                    })
+                }
+                SequenceEvent::KeyDown(key) => {
+                    if !can_send_key(&key_down, key, true) {
+                        continue;
+                    }
+                    key_down.set(i32::from(key) as usize, true);
+                    KeySequenceEventKind::KeyDown(KeyDownRequest { key: key.into() })
+                }
+                SequenceEvent::KeyUp(key) => {
+                    if !can_send_key(&key_down, key, false) {
+                        continue;
+                    }
+                    key_down.set(i32::from(key) as usize, false);
+                    KeySequenceEventKind::KeyUp(KeyUpRequest { key: key.into() })
+                }
+                SequenceEvent::Mouse {
+                    width,
+                    height,
+                    x,
+                    y,
+                    action,
+                } => KeySequenceEventKind::Mouse(MouseRequest {
+                    width,
+                    height,
+                    x,
+                    y,
+                    action: action.into(),
+                }),
+            };
+            sequence.push(KeySequenceEvent {
+                delay_ms,
+                event: Some(event),
+            });
+            delay_ms = 0.0;
+        }
+        if sequence.is_empty() {
+            return Ok(());
+        }
+
+        let mut client = self.client()?;
+        let result = block_future(async move {
+            let request = Request::new(KeySequenceRequest { events: sequence });
+
+            client.send_sequence(request).await
+        });
+        if let Err(status) = result {
+            // Servers implemented before `SendSequence` was added
+            if status.code() == Code::Unimplemented {
+                self.sequence_supported = false;
+            }
+            return Err(status.into());
+        }
+        self.key_down = key_down;
+        Ok(())
+    }
+
     #[inline]
     fn can_send_key(&self, key: Key, is_down: bool) -> bool {
-        let key_num = i32::from(key) as usize;
-        let was_down = self.key_down.get(key_num).unwrap();
-        !matches!((was_down, is_down), (true, true) | (false, false))
+        can_send_key(&self.key_down, key, is_down)
     }
 }
 
 impl Drop for InputService {
     fn drop(&mut self) {
         self.reset();
+        self.task.abort();
+    }
+}
+
+/// Monitors the connection to the input server at `endpoint` and publishes it to `sender`.
+///
+/// While connected, health is checked every [`HEALTH_CHECK_INTERVAL`]. While disconnected,
+/// connecting is retried with the backoff doubled on each failure. On reconnect, `Init` is re-run
+/// with the latest `seed` and all keys are released before the connection is published.
+async fn monitor_input_connection(
+    endpoint: RpcEndpoint,
+    sender: watch::Sender<InputConnection>,
+    seed: watch::Receiver<Option<Vec<u8>>>,
+) {
+    let mut generation = 0;
+    let mut backoff = RECONNECT_INITIAL_BACKOFF;
+    while !sender.is_closed() {
+        let client = match &*sender.borrow() {
+            InputConnection::Connected { client, .. } => Some(client.clone()),
+            InputConnection::Disconnected => None,
+        };
+        if let Some(mut client) = client {
+            sleep(HEALTH_CHECK_INTERVAL).await;
+            if check_health(&mut client).await.is_err() {
+                backoff = RECONNECT_INITIAL_BACKOFF;
+                sender.send_replace(InputConnection::Disconnected);
+            }
+            continue;
+        }
+
+        sleep(backoff).await;
+        let last_seed = seed.borrow().clone();
+        let Some((client, mouse_coordinate)) = reconnect_input_client(endpoint.clone(), last_seed)
+            .await
+            .ok()
+        else {
+            backoff = (backoff * 2).min(RECONNECT_MAX_BACKOFF);
+            continue;
+        };
+        generation += 1;
+        sender.send_replace(InputConnection::Connected {
+            client,
+            generation,
+            mouse_coordinate,
+        });
+    }
+}
+
+/// Connects to the input server at `endpoint` and re-runs `Init` with `seed` if any.
+///
+/// All keys are also released as the server may still hold keys down from before disconnecting.
+async fn reconnect_input_client(
+    endpoint: RpcEndpoint,
+    seed: Option<Vec<u8>>,
+) -> Result<(KeyInputClient<Channel>, Option<Coordinate>), Error> {
+    let channel = timeout(CONNECT_TIMEOUT, endpoint.connect()).await??;
+    let mut client = KeyInputClient::new(channel);
+    let mouse_coordinate = match seed {
+        Some(seed) => Some(init_client(&mut client, seed).await?),
+        None => None,
+    };
+    release_keys(&mut client).await;
+
+    Ok((client, mouse_coordinate))
+}
+
+async fn init_client(
+    client: &mut KeyInputClient<Channel>,
+    seed: Vec<u8>,
+) -> Result<Coordinate, Error> {
+    let response = client.init(KeyInitRequest { seed }).await?.into_inner();
+
+    Ok(response.mouse_coordinate())
+}
+
+/// Sends `Health` to the server.
+///
+/// Servers implemented before `Health` was added are considered healthy as long as they respond.
+async fn check_health(client: &mut KeyInputClient<Channel>) -> Result<(), Error> {
+    let result = timeout(
+        HEALTH_CHECK_TIMEOUT,
+        client.health(Request::new(HealthRequest {})),
+    )
+    .await?;
+    match result {
+        Err(status) if status.code() != Code::Unimplemented => Err(status.into()),
+        _ => Ok(()),
+    }
+}
+
+async fn release_keys(client: &mut KeyInputClient<Channel>) {
+    for i in 0..KEY_COUNT {
+        if Key::try_from(i as i32).is_ok() {
+            let _ = client
+                .send_up(Request::new(KeyUpRequest { key: i as i32 }))
+                .await;
+        }
+    }
+}
+
+/// Receives frames streamed from a remote frame source.
+///
+/// Frames are received and decoded in the background so that [`Self::grab`] returns the latest
+/// frame without waiting for the network. The stream is opened in the background and reopened
+/// with backoff when it cannot be opened or ends.
+#[derive(Debug)]
+pub struct FrameService {
+    /// The latest frame and the instant it was received.
+    frame: watch::Receiver<Option<(Frame, Instant)>>,
+    task: JoinHandle<()>,
+}
+
+impl FrameService {
+    /// Connects to the frame source server at `dest`.
+    ///
+    /// Only fails if `dest` is not a valid endpoint. If the server cannot be connected, the
+    /// service starts without frames and retries in the background.
+    pub fn connect<D>(dest: D) -> Result<Self, Error>
+    where
+        D: TryInto<Endpoint>,
+        D: AsRef<str>,
+        D::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let endpoint = TryInto::<Endpoint>::try_into(dest.as_ref().to_string())?;
+        let (sender, frame) = watch::channel(None);
+        let task = Handle::current().spawn(receive_frames(endpoint, sender));
+
+        Ok(Self { frame, task })
+    }
+
+    /// Gets the latest frame received.
+    ///
+    /// Returns [`PlatformError::WindowFrameNotAvailable`] if no frame has been received yet, the
+    /// stream has ended or the latest frame is older than [`FRAME_MAX_AGE`].
+    pub fn grab(&mut self) -> Result<Frame, PlatformError> {
+        if self.frame.has_changed().is_err() {
+            return Err(PlatformError::WindowFrameNotAvailable);
+        }
+
+        self.frame
+            .borrow_and_update()
+            .as_ref()
+            .filter(|(_, received)| received.elapsed() <= FRAME_MAX_AGE)
+            .map(|(frame, _)| frame.clone())
+            .ok_or(PlatformError::WindowFrameNotAvailable)
+    }
+}
+
+impl Drop for FrameService {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn open_frame_stream(endpoint: Endpoint) -> Result<Streaming<FrameResponse>, Error> {
+    let mut client = timeout(CONNECT_TIMEOUT, FrameSourceClient::connect(endpoint)).await??;
+    let request = Request::new(FrameStreamRequest {});
+
+    Ok(client.stream_frames(request).await?.into_inner())
+}
+
+/// Receives frames streamed from `endpoint` into `sender`.
+///
+/// The stream is opened with the backoff doubled on each failure. When the stream ends or fails,
+/// the latest frame is cleared and a new stream is opened.
+async fn receive_frames(endpoint: Endpoint, sender: watch::Sender<Option<(Frame, Instant)>>) {
+    let mut backoff = RECONNECT_INITIAL_BACKOFF;
+    loop {
+        let mut stream = match open_frame_stream(endpoint.clone()).await {
+            Result::Ok(stream) => stream,
+            Err(err) => {
+                error!(target: "rpc", "failed to open frame stream {err}");
+                sleep(backoff).await;
+                backoff = (backoff * 2).min(RECONNECT_MAX_BACKOFF);
+                continue;
+            }
+        };
+        info!(target: "rpc", "opened frame stream");
+        backoff = RECONNECT_INITIAL_BACKOFF;
+
+        while let Some(response) = stream
+            .message()
+            .await
+            .inspect_err(|status| error!(target: "rpc", "frame stream failed {status}"))
+            .ok()
+            .flatten()
+        {
+            // Skips frames that cannot be decoded instead of ending the stream
+            if let Some(frame) = frame_from_response(response) {
+                sender.send_replace(Some((frame, Instant::now())));
+            }
+        }
+        sender.send_replace(None);
+        sleep(backoff).await;
     }
 }
 
+fn frame_from_response(response: FrameResponse) -> Option<Frame> {
+    let width = response.width;
+    let height = response.height;
+    let format = match FrameFormat::try_from(response.format).ok()? {
+        FrameFormat::Bgra => PixelFormat::Bgra,
+        FrameFormat::Rgba => PixelFormat::Rgba,
+        FrameFormat::Rgb => PixelFormat::Rgb,
+        FrameFormat::Nv12 => PixelFormat::Nv12,
+        FrameFormat::Encoded => {
+            let mat = imdecode(&Vector::<u8>::from_slice(&response.data), IMREAD_COLOR).ok()?;
+
+            return frame_from_bgr(&mat)
+                .filter(|frame| frame.width == width && frame.height == height);
+        }
+    };
+    if width <= 0 || height <= 0 || response.data.len() != format.data_len(width, height) {
+        return None;
+    }
+    if matches!(format, PixelFormat::Nv12) && (width % 2 != 0 || height % 2 != 0) {
+        return None;
+    }
+
+    Some(Frame {
+        width,
+        height,
+        data: response.data,
+        format,
+    })
+}
+
+/// Measures the round-trip latencies of `count` `Health` requests to the input server at `dest`.
+#[cfg(debug_assertions)]
+pub async fn measure_input_latency(dest: &str, count: usize) -> Result<Vec<Duration>, Error> {
+    let endpoint = RpcEndpoint::parse(dest)?;
+    let mut client = KeyInputClient::new(timeout(CONNECT_TIMEOUT, endpoint.connect()).await??);
+    let mut latencies = Vec::with_capacity(count);
+    for _ in 0..count {
+        let instant = Instant::now();
+        let result = timeout(
+            HEALTH_CHECK_TIMEOUT,
+            client.health(Request::new(HealthRequest {})),
+        )
+        .await?;
+        // Still a full round trip for servers implemented before `Health` was added
+        if let Err(status) = result
+            && status.code() != Code::Unimplemented
+        {
+            return Err(status.into());
+        }
+        latencies.push(instant.elapsed());
+    }
+
+    Ok(latencies)
+}
+
+fn connect_input_client(endpoint: &RpcEndpoint) -> Result<KeyInputClient<Channel>, Error> {
+    let endpoint = endpoint.clone();
+    let channel = block_future(async move { timeout(CONNECT_TIMEOUT, endpoint.connect()).await })??;
+
+    Ok(KeyInputClient::new(channel))
+}
+
+#[cfg(unix)]
+async fn connect_unix(path: PathBuf) -> Result<Channel, Error> {
+    let channel = Endpoint::from_static(PLACEHOLDER_URI)
+        .connect_with_connector(service_fn(move |_: Uri| {
+            let path = path.clone();
+            async move { io::Result::Ok(TokioIo::new(UnixStream::connect(path).await?)) }
+        }))
+        .await?;
+
+    Ok(channel)
+}
+
+#[cfg(not(unix))]
+async fn connect_unix(_path: PathBuf) -> Result<Channel, Error> {
+    bail!("unix domain socket is not supported on this platform")
+}
+
+#[cfg(windows)]
+async fn connect_named_pipe(name: String) -> Result<Channel, Error> {
+    const ERROR_PIPE_BUSY: i32 = 231;
+
+    let channel = Endpoint::from_static(PLACEHOLDER_URI)
+        .connect_with_connector(service_fn(move |_: Uri| {
+            let name = name.clone();
+            async move {
+                loop {
+                    match ClientOptions::new().open(&name) {
+                        // All pipe instances are busy so waits for the server to create another
+                        Err(err) if err.raw_os_error() == Some(ERROR_PIPE_BUSY) => {
+                            sleep(Duration::from_millis(50)).await;
+                        }
+                        result => return result.map(TokioIo::new),
+                    }
+                }
+            }
+        }))
+        .await?;
+
+    Ok(channel)
+}
+
+#[cfg(not(windows))]
+async fn connect_named_pipe(_name: String) -> Result<Channel, Error> {
+    bail!("named pipe is not supported on this platform")
+}
+
+#[inline]
+fn can_send_key(key_down: &BitVec, key: Key, is_down: bool) -> bool {
+    let key_num = i32::from(key) as usize;
+    let was_down = key_down.get(key_num).unwrap();
+    !matches!((was_down, is_down), (true, true) | (false, false))
+}
+
 #[inline]
 fn block_future<F: Future>(f: F) -> F::Output {
     block_in_place(|| Handle::current().block_on(f))
@@ -163,5 +750,524 @@ fn block_future<F: Future>(f: F) -> F::Output {
 
 #[cfg(test)]
 mod test {
-    // TODO HOW TO?
+    use std::assert_matches::assert_matches;
+    #[cfg(unix)]
+    use std::fs;
+    #[cfg(unix)]
+    use std::io;
+    use std::net::SocketAddr;
+    use std::path::Path;
+    #[cfg(unix)]
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex};
+    #[cfg(unix)]
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    use opencv::core::{CV_8UC3, Mat, Scalar, Vector};
+    use opencv::imgcodecs::imencode;
+    use opencv::prelude::*;
+    use platforms::Error as PlatformError;
+    use platforms::capture::PixelFormat;
+    #[cfg(unix)]
+    use tokio::net::{UnixListener, UnixStream};
+    use tokio::sync::{Notify, watch};
+    use tokio::task::JoinHandle;
+    use tokio::time::{Instant, advance};
+    use tonic::codegen::BoxStream;
+    #[cfg(unix)]
+    use tonic::codegen::tokio_stream::Stream;
+    use tonic::codegen::tokio_stream::{self, StreamExt};
+    use tonic::transport::Server;
+    use tonic::transport::server::TcpIncoming;
+    use tonic::{Request, Response, Status};
+
+    use super::frame::frame_source_server::{FrameSource, FrameSourceServer};
+    use super::frame::{FrameFormat, FrameResponse, FrameStreamRequest};
+    use super::input::key_input_server::{KeyInput, KeyInputServer};
+    use super::input::{
+        HealthRequest, HealthResponse, KeyDownRequest, KeyDownResponse, KeyInitRequest,
+        KeyInitResponse, KeyRequest, KeyResponse, KeySequenceEvent, KeySequenceRequest,
+        KeySequenceResponse, KeyStateRequest, KeyStateResponse, KeyUpRequest, KeyUpResponse,
+        MouseRequest, MouseResponse,
+    };
+    use super::{
+        FRAME_MAX_AGE, FrameService, InputConnection, InputConnectionEvent, InputService, Key,
+        KeySequenceEventKind, RpcEndpoint, SequenceEvent, frame_from_response,
+    };
+
+    /// A stand-in for the input server recording received sequences.
+    #[derive(Clone)]
+    struct FakeKeyInput {
+        sequences: Arc<Mutex<Vec<Vec<KeySequenceEvent>>>>,
+        /// Seeds received from `Init`.
+        seeds: Arc<Mutex<Vec<Vec<u8>>>>,
+        /// Whether to implement `SendSequence` like servers implemented before it was added.
+        sequence_supported: bool,
+    }
+
+    #[tonic::async_trait]
+    impl KeyInput for FakeKeyInput {
+        async fn init(
+            &self,
+            request: Request<KeyInitRequest>,
+        ) -> Result<Response<KeyInitResponse>, Status> {
+            self.seeds.lock().unwrap().push(request.into_inner().seed);
+
+            Ok(Response::new(KeyInitResponse::default()))
+        }
+
+        async fn key_state(
+            &self,
+            _: Request<KeyStateRequest>,
+        ) -> Result<Response<KeyStateResponse>, Status> {
+            Ok(Response::new(KeyStateResponse::default()))
+        }
+
+        async fn send_mouse(
+            &self,
+            _: Request<MouseRequest>,
+        ) -> Result<Response<MouseResponse>, Status> {
+            Ok(Response::new(MouseResponse {}))
+        }
+
+        async fn send(&self, _: Request<KeyRequest>) -> Result<Response<KeyResponse>, Status> {
+            Ok(Response::new(KeyResponse {}))
+        }
+
+        async fn send_up(
+            &self,
+            _: Request<KeyUpRequest>,
+        ) -> Result<Response<KeyUpResponse>, Status> {
+            Ok(Response::new(KeyUpResponse {}))
+        }
+
+        async fn send_down(
+            &self,
+            _: Request<KeyDownRequest>,
+        ) -> Result<Response<KeyDownResponse>, Status> {
+            Ok(Response::new(KeyDownResponse {}))
+        }
+
+        async fn send_sequence(
+            &self,
+            request: Request<KeySequenceRequest>,
+        ) -> Result<Response<KeySequenceResponse>, Status> {
+            if !self.sequence_supported {
+                return Err(Status::unimplemented("SendSequence"));
+            }
+            self.sequences
+                .lock()
+                .unwrap()
+                .push(request.into_inner().events);
+
+            Ok(Response::new(KeySequenceResponse {}))
+        }
+
+        async fn health(
+            &self,
+            _: Request<HealthRequest>,
+        ) -> Result<Response<HealthResponse>, Status> {
+            Ok(Response::new(HealthResponse {}))
+        }
+    }
+
+    fn fake_input(sequence_supported: bool) -> FakeKeyInput {
+        FakeKeyInput {
+            sequences: Arc::new(Mutex::new(vec![])),
+            seeds: Arc::new(Mutex::new(vec![])),
+            sequence_supported,
+        }
+    }
+
+    fn serve_input(
+        sequence_supported: bool,
+    ) -> (SocketAddr, Arc<Mutex<Vec<Vec<KeySequenceEvent>>>>) {
+        let input = fake_input(sequence_supported);
+        let sequences = input.sequences.clone();
+        let (addr, _, _) = serve_input_at(input, "127.0.0.1:0".parse().unwrap());
+
+        (addr, sequences)
+    }
+
+    /// Serves `input` at `addr` until notified to shut down.
+    fn serve_input_at(
+        input: FakeKeyInput,
+        addr: SocketAddr,
+    ) -> (SocketAddr, Arc<Notify>, JoinHandle<()>) {
+        let incoming = TcpIncoming::bind(addr).unwrap();
+        let addr = incoming.local_addr().unwrap();
+        let shutdown = Arc::new(Notify::new());
+        let notified = shutdown.clone();
+        let handle = tokio::spawn(async move {
+            let _ = Server::builder()
+                .add_service(KeyInputServer::new(input))
+                .serve_with_incoming_shutdown(incoming, notified.notified())
+                .await;
+        });
+
+        (addr, shutdown, handle)
+    }
+
+    /// Serves `input` on a Unix domain socket at `path`.
+    #[cfg(unix)]
+    fn serve_input_unix(input: FakeKeyInput, path: &Path) {
+        struct UnixIncoming(UnixListener);
+
+        impl Stream for UnixIncoming {
+            type Item = io::Result<UnixStream>;
+
+            fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+                self.0
+                    .poll_accept(cx)
+                    .map(|result| Some(result.map(|(stream, _)| stream)))
+            }
+        }
+
+        let _ = fs::remove_file(path);
+        let incoming = UnixIncoming(UnixListener::bind(path).unwrap());
+        tokio::spawn(
+            Server::builder()
+                .add_service(KeyInputServer::new(input))
+                .serve_with_incoming(incoming),
+        );
+    }
+
+    /// A stand-in for the game-side agent streaming a fixed list of frames.
+    struct FakeFrameSource {
+        responses: Vec<FrameResponse>,
+        /// Whether to end the stream after the last frame instead of keeping it open.
+        end: bool,
+    }
+
+    #[tonic::async_trait]
+    impl FrameSource for FakeFrameSource {
+        type StreamFramesStream = BoxStream<FrameResponse>;
+
+        async fn stream_frames(
+            &self,
+            _: Request<FrameStreamRequest>,
+        ) -> Result<Response<Self::StreamFramesStream>, Status> {
+            let responses = tokio_stream::iter(self.responses.clone()).map(Ok);
+            let stream: Self::StreamFramesStream = if self.end {
+                Box::pin(responses)
+            } else {
+                Box::pin(responses.chain(tokio_stream::pending()))
+            };
+
+            Ok(Response::new(stream))
+        }
+    }
+
+    fn serve_frames(responses: Vec<FrameResponse>, end: bool) -> SocketAddr {
+        serve_frames_at(responses, end, "127.0.0.1:0".parse().unwrap())
+    }
+
+    fn serve_frames_at(responses: Vec<FrameResponse>, end: bool, addr: SocketAddr) -> SocketAddr {
+        let incoming = TcpIncoming::bind(addr).unwrap();
+        let addr = incoming.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(FrameSourceServer::new(FakeFrameSource { responses, end }))
+                .serve_with_incoming(incoming),
+        );
+
+        addr
+    }
+
+    fn connect(addr: SocketAddr) -> FrameService {
+        FrameService::connect(format!("http://{addr}")).unwrap()
+    }
+
+    fn raw_response() -> FrameResponse {
+        FrameResponse {
+            width: 2,
+            height: 1,
+            format: FrameFormat::Rgba.into(),
+            data: vec![1, 2, 3, 4, 5, 6, 7, 8],
+        }
+    }
+
+    fn encoded_response(width: i32, height: i32) -> FrameResponse {
+        let mat =
+            Mat::new_rows_cols_with_default(height, width, CV_8UC3, Scalar::all(7.0)).unwrap();
+        let mut data = Vector::<u8>::new();
+        imencode(".png", &mat, &mut data, &Vector::new()).unwrap();
+
+        FrameResponse {
+            width,
+            height,
+            format: FrameFormat::Encoded.into(),
+            data: data.to_vec(),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn frame_service_grab_raw_frame() {
+        let mut service = connect(serve_frames(vec![raw_response()], false));
+
+        service
+            .frame
+            .clone()
+            .wait_for(Option::is_some)
+            .await
+            .unwrap();
+        let frame = service.grab().unwrap();
+        assert_eq!(frame.width, 2);
+        assert_eq!(frame.height, 1);
+        assert_eq!(frame.format, PixelFormat::Rgba);
+        assert_eq!(frame.data, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn frame_service_grab_encoded_frame_skip_invalid() {
+        let invalid = FrameResponse {
+            width: 2,
+            height: 2,
+            format: FrameFormat::Bgra.into(),
+            data: vec![0; 4],
+        };
+        let mismatched = FrameResponse {
+            width: 4,
+            ..encoded_response(8, 4)
+        };
+        let responses = vec![invalid, mismatched, encoded_response(8, 4)];
+        let mut service = connect(serve_frames(responses, false));
+
+        service
+            .frame
+            .clone()
+            .wait_for(Option::is_some)
+            .await
+            .unwrap();
+        let frame = service.grab().unwrap();
+        assert_eq!(frame.width, 8);
+        assert_eq!(frame.height, 4);
+        assert_eq!(frame.format, PixelFormat::Bgra);
+        assert_eq!(&frame.data[..4], &[7, 7, 7, 255]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn frame_service_grab_stream_ended() {
+        let mut service = connect(serve_frames(vec![raw_response()], true));
+        let mut receiver = service.frame.clone();
+
+        receiver.wait_for(Option::is_some).await.unwrap();
+        receiver.wait_for(Option::is_none).await.unwrap();
+        assert_eq!(
+            service.grab().unwrap_err(),
+            PlatformError::WindowFrameNotAvailable
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn frame_service_grab_stream_reopened() {
+        let mut service = connect(serve_frames(vec![raw_response()], true));
+        let mut receiver = service.frame.clone();
+
+        receiver.wait_for(Option::is_some).await.unwrap();
+        receiver.wait_for(Option::is_none).await.unwrap();
+        receiver.wait_for(Option::is_some).await.unwrap();
+        assert!(service.grab().is_ok());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn frame_service_grab_connected_after_retry() {
+        // Reserves a free port without listening on it
+        let addr = TcpIncoming::bind("127.0.0.1:0".parse().unwrap())
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let mut service = connect(addr);
+
+        assert_eq!(
+            service.grab().unwrap_err(),
+            PlatformError::WindowFrameNotAvailable
+        );
+        let _ = serve_frames_at(vec![raw_response()], false, addr);
+        service
+            .frame
+            .clone()
+            .wait_for(Option::is_some)
+            .await
+            .unwrap();
+        assert!(service.grab().is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn frame_service_grab_stale_frame() {
+        let frame = frame_from_response(raw_response()).unwrap();
+        let (_sender, frame) = watch::channel(Some((frame, Instant::now())));
+        let mut service = FrameService {
+            frame,
+            task: tokio::spawn(async {}),
+        };
+
+        assert!(service.grab().is_ok());
+        advance(FRAME_MAX_AGE + Duration::from_millis(1)).await;
+        assert_eq!(
+            service.grab().unwrap_err(),
+            PlatformError::WindowFrameNotAvailable
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn input_service_send_sequence_skip_redundant_key_up_down() {
+        let (addr, sequences) = serve_input(true);
+        let mut service = InputService::connect(format!("http://{addr}")).unwrap();
+
+        service
+            .send_sequence(vec![
+                (0.0, SequenceEvent::KeyUp(Key::A)),
+                (10.0, SequenceEvent::KeyDown(Key::A)),
+                (5.0, SequenceEvent::KeyDown(Key::A)),
+                (20.0, SequenceEvent::Key(Key::S, 50.0)),
+            ])
+            .unwrap();
+        assert_eq!(
+            *sequences.lock().unwrap(),
+            vec![vec![
+                KeySequenceEvent {
+                    delay_ms: 10.0,
+                    event: Some(KeySequenceEventKind::KeyDown(KeyDownRequest {
+                        key: Key::A.into()
+                    })),
+                },
+                KeySequenceEvent {
+                    delay_ms: 25.0,
+                    event: Some(KeySequenceEventKind::Key(KeyRequest {
+                        key: Key::S.into(),
+                        down_ms: 50.0
+                    })),
+                },
+            ]]
+        );
+        assert!(service.can_send_key(Key::A, false));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn input_service_send_sequence_unimplemented() {
+        let (addr, sequences) = serve_input(false);
+        let mut service = InputService::connect(format!("http://{addr}")).unwrap();
+
+        assert!(
+            service
+                .send_sequence(vec![(0.0, SequenceEvent::KeyDown(Key::A))])
+                .is_err()
+        );
+        assert!(!service.sequence_supported());
+        assert!(service.can_send_key(Key::A, true));
+        assert!(sequences.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn input_service_update_connection_connected_after_retry() {
+        // Reserves a free port without listening on it
+        let addr = TcpIncoming::bind("127.0.0.1:0".parse().unwrap())
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let mut service = InputService::connect(format!("http://{addr}")).unwrap();
+        assert_matches!(*service.connection.borrow(), InputConnection::Disconnected);
+        assert!(service.init(&[1, 2, 3]).is_err());
+        assert_eq!(service.update_connection(), None);
+
+        let input = fake_input(true);
+        let seeds = input.seeds.clone();
+        let _ = serve_input_at(input, addr);
+        service
+            .connection
+            .clone()
+            .wait_for(|connection| matches!(connection, InputConnection::Connected { .. }))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            service.update_connection(),
+            Some(InputConnectionEvent::Connected)
+        );
+        assert_eq!(service.update_connection(), None);
+        assert_eq!(*seeds.lock().unwrap(), vec![vec![1, 2, 3]]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn input_service_update_connection_disconnected_and_reconnected() {
+        let input = fake_input(true);
+        let seeds = input.seeds.clone();
+        let (addr, shutdown, handle) =
+            serve_input_at(input.clone(), "127.0.0.1:0".parse().unwrap());
+        let mut service = InputService::connect(format!("http://{addr}")).unwrap();
+        let mut connection = service.connection.clone();
+        service.init(&[1, 2, 3]).unwrap();
+        service.send_key_down(Key::A).unwrap();
+        assert_eq!(
+            service.update_connection(),
+            Some(InputConnectionEvent::Connected)
+        );
+
+        shutdown.notify_one();
+        handle.await.unwrap();
+        connection
+            .wait_for(|connection| matches!(connection, InputConnection::Disconnected))
+            .await
+            .unwrap();
+        assert_eq!(
+            service.update_connection(),
+            Some(InputConnectionEvent::Disconnected)
+        );
+        assert_eq!(service.update_connection(), None);
+        assert!(service.send_key_up(Key::A).is_err());
+
+        let _ = serve_input_at(input, addr);
+        connection
+            .wait_for(|connection| matches!(connection, InputConnection::Connected { .. }))
+            .await
+            .unwrap();
+        assert_eq!(
+            service.update_connection(),
+            Some(InputConnectionEvent::Reconnected)
+        );
+        assert_eq!(*seeds.lock().unwrap(), vec![vec![1, 2, 3], vec![1, 2, 3]]);
+        assert!(service.can_send_key(Key::A, true));
+    }
+
+    #[test]
+    fn rpc_endpoint_parse_transports() {
+        assert_matches!(
+            RpcEndpoint::parse("http://localhost:5001"),
+            Ok(RpcEndpoint::Http(_))
+        );
+        assert_matches!(
+            RpcEndpoint::parse("unix:///tmp/input.sock"),
+            Ok(RpcEndpoint::Unix(path)) if path == Path::new("/tmp/input.sock")
+        );
+        assert_matches!(
+            RpcEndpoint::parse("pipe://input"),
+            Ok(RpcEndpoint::NamedPipe(name)) if name == r"\\.\pipe\input"
+        );
+        assert_matches!(
+            RpcEndpoint::parse(r"\\.\pipe\input"),
+            Ok(RpcEndpoint::NamedPipe(name)) if name == r"\\.\pipe\input"
+        );
+        assert!(RpcEndpoint::parse("unix://").is_err());
+        assert!(RpcEndpoint::parse("pipe://").is_err());
+        assert!(RpcEndpoint::parse("not a url").is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn input_service_connect_unix_socket() {
+        let path = std::env::temp_dir().join(format!("komari-input-{}.sock", std::process::id()));
+        let input = fake_input(true);
+        let sequences = input.sequences.clone();
+        serve_input_unix(input, &path);
+
+        let mut service = InputService::connect(format!("unix://{}", path.display())).unwrap();
+        service
+            .send_sequence(vec![(0.0, SequenceEvent::KeyDown(Key::A))])
+            .unwrap();
+        assert_eq!(sequences.lock().unwrap().len(), 1);
+
+        let _ = fs::remove_file(path);
+    }
 }
@@ -55,6 +55,14 @@ impl CharacterService for DefaultCharacterService {
                     (_, PotionMode::Percentage(percent)) => Some(percent / 100.0),
                 };
             state.config.update_health_millis = Some(character.health_update_millis);
+            state.config.mana_potion_key = character.mana_potion_key.key.into();
+            state.config.use_mana_potion_below_percent = match (
+                character.mana_potion_key.enabled,
+                character.mana_potion_mode,
+            ) {
+                (false, _) | (_, PotionMode::EveryMillis(_)) => None,
+                (_, PotionMode::Percentage(percent)) => Some(percent / 100.0),
+            };
         }
     }
 }
@@ -110,6 +118,11 @@ mod tests {
             },
             potion_mode: PotionMode::Percentage(50.0),
             health_update_millis: 3000,
+            mana_potion_key: KeyBindingConfiguration {
+                key: KeyBinding::O,
+                enabled: true,
+            },
+            mana_potion_mode: PotionMode::Percentage(30.0),
             ..Default::default()
         }
     }
@@ -159,5 +172,7 @@ mod tests {
         assert_eq!(state.config.potion_key, KeyKind::P);
         assert_eq!(state.config.use_potion_below_percent, Some(0.5));
         assert_eq!(state.config.update_health_millis, Some(3000));
+        assert_eq!(state.config.mana_potion_key, KeyKind::O);
+        assert_eq!(state.config.use_mana_potion_below_percent, Some(0.3));
     }
 }
@@ -9,23 +9,35 @@ use opencv::{
 };
 use rand::distr::SampleString;
 use rand_distr::Alphanumeric;
-use tokio::sync::broadcast::{self, Receiver, Sender};
+use tokio::{
+    spawn,
+    sync::{
+        broadcast::{self, Receiver, Sender},
+        oneshot::{self, error::TryRecvError},
+    },
+};
 
 use crate::{
-    DebugState,
+    DebugState, InputLatency,
     context::Context,
     debug::{save_image_for_training, save_image_for_training_to, save_minimap_for_training},
     detect::{ArrowsCalibrating, ArrowsState, CachedDetector, Detector},
     mat::OwnedMat,
+    rpc::measure_input_latency,
 };
 
 const SOLVE_RUNE_TIMEOUT_SECS: u64 = 10;
 
+const INPUT_LATENCY_SAMPLES: usize = 20;
+
 #[derive(Debug)]
 pub struct DebugService {
     state: Sender<DebugState>,
     recording_id: Option<String>,
     infering_rune: Option<(ArrowsCalibrating, Instant)>,
+    input_latencies: Vec<InputLatency>,
+    /// Receives the results of [`Self::compare_input_latency`] measured in the background.
+    input_latencies_receiver: Option<oneshot::Receiver<Vec<InputLatency>>>,
 }
 
 impl Default for DebugService {
@@ -34,12 +46,25 @@ impl Default for DebugService {
             state: broadcast::channel(1).0,
             recording_id: None,
             infering_rune: None,
+            input_latencies: vec![],
+            input_latencies_receiver: None,
         }
     }
 }
 
 impl DebugService {
     pub fn poll(&mut self, context: &Context) {
+        if let Some(receiver) = self.input_latencies_receiver.as_mut() {
+            match receiver.try_recv() {
+                Ok(latencies) => {
+                    self.input_latencies = latencies;
+                    self.input_latencies_receiver = None;
+                }
+                Err(TryRecvError::Empty) => (),
+                Err(TryRecvError::Closed) => self.input_latencies_receiver = None,
+            }
+        }
+
         if let Some(id) = self.recording_id.clone() {
             save_image_for_training_to(context.detector_unwrap().mat(), Some(id), false, false);
         }
@@ -71,6 +96,7 @@ impl DebugService {
             let _ = self.state.send(DebugState {
                 is_recording: self.recording_id.is_some(),
                 is_rune_auto_saving: context.debug.auto_save_rune(),
+                input_latencies: self.input_latencies.clone(),
             });
         }
     }
@@ -109,6 +135,22 @@ impl DebugService {
         }
     }
 
+    /// Measures the latencies of input servers at `urls` for comparing transports.
+    ///
+    /// The measurement runs in the background and its results are published with the next
+    /// [`DebugState`] once completed.
+    pub fn compare_input_latency(&mut self, urls: Vec<String>) {
+        let (sender, receiver) = oneshot::channel();
+        spawn(async move {
+            let mut latencies = Vec::with_capacity(urls.len());
+            for url in urls {
+                latencies.push(input_latency_from(url).await);
+            }
+            let _ = sender.send(latencies);
+        });
+        self.input_latencies_receiver = Some(receiver);
+    }
+
     pub fn test_spin_rune(&self) {
         static SPIN_TEST_DIR: Dir<'static> = include_dir!("$SPIN_TEST_DIR");
         static SPIN_TEST_IMAGES: LazyLock<Vec<Mat>> = LazyLock::new(|| {
@@ -149,3 +191,23 @@ impl DebugService {
         }
     }
 }
+
+async fn input_latency_from(url: String) -> InputLatency {
+    let latency = measure_input_latency(&url, INPUT_LATENCY_SAMPLES)
+        .await
+        .map(|latencies| {
+            let millis = latencies
+                .into_iter()
+                .map(|latency| latency.as_secs_f32() * 1000.0)
+                .collect::<Vec<_>>();
+            let mean = millis.iter().sum::<f32>() / millis.len() as f32;
+            let min = millis.iter().copied().fold(f32::MAX, f32::min);
+            let max = millis.iter().copied().fold(0.0, f32::max);
+
+            (mean, min, max)
+        })
+        .map_err(|err| err.to_string());
+    debug!(target: "debug", "input latency of {url} {latency:?}");
+
+    InputLatency { url, latency }
+}
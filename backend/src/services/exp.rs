@@ -0,0 +1,230 @@
+use std::{
+    fmt::Debug,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::error;
+#[cfg(test)]
+use mockall::automock;
+
+use crate::database::{ExpSession, upsert_exp_session};
+
+/// The interval in milliseconds to save the current EXP session.
+const SAVE_INTERVAL_MILLIS: i64 = 60_000;
+
+/// The maximum EXP percentage gained per second between two readings to be counted.
+///
+/// The maximum change between two readings grows with the time between them so that EXP gained
+/// while readings fail is still counted. Larger changes are treated as OCR misreads.
+const MAX_EXP_DELTA_PER_SEC: f32 = 4.0;
+
+/// A service to track EXP gained while running.
+#[cfg_attr(test, automock)]
+pub trait ExpService: Debug {
+    /// Updates the current session with `exp` read in the current tick if any.
+    ///
+    /// The current session is ended and saved when `running` is `false` or when any of
+    /// `character_id`, `minimap_id` or `preset` changes.
+    fn update(
+        &mut self,
+        exp: Option<f32>,
+        running: bool,
+        character_id: Option<i64>,
+        minimap_id: Option<i64>,
+        preset: Option<String>,
+    );
+
+    /// Gets the current EXP session.
+    #[allow(clippy::needless_lifetimes)]
+    fn session<'a>(&'a self) -> Option<&'a ExpSession>;
+}
+
+#[derive(Debug, Default)]
+pub struct DefaultExpService {
+    session: Option<ExpSession>,
+    /// The last EXP reading and the milliseconds it was read at.
+    last_exp: Option<(f32, i64)>,
+    last_save_millis: i64,
+}
+
+impl DefaultExpService {
+    /// Updates the current session at `now_millis` and returns the session to save if any.
+    fn update_session(
+        &mut self,
+        exp: Option<f32>,
+        running: bool,
+        ids: Option<(i64, i64)>,
+        preset: Option<String>,
+        now_millis: i64,
+    ) -> Option<ExpSession> {
+        let ended = self.session.as_ref().is_some_and(|session| {
+            !running
+                || ids != Some((session.character_id, session.minimap_id))
+                || preset != session.preset
+        });
+        if ended {
+            self.last_exp = None;
+            return self.session.take();
+        }
+
+        let Some((character_id, minimap_id)) = ids.filter(|_| running) else {
+            return None;
+        };
+        let session = self.session.get_or_insert_with(|| {
+            self.last_save_millis = now_millis;
+            ExpSession {
+                character_id,
+                minimap_id,
+                preset,
+                start_millis: now_millis,
+                end_millis: now_millis,
+                ..ExpSession::default()
+            }
+        });
+        session.end_millis = now_millis;
+        if let Some(exp) = exp {
+            if let Some((last_exp, last_millis)) = self.last_exp {
+                session.gained += exp_delta(last_exp, exp, now_millis - last_millis).unwrap_or(0.0);
+            }
+            // A misread also becomes the new baseline so it is not compared against forever
+            self.last_exp = Some((exp, now_millis));
+        }
+
+        if now_millis - self.last_save_millis >= SAVE_INTERVAL_MILLIS {
+            self.last_save_millis = now_millis;
+            return Some(session.clone());
+        }
+        None
+    }
+}
+
+impl ExpService for DefaultExpService {
+    fn update(
+        &mut self,
+        exp: Option<f32>,
+        running: bool,
+        character_id: Option<i64>,
+        minimap_id: Option<i64>,
+        preset: Option<String>,
+    ) {
+        let ids = character_id.zip(minimap_id);
+        let Some(mut session) = self.update_session(exp, running, ids, preset, now_millis()) else {
+            return;
+        };
+        if let Err(err) = upsert_exp_session(&mut session) {
+            error!(target: "exp", "failed to save EXP session {err}");
+            return;
+        }
+        // Keep the id of the newly inserted session so later saves update the same row
+        if let Some(current) = self.session.as_mut() {
+            current.id = session.id;
+        }
+    }
+
+    fn session(&self) -> Option<&ExpSession> {
+        self.session.as_ref()
+    }
+}
+
+/// Computes the EXP gained from `last` to `current` percentage read `elapsed_millis` apart.
+///
+/// A drop is treated as a level up. Returns [`None`] if the gain is larger than
+/// [`MAX_EXP_DELTA_PER_SEC`] allows within `elapsed_millis`.
+#[inline]
+fn exp_delta(last: f32, current: f32, elapsed_millis: i64) -> Option<f32> {
+    let delta = if current >= last {
+        current - last
+    } else {
+        (100.0 - last) + current
+    };
+    let max_delta = MAX_EXP_DELTA_PER_SEC * elapsed_millis.max(0) as f32 / 1000.0;
+
+    (delta <= max_delta).then_some(delta)
+}
+
+#[inline]
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exp_delta_level_up_and_misread() {
+        assert_eq!(exp_delta(10.0, 10.5, 5000), Some(0.5));
+        assert_eq!(exp_delta(99.0, 1.0, 5000), Some(2.0));
+        assert_eq!(exp_delta(10.0, 80.0, 5000), None);
+        assert_eq!(exp_delta(50.0, 1.0, 5000), None);
+    }
+
+    #[test]
+    fn exp_delta_scales_with_elapsed_time() {
+        assert_eq!(exp_delta(10.0, 25.0, 5000), Some(15.0));
+        assert_eq!(exp_delta(10.0, 40.0, 5000), None);
+        assert_eq!(exp_delta(10.0, 40.0, 60_000), Some(30.0));
+    }
+
+    #[test]
+    fn update_session_resets_baseline_after_misread() {
+        let mut service = DefaultExpService::default();
+        service.update_session(Some(10.0), true, Some((1, 2)), None, 0);
+        service.update_session(Some(90.0), true, Some((1, 2)), None, 5000);
+        service.update_session(Some(91.0), true, Some((1, 2)), None, 10_000);
+
+        assert_eq!(service.session().unwrap().gained, 1.0);
+    }
+
+    #[test]
+    fn update_session_accumulates_and_saves_periodically() {
+        let mut service = DefaultExpService::default();
+
+        assert!(
+            service
+                .update_session(Some(10.0), true, Some((1, 2)), None, 0)
+                .is_none()
+        );
+        assert!(
+            service
+                .update_session(Some(11.0), true, Some((1, 2)), None, 1000)
+                .is_none()
+        );
+        let saved = service
+            .update_session(Some(12.5), true, Some((1, 2)), None, SAVE_INTERVAL_MILLIS)
+            .unwrap();
+
+        assert_eq!(saved.gained, 2.5);
+        assert_eq!(saved.start_millis, 0);
+        assert_eq!(saved.end_millis, SAVE_INTERVAL_MILLIS);
+        assert!(service.session().is_some());
+    }
+
+    #[test]
+    fn update_session_ends_on_halt_or_change() {
+        let mut service = DefaultExpService::default();
+        service.update_session(Some(10.0), true, Some((1, 2)), None, 0);
+
+        let saved = service
+            .update_session(Some(10.0), true, Some((1, 3)), None, 1000)
+            .unwrap();
+        assert_eq!(saved.minimap_id, 2);
+        assert!(service.session().is_none());
+
+        service.update_session(Some(10.0), true, Some((1, 3)), None, 2000);
+        assert!(
+            service
+                .update_session(Some(10.0), false, Some((1, 3)), None, 3000)
+                .is_some()
+        );
+        assert!(
+            service
+                .update_session(Some(10.0), false, Some((1, 3)), None, 4000)
+                .is_none()
+        );
+        assert!(service.session().is_none());
+    }
+}
@@ -17,9 +17,10 @@ use crate::{
     bridge::InputReceiver,
     buff::BuffKind,
     context::{Context, Operation},
+    database::ExpSession,
     database_event_receiver, minimap,
     player::{PlayerState, Quadrant},
-    skill::SkillKind,
+    skill::{Skill, SkillKind},
 };
 
 #[derive(Debug)]
@@ -29,6 +30,7 @@ pub enum GameEvent {
     CharacterUpdated(Option<Character>),
     SettingsUpdated(Settings),
     NavigationPathsUpdated,
+    ProfileSwitched(Settings),
 }
 
 /// A service to handle game-related incoming requests and events polling.
@@ -64,7 +66,14 @@ pub trait GameService: Debug {
 
     /// Broadcasts game state to listeners.
     #[cfg_attr(test, concretize)]
-    fn broadcast_state(&self, context: &Context, player: &PlayerState, minimap: Option<&Minimap>);
+    fn broadcast_state(
+        &self,
+        context: &Context,
+        player: &PlayerState,
+        minimap: Option<&Minimap>,
+        map_name: Option<&str>,
+        exp_session: Option<&ExpSession>,
+    );
 
     /// Subscribes to game state.
     fn subscribe_state(&self) -> Receiver<GameState>;
@@ -148,14 +157,30 @@ impl GameService for DefaultGameService {
     }
 
     #[cfg_attr(test, concretize)]
-    fn broadcast_state(&self, context: &Context, player: &PlayerState, minimap: Option<&Minimap>) {
+    fn broadcast_state(
+        &self,
+        context: &Context,
+        player: &PlayerState,
+        minimap: Option<&Minimap>,
+        map_name: Option<&str>,
+        exp_session: Option<&ExpSession>,
+    ) {
         if self.game_state_sender.is_empty() {
             let position = player.last_known_pos.map(|pos| (pos.x, pos.y));
             let state = context.player.to_string();
+            let map_name = map_name.map(str::to_string);
             let health = player.health();
+            let mana = player.mana();
+            let exp = player.exp();
+            let exp_gained = exp_session.map(|session| session.gained);
+            let exp_per_hour = exp_session.and_then(ExpSession::exp_per_hour);
             let normal_action = player.normal_action_name();
             let priority_action = player.priority_action_name();
-            let erda_shower_state = context.skills[SkillKind::ErdaShower].to_string();
+            let erda_shower_state = context
+                .skills
+                .get(SkillKind::ErdaShower.index())
+                .map(Skill::to_string)
+                .unwrap_or_default();
             let destinations = player
                 .last_destinations
                 .clone()
@@ -220,6 +245,11 @@ impl GameService for DefaultGameService {
                 let game_state = GameState {
                     position,
                     health,
+                    mana,
+                    exp,
+                    exp_gained,
+                    exp_per_hour,
+                    map_name,
                     state,
                     normal_action,
                     priority_action,
@@ -337,9 +367,18 @@ fn buffs_from(character: &Character) -> Vec<(BuffKind, KeyBinding)> {
                     .extreme_gold_potion_key
                     .enabled
                     .then_some(character.extreme_gold_potion_key.key),
+                BuffKind::Custom(_) => None,
             };
             Some(kind).zip(enabled_key)
         })
+        .chain(
+            character
+                .buffs
+                .iter()
+                .enumerate()
+                .filter(|(_, buff)| buff.key.enabled)
+                .map(|(index, buff)| (BuffKind::Custom(index), buff.key.key)),
+        )
         .collect()
 }
 
@@ -370,6 +409,18 @@ fn actions_from(character: &Character) -> Vec<Action> {
             ..ActionKey::default()
         }));
     }
+    if let KeyBindingConfiguration { key, enabled: true } = character.mana_potion_key
+        && let PotionMode::EveryMillis(millis) = character.mana_potion_mode
+    {
+        vec.push(Action::Key(ActionKey {
+            key,
+            count: 1,
+            condition: ActionCondition::EveryMillis(millis),
+            wait_before_use_millis: 350,
+            wait_after_use_millis: 350,
+            ..ActionKey::default()
+        }));
+    }
 
     let mut i = 0;
     let config_actions = &character.actions;
@@ -454,6 +505,10 @@ fn poll_database(
                 return Some(GameEvent::CharacterUpdated(None));
             }
         }
+        DatabaseEvent::ProfileSwitched(settings) => {
+            return Some(GameEvent::ProfileSwitched(settings));
+        }
+        DatabaseEvent::ExpSessionUpdated | DatabaseEvent::RowQuarantined(_) => (),
     }
 
     None
@@ -542,6 +597,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn actions_from_mana_potion_every_millis() {
+        let character = Character {
+            mana_potion_key: KeyBindingConfiguration {
+                key: KeyBinding::O,
+                enabled: true,
+            },
+            mana_potion_mode: PotionMode::EveryMillis(60000),
+            ..Default::default()
+        };
+
+        assert_matches!(
+            actions_from(&character).as_slice(),
+            [Action::Key(ActionKey {
+                key: KeyBinding::O,
+                condition: ActionCondition::EveryMillis(60000),
+                ..
+            })]
+        );
+        assert!(
+            actions_from(&Character {
+                mana_potion_mode: PotionMode::Percentage(30.0),
+                ..character
+            })
+            .is_empty()
+        );
+    }
+
     #[test]
     fn update_include_actions_while_fixed_actions_disabled() {
         let actions = vec![
@@ -1,21 +1,45 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, sync::Arc};
 
+use anyhow::Result;
+use log::{debug, info};
 #[cfg(test)]
 use mockall::automock;
+use opencv::core::Rect;
+use tokio::sync::broadcast::{self, Receiver, Sender, error::TryRecvError};
 
 use crate::{
+    DatabaseEvent, MinimapAutoSelectPolicy,
     context::Context,
-    database::Minimap as MinimapData,
+    database::{
+        Minimap as MinimapData, MinimapFingerprint, NavigationPaths, query_minimaps,
+        query_navigation_paths,
+    },
+    database_event_receiver,
+    detect::Detector,
     minimap::{Minimap, MinimapState},
+    navigator::decode_base64_to_mat,
     pathing::Platform,
     player::PlayerState,
+    services::navigator::extract_minimap_and_name_base64,
+    task::{Task, Update, update_detection_task},
 };
 
+/// The number of matches in a row required by [`MinimapAutoSelectPolicy::Consecutive`].
+const AUTO_SELECT_CONSECUTIVE_COUNT: u32 = 3;
+
 /// A service to handle minimap-related incoming requests.
 #[cfg_attr(test, automock)]
 pub trait MinimapService: Debug {
     /// Creates a new [`MinimapData`] from currently detected minimap with `name`.
-    fn create(&self, context: &Context, name: String) -> Option<MinimapData>;
+    ///
+    /// The recognized map name from `minimap_state` is also stored so the [`MinimapData`] can be
+    /// looked up by map name.
+    fn create(
+        &self,
+        context: &Context,
+        minimap_state: &MinimapState,
+        name: String,
+    ) -> Option<MinimapData>;
 
     /// Gets the currently in use [`MinimapData`].
     #[allow(clippy::needless_lifetimes)]
@@ -33,19 +57,102 @@ pub trait MinimapService: Debug {
 
     /// Re-detects current minimap.
     fn redetect(&self, context: &mut Context);
+
+    /// Polls for a stored [`MinimapData`] matching the currently detected minimap.
+    ///
+    /// Returns the matched [`MinimapData`] and preset to switch to once `policy` confirms the
+    /// match. The match is also sent to [`Self::subscribe_auto_selected`] subscribers.
+    fn poll_auto_select(
+        &mut self,
+        context: &Context,
+        minimap_state: &MinimapState,
+        policy: MinimapAutoSelectPolicy,
+    ) -> Option<(MinimapData, Option<String>)>;
+
+    /// Subscribes to automatically selected [`MinimapData`] and preset.
+    fn subscribe_auto_selected(&self) -> Receiver<(MinimapData, Option<String>)>;
 }
 
+/// States for automatically selecting a [`MinimapData`] on map entry.
 #[derive(Debug, Default)]
+struct AutoSelect {
+    /// Task to find the stored [`MinimapData`] matching the currently detected minimap.
+    ///
+    /// The task also returns the candidates it matched against to be cached.
+    task: Option<Task<Result<(Arc<Vec<MinimapData>>, Option<MinimapData>)>>>,
+    /// Cached candidates from [`query_auto_select_candidates`].
+    ///
+    /// Cleared when minimaps or navigation paths change in the database.
+    candidates: Option<Arc<Vec<MinimapData>>>,
+    /// Whether matching for the currently detected minimap has completed.
+    completed: bool,
+    /// The last matched [`MinimapData`] id and the number of times it matched in a row.
+    last_match: Option<(i64, u32)>,
+}
+
+#[derive(Debug)]
 pub struct DefaultMinimapService {
     minimap: Option<MinimapData>,
     preset: Option<String>,
+    auto_select: AutoSelect,
+    auto_selected_sender: Sender<(MinimapData, Option<String>)>,
+    database_event_receiver: Receiver<DatabaseEvent>,
+}
+
+impl DefaultMinimapService {
+    /// Clears cached auto-select candidates if any minimap or navigation paths changed.
+    fn poll_database(&mut self) {
+        loop {
+            match self.database_event_receiver.try_recv() {
+                Ok(
+                    DatabaseEvent::MinimapUpdated(_)
+                    | DatabaseEvent::MinimapDeleted(_)
+                    | DatabaseEvent::NavigationPathsUpdated
+                    | DatabaseEvent::NavigationPathsDeleted
+                    | DatabaseEvent::ProfileSwitched(_),
+                )
+                | Err(TryRecvError::Lagged(_)) => {
+                    self.auto_select.candidates = None;
+                    self.auto_select.task = None;
+                }
+                Ok(_) => (),
+                Err(TryRecvError::Empty | TryRecvError::Closed) => break,
+            }
+        }
+    }
+}
+
+impl Default for DefaultMinimapService {
+    fn default() -> Self {
+        Self {
+            minimap: None,
+            preset: None,
+            auto_select: AutoSelect::default(),
+            auto_selected_sender: broadcast::channel(1).0,
+            database_event_receiver: database_event_receiver(),
+        }
+    }
 }
 
 impl MinimapService for DefaultMinimapService {
-    fn create(&self, context: &Context, name: String) -> Option<MinimapData> {
+    fn create(
+        &self,
+        context: &Context,
+        minimap_state: &MinimapState,
+        name: String,
+    ) -> Option<MinimapData> {
         if let Minimap::Idle(idle) = context.minimap {
+            let fingerprint = extract_minimap_and_name_base64(context).map(
+                |(minimap_snapshot_base64, name_snapshot_base64, _)| MinimapFingerprint {
+                    minimap_snapshot_base64,
+                    minimap_snapshot_grayscale: false,
+                    name_snapshot_base64,
+                },
+            );
             Some(MinimapData {
                 name,
+                map_name: minimap_state.name().map(str::to_string),
+                fingerprint,
                 width: idle.bbox.width,
                 height: idle.bbox.height,
                 ..MinimapData::default()
@@ -102,18 +209,200 @@ impl MinimapService for DefaultMinimapService {
     fn redetect(&self, context: &mut Context) {
         context.minimap = Minimap::Detecting;
     }
+
+    fn poll_auto_select(
+        &mut self,
+        context: &Context,
+        minimap_state: &MinimapState,
+        policy: MinimapAutoSelectPolicy,
+    ) -> Option<(MinimapData, Option<String>)> {
+        self.poll_database();
+        let Minimap::Idle(idle) = context.minimap else {
+            self.auto_select = AutoSelect {
+                candidates: self.auto_select.candidates.take(),
+                ..AutoSelect::default()
+            };
+            return None;
+        };
+        let required_count = match policy {
+            MinimapAutoSelectPolicy::Disabled => return None,
+            MinimapAutoSelectPolicy::Immediately => 1,
+            MinimapAutoSelectPolicy::Consecutive => AUTO_SELECT_CONSECUTIVE_COUNT,
+        };
+        if self.auto_select.completed {
+            return None;
+        }
+
+        let bbox = idle.bbox;
+        let map_name = minimap_state.name().map(str::to_string);
+        let candidates = self.auto_select.candidates.clone();
+        let update =
+            update_detection_task(context, 1000, &mut self.auto_select.task, move |detector| {
+                let candidates = match candidates {
+                    Some(candidates) => candidates,
+                    None => Arc::new(query_auto_select_candidates()?),
+                };
+                let matched =
+                    find_matching_minimap(&*detector, &candidates, bbox, map_name.as_deref())?;
+
+                Ok((candidates, matched))
+            });
+        let matched = match update {
+            Update::Ok((candidates, matched)) => {
+                self.auto_select.candidates = Some(candidates);
+                let Some(matched) = matched else {
+                    self.auto_select.completed = true;
+                    return None;
+                };
+                matched
+            }
+            Update::Err(_) => {
+                self.auto_select.last_match = None;
+                return None;
+            }
+            Update::Pending => return None,
+        };
+        let id = matched.id?;
+        if self.minimap.as_ref().and_then(|minimap| minimap.id) == Some(id) {
+            self.auto_select.completed = true;
+            return None;
+        }
+
+        let count = match self.auto_select.last_match {
+            Some((last_id, count)) if last_id == id => count + 1,
+            _ => 1,
+        };
+        self.auto_select.last_match = Some((id, count));
+        if count < required_count {
+            return None;
+        }
+        self.auto_select.completed = true;
+
+        let preset = self
+            .preset
+            .clone()
+            .filter(|preset| matched.actions.contains_key(preset))
+            .or_else(|| matched.actions.keys().min().cloned());
+        info!(
+            target: "minimap",
+            "automatically selected minimap {} with preset {preset:?}",
+            matched.name
+        );
+        let _ = self
+            .auto_selected_sender
+            .send((matched.clone(), preset.clone()));
+
+        Some((matched, preset))
+    }
+
+    fn subscribe_auto_selected(&self) -> Receiver<(MinimapData, Option<String>)> {
+        self.auto_selected_sender.subscribe()
+    }
+}
+
+/// Queries stored [`MinimapData`] as candidates for automatic selection.
+fn query_auto_select_candidates() -> Result<Vec<MinimapData>> {
+    let mut minimaps = query_minimaps()?;
+    if minimaps
+        .iter()
+        .any(|minimap| minimap.fingerprint.is_none() && minimap.paths_id_index.is_some())
+    {
+        backfill_fingerprints(&mut minimaps, &query_navigation_paths()?);
+    }
+
+    Ok(minimaps)
+}
+
+/// Fills in the fingerprint of each [`MinimapData`] without one from its bound navigation path.
+///
+/// Minimaps created before fingerprints were stored can then still be automatically selected.
+fn backfill_fingerprints(minimaps: &mut [MinimapData], paths: &[NavigationPaths]) {
+    for minimap in minimaps
+        .iter_mut()
+        .filter(|minimap| minimap.fingerprint.is_none())
+    {
+        let Some((paths_id, index)) = minimap.paths_id_index else {
+            continue;
+        };
+        minimap.fingerprint = paths
+            .iter()
+            .find(|paths| paths.id == Some(paths_id))
+            .and_then(|paths| paths.paths.get(index))
+            .map(|path| MinimapFingerprint {
+                minimap_snapshot_base64: path.minimap_snapshot_base64.clone(),
+                minimap_snapshot_grayscale: path.minimap_snapshot_grayscale,
+                name_snapshot_base64: path.name_snapshot_base64.clone(),
+            });
+    }
+}
+
+/// Finds the [`MinimapData`] in `minimaps` whose fingerprint best matches the currently detected
+/// minimap.
+///
+/// Candidates must have the same size as `minimap_bbox`. If both the candidate and the current
+/// minimap have a recognized map name, the names must also be the same.
+fn find_matching_minimap(
+    detector: &dyn Detector,
+    minimaps: &[MinimapData],
+    minimap_bbox: Rect,
+    map_name: Option<&str>,
+) -> Result<Option<MinimapData>> {
+    let name_bbox = detector.detect_minimap_name(minimap_bbox)?;
+    let matched = minimaps
+        .iter()
+        .filter(|minimap| {
+            minimap.width == minimap_bbox.width && minimap.height == minimap_bbox.height
+        })
+        .filter(|minimap| match (map_name, minimap.map_name.as_deref()) {
+            (Some(current), Some(stored)) => current == stored,
+            _ => true,
+        })
+        .filter_map(|minimap| {
+            let fingerprint = minimap.fingerprint.as_ref()?;
+            let minimap_mat = decode_base64_to_mat(
+                &fingerprint.minimap_snapshot_base64,
+                fingerprint.minimap_snapshot_grayscale,
+            )
+            .ok()?;
+            let name_mat = decode_base64_to_mat(&fingerprint.name_snapshot_base64, true).ok()?;
+            let score = detector
+                .detect_minimap_match(
+                    &minimap_mat,
+                    fingerprint.minimap_snapshot_grayscale,
+                    &name_mat,
+                    minimap_bbox,
+                    name_bbox,
+                )
+                .ok()?;
+            debug!(
+                target: "minimap",
+                "candidate minimap {} matched with score {score}",
+                minimap.name
+            );
+
+            Some((score, minimap))
+        })
+        .max_by(|(first_score, _), (second_score, _)| first_score.total_cmp(second_score))
+        .map(|(_, minimap)| minimap.clone());
+
+    Ok(matched)
 }
 
 #[cfg(test)]
 mod tests {
     use std::assert_matches::assert_matches;
 
-    use opencv::core::Rect;
+    use base64::{Engine, prelude::BASE64_STANDARD};
+    use opencv::{
+        core::{CV_8UC3, Mat, MatExprTraitConst, Rect, Vector},
+        imgcodecs::imencode_def,
+    };
 
     use super::*;
     use crate::{
-        Platform as DatabasePlatform,
+        NavigationPath, Platform as DatabasePlatform,
         context::Context,
+        detect::MockDetector,
         minimap::{Minimap, MinimapIdle, MinimapState},
         pathing::Platform,
     };
@@ -124,6 +413,19 @@ mod tests {
         idle
     }
 
+    fn mock_fingerprint() -> MinimapFingerprint {
+        let mat = Mat::zeros(10, 10, CV_8UC3).unwrap().to_mat().unwrap();
+        let mut bytes = Vector::new();
+        imencode_def(".png", &mat, &mut bytes).unwrap();
+        let base64 = BASE64_STANDARD.encode(bytes);
+
+        MinimapFingerprint {
+            minimap_snapshot_base64: base64.clone(),
+            minimap_snapshot_grayscale: false,
+            name_snapshot_base64: base64,
+        }
+    }
+
     fn mock_minimap_data() -> MinimapData {
         MinimapData {
             name: "MapData".to_string(),
@@ -142,14 +444,17 @@ mod tests {
         let service = DefaultMinimapService::default();
         let mut context = Context::new(None, None);
         context.minimap = Minimap::Idle(mock_idle_minimap());
+        let mut state = MinimapState::default();
+        state.set_name("Cavern Upper Path".to_string());
 
-        let result = service.create(&context, "MapData".to_string());
+        let result = service.create(&context, &state, "MapData".to_string());
 
         assert!(result.is_some());
         assert_eq!(
             result.unwrap(),
             MinimapData {
                 name: "MapData".to_string(),
+                map_name: Some("Cavern Upper Path".to_string()),
                 width: 100,
                 height: 100,
                 ..Default::default()
@@ -162,7 +467,11 @@ mod tests {
         let service = DefaultMinimapService::default();
         let context = Context::new(None, None);
 
-        let result = service.create(&context, "ShouldNotExist".to_string());
+        let result = service.create(
+            &context,
+            &MinimapState::default(),
+            "ShouldNotExist".to_string(),
+        );
 
         assert!(result.is_none());
     }
@@ -179,6 +488,118 @@ mod tests {
         assert_eq!(service.preset, preset);
     }
 
+    #[test]
+    fn poll_auto_select_none_when_disabled_or_detecting() {
+        let mut service = DefaultMinimapService::default();
+        let state = MinimapState::default();
+        let mut context = Context::new(None, None);
+
+        assert!(
+            service
+                .poll_auto_select(&context, &state, MinimapAutoSelectPolicy::Immediately)
+                .is_none()
+        );
+
+        context.minimap = Minimap::Idle(mock_idle_minimap());
+        assert!(
+            service
+                .poll_auto_select(&context, &state, MinimapAutoSelectPolicy::Disabled)
+                .is_none()
+        );
+        assert!(service.auto_select.task.is_none());
+    }
+
+    #[test]
+    fn find_matching_minimap_filters_candidates() {
+        let bbox = Rect::new(0, 0, 100, 100);
+        let name_bbox = Rect::new(36, 0, 50, 20);
+        let mut detector = MockDetector::new();
+        detector
+            .expect_detect_minimap_name()
+            .return_once(move |_| Ok(name_bbox));
+        detector
+            .expect_detect_minimap_match()
+            .withf(move |_, _, _, minimap, name| *minimap == bbox && *name == name_bbox)
+            .times(1)
+            .returning(|_, _, _, _, _| Ok(0.9));
+        let minimaps = vec![
+            MinimapData {
+                name: "OtherName".to_string(),
+                map_name: Some("Other".to_string()),
+                fingerprint: Some(mock_fingerprint()),
+                ..mock_minimap_data()
+            },
+            MinimapData {
+                name: "OtherSize".to_string(),
+                width: 50,
+                fingerprint: Some(mock_fingerprint()),
+                ..mock_minimap_data()
+            },
+            MinimapData {
+                name: "NoFingerprint".to_string(),
+                ..mock_minimap_data()
+            },
+            MinimapData {
+                name: "Matched".to_string(),
+                map_name: Some("Cavern".to_string()),
+                fingerprint: Some(mock_fingerprint()),
+                ..mock_minimap_data()
+            },
+        ];
+
+        let matched = find_matching_minimap(&detector, &minimaps, bbox, Some("Cavern")).unwrap();
+
+        assert_eq!(
+            matched.map(|minimap| minimap.name),
+            Some("Matched".to_string())
+        );
+    }
+
+    #[test]
+    fn backfill_fingerprints_from_bound_navigation_path() {
+        let paths = vec![NavigationPaths {
+            id: Some(2),
+            paths: vec![
+                NavigationPath::default(),
+                NavigationPath {
+                    minimap_snapshot_base64: "minimap".to_string(),
+                    minimap_snapshot_grayscale: true,
+                    name_snapshot_base64: "name".to_string(),
+                    ..NavigationPath::default()
+                },
+            ],
+            ..NavigationPaths::default()
+        }];
+        let mut minimaps = vec![
+            MinimapData {
+                paths_id_index: Some((2, 1)),
+                ..mock_minimap_data()
+            },
+            MinimapData {
+                paths_id_index: Some((3, 0)),
+                ..mock_minimap_data()
+            },
+            MinimapData {
+                paths_id_index: Some((2, 1)),
+                fingerprint: Some(mock_fingerprint()),
+                ..mock_minimap_data()
+            },
+        ];
+
+        backfill_fingerprints(&mut minimaps, &paths);
+
+        assert_eq!(
+            minimaps[0].fingerprint,
+            Some(MinimapFingerprint {
+                minimap_snapshot_base64: "minimap".to_string(),
+                minimap_snapshot_grayscale: true,
+                name_snapshot_base64: "name".to_string(),
+            })
+        );
+        assert_eq!(minimaps[1].fingerprint, None);
+        assert_eq!(minimaps[2].fingerprint, Some(mock_fingerprint()));
+    }
+
     #[test]
     fn redetect_sets_minimap_to_detecting() {
         let service = DefaultMinimapService::default();
@@ -226,6 +647,7 @@ mod tests {
         let service = DefaultMinimapService {
             minimap: Some(mock_minimap_data()),
             preset: Some("preset".to_string()),
+            ..Default::default()
         };
         let mut minimap_state = MinimapState::default();
         let mut player_state = PlayerState::default();
@@ -8,7 +8,7 @@ use opencv::{
     core::{ToInputArray, Vector},
     imgcodecs::imencode_def,
 };
-use platforms::input::InputKind;
+use platforms::{Window, input::InputKind};
 use serenity::all::{CreateAttachment, EditInteractionResponse};
 use strum::EnumMessage;
 use tokio::{
@@ -21,8 +21,11 @@ use crate::{
     ActionKeyDirection, ActionKeyWith, Character, CycleRunStopMode, GameState, KeyBinding,
     LinkKeyBinding, Minimap, NavigationPath, RequestHandler, RotateKind, Settings,
     bot::{BotAction, BotCommandKind},
-    bridge::{Capture, DefaultCapture, DefaultInput, DefaultInputReceiver, InputMethod},
-    buff::BuffState,
+    bridge::{
+        Capture, DefaultCapture, DefaultInput, DefaultInputReceiver, Input, InputMethod,
+        InputReceiver,
+    },
+    buff::{Buff, BuffKind, BuffState, custom_buff_states},
     context::{Context, ContextEvent, Operation},
     database::Seeds,
     minimap::MinimapState,
@@ -36,12 +39,14 @@ use crate::{
     services::{
         bot::BotService,
         character::{CharacterService, DefaultCharacterService},
+        exp::{DefaultExpService, ExpService},
         game::{DefaultGameService, GameEvent, GameService},
         minimap::{DefaultMinimapService, MinimapService},
         navigator::{DefaultNavigatorService, NavigatorService},
         rotator::{DefaultRotatorService, RotatorService},
         settings::{DefaultSettingsService, SettingsService},
     },
+    skill::{Skill, SkillState, skill_states_from},
 };
 #[cfg(debug_assertions)]
 use crate::{DebugState, services::debug::DebugService};
@@ -50,6 +55,7 @@ mod bot;
 mod character;
 #[cfg(debug_assertions)]
 mod debug;
+mod exp;
 mod game;
 mod minimap;
 mod navigator;
@@ -61,6 +67,7 @@ pub struct PollArgs<'a> {
     pub context: &'a mut Context,
     pub player: &'a mut PlayerState,
     pub minimap: &'a mut MinimapState,
+    pub skills: &'a mut Vec<SkillState>,
     pub buffs: &'a mut Vec<BuffState>,
     pub rotator: &'a mut dyn Rotator,
     pub navigator: &'a mut dyn Navigator,
@@ -74,6 +81,7 @@ pub struct DefaultService {
     game: Box<dyn GameService>,
     minimap: Box<dyn MinimapService>,
     character: Box<dyn CharacterService>,
+    exp: Box<dyn ExpService>,
     rotator: Box<dyn RotatorService>,
     navigator: Box<dyn NavigatorService>,
     settings: Box<dyn SettingsService>,
@@ -88,43 +96,74 @@ impl DefaultService {
         settings: Rc<RefCell<Settings>>,
         event_receiver: Receiver<ContextEvent>,
     ) -> (Self, DefaultInput, DefaultCapture) {
-        let mut settings_service = DefaultSettingsService::new(settings.clone());
+        let settings_service = DefaultSettingsService::new(settings);
 
         // Initialize with default window and input method
         let window = settings_service.selected_window();
         let input_method = InputMethod::Default(window, InputKind::Focused);
         let mut input = DefaultInput::new(input_method, seeds);
-        let mut input_receiver = DefaultInputReceiver::new(window, InputKind::Focused);
-
-        let mut bot = BotService::default();
+        let input_receiver = DefaultInputReceiver::new(window, InputKind::Focused);
         let mut capture = DefaultCapture::new(window);
-        // Update to current settings
-        settings_service.update_selected_window(
+        let service = Self::new_with_settings_service(
+            settings_service,
+            event_receiver,
             &mut input,
-            &mut input_receiver,
+            input_receiver,
             &mut capture,
-            None,
         );
+
+        (service, input, capture)
+    }
+
+    /// Creates a service that uses the provided `input`, `input_receiver` and `capture` instead
+    /// of the platform-specific ones.
+    ///
+    /// The service does not query any platform windows so it can run without a game.
+    pub fn new_headless(
+        settings: Rc<RefCell<Settings>>,
+        window: Window,
+        event_receiver: Receiver<ContextEvent>,
+        input: &mut dyn Input,
+        input_receiver: impl InputReceiver,
+        capture: &mut dyn Capture,
+    ) -> Self {
+        Self::new_with_settings_service(
+            DefaultSettingsService::new_with_window(settings, window),
+            event_receiver,
+            input,
+            input_receiver,
+            capture,
+        )
+    }
+
+    fn new_with_settings_service(
+        mut settings_service: DefaultSettingsService,
+        event_receiver: Receiver<ContextEvent>,
+        input: &mut dyn Input,
+        mut input_receiver: impl InputReceiver,
+        capture: &mut dyn Capture,
+    ) -> Self {
+        let mut bot = BotService::default();
+        // Update to current settings
+        settings_service.update_selected_window(input, &mut input_receiver, capture, None);
         bot.update(&settings_service.settings());
 
-        let service = Self {
+        Self {
             event_receiver,
             pending_halt: None,
             game: Box::new(DefaultGameService::new(input_receiver)),
             minimap: Box::new(DefaultMinimapService::default()),
             character: Box::new(DefaultCharacterService::default()),
+            exp: Box::new(DefaultExpService::default()),
             rotator: Box::new(DefaultRotatorService),
             navigator: Box::new(DefaultNavigatorService),
             settings: Box::new(settings_service),
             bot,
             #[cfg(debug_assertions)]
             debug: DebugService::default(),
-        };
-
-        (service, input, capture)
+        }
     }
 
-    #[inline]
     pub fn poll(&mut self, args: PollArgs<'_>) {
         let mut handler = DefaultRequestHandler {
             service: self,
@@ -134,9 +173,20 @@ impl DefaultService {
         handler.poll_request();
         handler.poll_game_events();
         handler.poll_context_event();
+        handler.poll_minimap_auto_select();
         handler.poll_bot();
+        handler.poll_exp();
         handler.broadcast_state();
     }
+
+    /// Invokes `f` with a [`RequestHandler`] as if requests were sent from the UI.
+    pub fn handle(&mut self, args: PollArgs<'_>, f: impl FnOnce(&mut dyn RequestHandler)) {
+        let mut handler = DefaultRequestHandler {
+            service: self,
+            args,
+        };
+        f(&mut handler);
+    }
 }
 
 #[derive(Debug)]
@@ -176,25 +226,16 @@ impl DefaultRequestHandler<'_> {
                     self.on_update_minimap(self.service.minimap.preset(), minimap)
                 }
                 GameEvent::CharacterUpdated(character) => self.on_update_character(character),
-                GameEvent::SettingsUpdated(settings) => {
-                    self.service.settings.update(
-                        &mut self.args.context.operation,
-                        self.args.context.input.as_mut(),
-                        self.service.game.input_receiver_mut(),
-                        self.args.capture,
-                        settings,
-                    );
-                    self.service.bot.update(&self.service.settings.settings());
-                    self.service.rotator.update(
-                        self.args.rotator,
-                        self.service.minimap.minimap(),
-                        self.service.character.character(),
-                        &self.service.settings.settings(),
-                        self.service.game.actions(),
-                        self.service.game.buffs(),
-                    );
-                }
+                GameEvent::SettingsUpdated(settings) => self.on_update_settings(settings),
                 GameEvent::NavigationPathsUpdated => self.args.navigator.mark_dirty(true),
+                GameEvent::ProfileSwitched(settings) => {
+                    // Data from the previous profile no longer exists in the current database
+                    self.update_halting(RotateKind::Halt);
+                    self.on_update_minimap(None, None);
+                    self.on_update_character(None);
+                    self.on_update_settings(settings);
+                    self.args.navigator.mark_dirty(true);
+                }
             }
         }
 
@@ -214,6 +255,14 @@ impl DefaultRequestHandler<'_> {
             self.service.pending_halt = None;
             if !self.args.navigator.was_last_point_available_or_completed() {
                 self.update_halt_or_panic(true, true);
+                let _ = self
+                    .args
+                    .context
+                    .notification
+                    .schedule_map_changed_notification(
+                        self.args.minimap.previous_name(),
+                        self.args.minimap.name(),
+                    );
             }
         }
 
@@ -262,9 +311,45 @@ impl DefaultRequestHandler<'_> {
                     .notification
                     .schedule_notification(NotificationKind::FailOrMapChange);
             }
+            // Nothing can be running before the first connection
+            ContextEvent::InputConnected => (),
+            ContextEvent::InputDisconnected => {
+                if !self.args.context.operation.halting() {
+                    self.update_halt_or_panic(true, false);
+                }
+                let _ = self
+                    .args
+                    .context
+                    .notification
+                    .schedule_notification(NotificationKind::InputDisconnected);
+            }
+            ContextEvent::InputReconnected => {
+                let _ = self
+                    .args
+                    .context
+                    .notification
+                    .schedule_notification(NotificationKind::InputReconnected);
+            }
         }
     }
 
+    fn poll_minimap_auto_select(&mut self) {
+        let policy = self.service.settings.settings().minimap_auto_select;
+        let Some((minimap, preset)) =
+            self.service
+                .minimap
+                .poll_auto_select(self.args.context, self.args.minimap, policy)
+        else {
+            return;
+        };
+
+        // The map change is expected so there is no need to halt
+        if let Some(handle) = self.service.pending_halt.take() {
+            handle.abort();
+        }
+        self.on_update_minimap(preset, Some(minimap));
+    }
+
     fn poll_bot(&mut self) {
         if let Some(command) = self.service.bot.poll() {
             match command.kind {
@@ -380,11 +465,29 @@ impl DefaultRequestHandler<'_> {
         }
     }
 
+    fn poll_exp(&mut self) {
+        self.service.exp.update(
+            self.args.player.exp_read_in(self.args.context),
+            !self.args.context.operation.halting(),
+            self.service
+                .character
+                .character()
+                .and_then(|character| character.id),
+            self.service
+                .minimap
+                .minimap()
+                .and_then(|minimap| minimap.id),
+            self.service.minimap.preset(),
+        );
+    }
+
     fn broadcast_state(&self) {
         self.service.game.broadcast_state(
             self.args.context,
             self.args.player,
             self.service.minimap.minimap(),
+            self.args.minimap.name(),
+            self.service.exp.session(),
         );
     }
 
@@ -462,6 +565,25 @@ impl DefaultRequestHandler<'_> {
                 .inject_action(PlayerAction::Panic(Panic { to: PanicTo::Town }));
         }
     }
+
+    fn on_update_settings(&mut self, settings: Settings) {
+        self.service.settings.update(
+            &mut self.args.context.operation,
+            self.args.context.input.as_mut(),
+            self.service.game.input_receiver_mut(),
+            self.args.capture,
+            settings,
+        );
+        self.service.bot.update(&self.service.settings.settings());
+        self.service.rotator.update(
+            self.args.rotator,
+            self.service.minimap.minimap(),
+            self.service.character.character(),
+            &self.service.settings.settings(),
+            self.service.game.actions(),
+            self.service.game.buffs(),
+        );
+    }
 }
 
 impl RequestHandler for DefaultRequestHandler<'_> {
@@ -474,7 +596,9 @@ impl RequestHandler for DefaultRequestHandler<'_> {
     }
 
     fn on_create_minimap(&self, name: String) -> Option<Minimap> {
-        self.service.minimap.create(self.args.context, name)
+        self.service
+            .minimap
+            .create(self.args.context, self.args.minimap, name)
     }
 
     fn on_update_minimap(&mut self, preset: Option<String>, minimap: Option<Minimap>) {
@@ -530,11 +654,21 @@ impl RequestHandler for DefaultRequestHandler<'_> {
 
         self.service.game.update_actions(minimap, preset, character);
         self.service.game.update_buffs(character);
+        // Custom buffs are recreated because they may have been changed
+        self.args.buffs.truncate(BuffKind::COUNT);
         if let Some(character) = character {
+            self.args.buffs.extend(custom_buff_states(character));
             self.args.buffs.iter_mut().for_each(|state| {
                 state.update_enabled_state(character, &settings);
             });
         }
+        self.args.context.buffs.truncate(BuffKind::COUNT);
+        self.args
+            .context
+            .buffs
+            .resize(self.args.buffs.len().max(BuffKind::COUNT), Buff::No);
+        *self.args.skills = skill_states_from(character);
+        self.args.context.skills = vec![Skill::Detecting; self.args.skills.len()];
         self.service.rotator.update(
             self.args.rotator,
             minimap,
@@ -558,6 +692,10 @@ impl RequestHandler for DefaultRequestHandler<'_> {
         self.service.game.subscribe_key()
     }
 
+    fn on_minimap_auto_selected_receiver(&self) -> Receiver<(Minimap, Option<String>)> {
+        self.service.minimap.subscribe_auto_selected()
+    }
+
     fn on_refresh_capture_handles(&mut self) {
         self.service.settings.update_windows();
         self.on_select_capture_handle(None);
@@ -617,6 +755,11 @@ impl RequestHandler for DefaultRequestHandler<'_> {
     fn on_test_spin_rune(&self) {
         self.service.debug.test_spin_rune();
     }
+
+    #[cfg(debug_assertions)]
+    fn on_compare_input_latency(&mut self, urls: Vec<String>) {
+        self.service.debug.compare_input_latency(urls);
+    }
 }
 
 fn state_and_frame(context: &Context) -> (String, Option<Vec<u8>>) {
@@ -672,7 +815,7 @@ fn frame_from(mat: &impl ToInputArray) -> Option<Vec<u8>> {
 
 #[cfg(test)]
 mod tests {
-    use std::cell::RefCell;
+    use std::{assert_matches::assert_matches, cell::RefCell};
 
     use mockall::Sequence;
     use tokio::sync::broadcast::channel;
@@ -695,7 +838,7 @@ mod tests {
     };
 
     fn mock_poll_args(
-        (context, player, minimap, buffs, rotator, navigator, capture): &mut (
+        (context, player, minimap, buffs, rotator, navigator, capture, skills): &mut (
             Context,
             PlayerState,
             MinimapState,
@@ -703,12 +846,14 @@ mod tests {
             MockRotator,
             MockNavigator,
             MockCapture,
+            Vec<SkillState>,
         ),
     ) -> PollArgs<'_> {
         PollArgs {
             context,
             player,
             minimap,
+            skills,
             buffs,
             rotator,
             navigator,
@@ -724,6 +869,7 @@ mod tests {
         MockRotator,
         MockNavigator,
         MockCapture,
+        Vec<SkillState>,
     ) {
         let context = Context::new(None, None);
         let player = PlayerState::default();
@@ -732,8 +878,11 @@ mod tests {
         let rotator = MockRotator::default();
         let navigator = MockNavigator::default();
         let capture = MockCapture::default();
+        let skills = vec![];
 
-        (context, player, minimap, buffs, rotator, navigator, capture)
+        (
+            context, player, minimap, buffs, rotator, navigator, capture, skills,
+        )
     }
 
     #[test]
@@ -808,6 +957,7 @@ mod tests {
             game: Box::new(game),
             minimap: Box::new(minimap),
             character: Box::new(character),
+            exp: Box::new(DefaultExpService::default()),
             rotator: Box::new(rotator),
             navigator,
             settings: Box::new(settings),
@@ -823,6 +973,37 @@ mod tests {
         handler.on_update_minimap(Some("preset".into()), Some(minimap_data.clone()));
     }
 
+    #[test]
+    fn poll_context_event_input_disconnected_halts() {
+        let mut states = mock_states();
+        states.4.expect_reset_queue().once().return_const(());
+
+        let (tx, rx) = channel(1);
+        let args = mock_poll_args(&mut states);
+        let mut service = DefaultService {
+            event_receiver: rx,
+            pending_halt: None,
+            game: Box::new(MockGameService::default()),
+            minimap: Box::new(MockMinimapService::default()),
+            character: Box::new(MockCharacterService::default()),
+            exp: Box::new(DefaultExpService::default()),
+            rotator: Box::new(MockRotatorService::default()),
+            navigator: Box::new(DefaultNavigatorService),
+            settings: Box::new(MockSettingsService::default()),
+            bot: BotService::default(),
+            #[cfg(debug_assertions)]
+            debug: crate::services::debug::DebugService::default(),
+        };
+        let mut handler = DefaultRequestHandler {
+            service: &mut service,
+            args,
+        };
+
+        tx.send(ContextEvent::InputDisconnected).unwrap();
+        handler.poll_context_event();
+        assert_matches!(handler.args.context.operation, Operation::Halting);
+    }
+
     #[test]
     fn on_update_character_calls_dependencies() {
         let mut states = mock_states();
@@ -910,6 +1091,7 @@ mod tests {
             game: Box::new(game),
             minimap: Box::new(minimap),
             character: Box::new(character),
+            exp: Box::new(DefaultExpService::default()),
             rotator: Box::new(rotator),
             navigator,
             settings: Box::new(settings),
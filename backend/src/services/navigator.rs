@@ -73,7 +73,7 @@ fn convert_color_base64_to_grayscale_base64(base64: String) -> Option<String> {
 }
 
 // TODO: Better way?
-fn extract_minimap_and_name_base64(context: &Context) -> Option<(String, String, Rect)> {
+pub(super) fn extract_minimap_and_name_base64(context: &Context) -> Option<(String, String, Rect)> {
     if let Minimap::Idle(idle) = context.minimap
         && let Some(detector) = context.detector.as_ref()
     {
@@ -52,9 +52,19 @@ impl RotatorService for DefaultRotatorService {
         let elite_boss_behavior_key = character
             .map(|character| character.elite_boss_behavior_key)
             .unwrap_or_default();
+        let skill_ids = character
+            .map(|character| {
+                character
+                    .skills
+                    .iter()
+                    .map(|skill| skill.id)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
         let args = RotatorBuildArgs {
             mode,
             actions,
+            skill_ids: &skill_ids,
             buffs,
             familiar_essence_key,
             familiar_swappable_slots: settings.familiars.swappable_familiars,
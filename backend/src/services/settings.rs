@@ -71,7 +71,7 @@ impl DefaultSettingsService {
         // MapleStoryClass <- GMS
         // MapleStoryClassSG <- MSEA
         // MapleStoryClassTW <- TMS
-        if cfg!(windows) {
+        if cfg!(any(windows, target_os = "linux")) {
             let window = Window::new("MapleStoryClass");
 
             return Self {
@@ -86,9 +86,26 @@ impl DefaultSettingsService {
         panic!("unsupported platform")
     }
 
+    /// Creates a service that always selects `window` without querying other windows.
+    pub fn new_with_window(settings: Rc<RefCell<Settings>>, window: Window) -> Self {
+        Self {
+            settings,
+            capture_default_window: window,
+            capture_name_window_pairs: vec![],
+            capture_selected_window_index: None,
+        }
+    }
+
     fn update_capture(&self, capture: &mut dyn Capture, forced: bool) {
         let settings = self.settings();
         if forced || capture.mode() != settings.capture_mode {
+            match settings.capture_mode {
+                CaptureMode::Playback => capture.set_playback(settings.capture_playback.clone()),
+                CaptureMode::Rpc => {
+                    capture.set_rpc_server_url(settings.capture_rpc_server_url.clone())
+                }
+                _ => (),
+            }
             capture.set_mode(settings.capture_mode);
             capture.set_window(self.selected_window());
         }
@@ -180,8 +197,17 @@ impl SettingsService for DefaultSettingsService {
             new_settings.cycle_run_duration_millis,
             new_settings.cycle_stop_duration_millis,
         );
+        let source_changed = match new_settings.capture_mode {
+            CaptureMode::Playback => {
+                self.settings().capture_playback != new_settings.capture_playback
+            }
+            CaptureMode::Rpc => {
+                self.settings().capture_rpc_server_url != new_settings.capture_rpc_server_url
+            }
+            _ => false,
+        };
         *self.settings.borrow_mut() = new_settings;
-        self.update_capture(capture, false);
+        self.update_capture(capture, source_changed);
         self.update_inputs(input, input_receiver, capture);
     }
 }
@@ -197,7 +223,7 @@ mod tests {
         InputMethod as BridgeInputMethod, MockCapture, MockInput, MockInputReceiver,
     };
     use crate::context::Operation;
-    use crate::{CaptureMode, CycleRunStopMode, InputMethod};
+    use crate::{CaptureMode, CapturePlayback, CycleRunStopMode, InputMethod};
 
     #[test]
     fn settings_service_initialization() {
@@ -322,6 +348,72 @@ mod tests {
         assert_eq!(current.input_method_rpc_server_url, "http://localhost:9000");
     }
 
+    /// Updates from settings with `new_settings.capture_mode` to `new_settings`.
+    ///
+    /// `capture` only needs expectations specific to the changed capture settings.
+    fn update_settings_same_capture_mode(new_settings: Settings, mut capture: MockCapture) {
+        let mode = new_settings.capture_mode;
+        let settings = Rc::new(RefCell::new(Settings {
+            capture_mode: mode,
+            ..Default::default()
+        }));
+        let mut service = DefaultSettingsService::new(settings.clone());
+        let mut mock_keys = MockInput::default();
+        mock_keys.expect_set_method().once();
+        let mut key_receiver = MockInputReceiver::default();
+        key_receiver.expect_set_window_and_input_kind().once();
+        capture
+            .expect_set_mode()
+            .withf(move |new_mode| *new_mode == mode)
+            .once();
+        capture.expect_set_window().once();
+        capture.expect_mode().return_const(mode);
+        let mut op = Operation::Running;
+
+        service.update(
+            &mut op,
+            &mut mock_keys,
+            &mut key_receiver,
+            &mut capture,
+            new_settings,
+        );
+    }
+
+    #[test]
+    fn update_settings_playback_changed_sets_playback() {
+        let new_settings = Settings {
+            capture_mode: CaptureMode::Playback,
+            capture_playback: CapturePlayback {
+                path: "recordings".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut capture = MockCapture::default();
+        capture
+            .expect_set_playback()
+            .withf(|playback| playback.path == "recordings")
+            .once();
+
+        update_settings_same_capture_mode(new_settings, capture);
+    }
+
+    #[test]
+    fn update_settings_rpc_server_url_changed_sets_rpc_server_url() {
+        let new_settings = Settings {
+            capture_mode: CaptureMode::Rpc,
+            capture_rpc_server_url: "http://localhost:9001".to_string(),
+            ..Default::default()
+        };
+        let mut capture = MockCapture::default();
+        capture
+            .expect_set_rpc_server_url()
+            .withf(|url| url == "http://localhost:9001")
+            .once();
+
+        update_settings_same_capture_mode(new_settings, capture);
+    }
+
     #[test]
     fn update_settings_input_receiver_foreground() {
         let settings = Rc::new(RefCell::new(Settings::default()));
@@ -1,14 +1,15 @@
-use std::{
-    mem,
-    ops::{Index, IndexMut},
-};
+use std::sync::Arc;
 
-use anyhow::Result;
-use log::debug;
-use opencv::core::{MatTraitConst, Point, Rect, Vec4b};
-use strum::{Display, EnumIter};
+use anyhow::{Result, anyhow};
+use log::{debug, error};
+use opencv::{
+    core::{Mat, MatTraitConst, Point, Rect, Vec4b, Vector},
+    imgcodecs::{IMREAD_GRAYSCALE, imdecode},
+};
+use strum::Display;
 
 use crate::{
+    Character,
     context::{Context, Contextual, ControlFlow},
     player::Player,
     task::{Task, Update, update_detection_task},
@@ -17,15 +18,56 @@ use crate::{
 #[derive(Debug)]
 pub struct SkillState {
     kind: SkillKind,
+    /// The grayscale icon of [`SkillKind::Custom`].
+    ///
+    /// This is [`None`] if the skill is not custom or its icon cannot be decoded.
+    icon: Option<Arc<Mat>>,
     task: Option<Task<Result<(Point, Vec4b)>>>,
 }
 
 impl SkillState {
     pub fn new(kind: SkillKind) -> Self {
-        Self { kind, task: None }
+        Self {
+            kind,
+            icon: None,
+            task: None,
+        }
+    }
+
+    /// Creates a state for the custom skill at `index` from its PNG-encoded `icon`.
+    pub fn new_custom(index: usize, icon: &[u8]) -> Self {
+        let icon = imdecode(&Vector::<u8>::from_slice(icon), IMREAD_GRAYSCALE)
+            .ok()
+            .filter(|icon| !icon.empty());
+        if icon.is_none() {
+            error!(target: "skill", "failed to decode icon of custom skill {index}");
+        }
+
+        Self {
+            kind: SkillKind::Custom(index),
+            icon: icon.map(Arc::new),
+            task: None,
+        }
     }
 }
 
+/// Creates the states of all skills including the ones registered in `character`.
+///
+/// The states are ordered by [`SkillKind::index`].
+pub fn skill_states_from(character: Option<&Character>) -> Vec<SkillState> {
+    let custom = character
+        .map(|character| character.skills.as_slice())
+        .unwrap_or_default()
+        .iter()
+        .enumerate()
+        .map(|(index, skill)| SkillState::new_custom(index, &skill.icon));
+
+    [SkillState::new(SkillKind::ErdaShower)]
+        .into_iter()
+        .chain(custom)
+        .collect()
+}
+
 #[derive(Clone, Copy, Debug, Display)]
 pub enum Skill {
     Detecting,
@@ -33,27 +75,21 @@ pub enum Skill {
     Cooldown,
 }
 
-#[derive(Clone, Copy, Debug, EnumIter)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum SkillKind {
     ErdaShower,
-    // TODO: Sol Janus?
+    /// A user-registered skill at the index of [`Character::skills`].
+    Custom(usize),
 }
 
 impl SkillKind {
-    pub const COUNT: usize = mem::variant_count::<SkillKind>();
-}
-
-impl Index<SkillKind> for [Skill; SkillKind::COUNT] {
-    type Output = Skill;
-
-    fn index(&self, index: SkillKind) -> &Self::Output {
-        self.get(index as usize).unwrap()
-    }
-}
-
-impl IndexMut<SkillKind> for [Skill; SkillKind::COUNT] {
-    fn index_mut(&mut self, index: SkillKind) -> &mut Self::Output {
-        self.get_mut(index as usize).unwrap()
+    /// The index of this skill in [`Context::skills`].
+    #[inline]
+    pub fn index(self) -> usize {
+        match self {
+            SkillKind::ErdaShower => 0,
+            SkillKind::Custom(index) => index + 1,
+        }
     }
 }
 
@@ -97,21 +133,22 @@ fn update_detection(
     on_next: impl FnOnce(Point, Vec4b) -> Skill,
 ) -> Skill {
     let kind = state.kind;
+    let icon = state.icon.clone();
     let update = update_detection_task(context, 1000, &mut state.task, move |detector| {
         let bbox = match kind {
             SkillKind::ErdaShower => detector.detect_erda_shower()?,
+            SkillKind::Custom(_) => {
+                detector.detect_skill(&icon.ok_or(anyhow!("skill icon is invalid"))?)?
+            }
         };
         Ok(get_anchor(detector.mat(), bbox))
     });
     match update {
         Update::Ok((point, pixel)) => on_next(point, pixel),
-        Update::Err(err) => {
-            if err.downcast::<f64>().unwrap() < 0.52 {
-                Skill::Detecting
-            } else {
-                contextual
-            }
-        }
+        Update::Err(err) => match err.downcast::<f64>() {
+            Ok(score) if score >= 0.52 => contextual,
+            _ => Skill::Detecting,
+        },
         Update::Pending => contextual,
     }
 }
@@ -141,7 +178,10 @@ mod tests {
     use std::{assert_matches::assert_matches, time::Duration};
 
     use anyhow::{Context as AnyhowContext, anyhow};
-    use opencv::core::{CV_8UC4, Mat, MatExprTraitConst, MatTrait};
+    use opencv::{
+        core::{CV_8UC4, MatExprTraitConst, MatTrait},
+        imgcodecs::imencode_def,
+    };
     use tokio::time::advance;
 
     use super::*;
@@ -241,6 +281,37 @@ mod tests {
         }
     }
 
+    #[tokio::test(start_paused = true)]
+    async fn custom_skill_detecting_to_idle() {
+        let (mat, rect) = create_test_mat_bbox(255);
+        let mut detector = MockDetector::new();
+        detector.expect_clone().returning(move || {
+            let mut detector = MockDetector::new();
+            detector
+                .expect_mat()
+                .return_const(create_test_mat_bbox(255).0.into());
+            detector.expect_detect_skill().returning(move |_| Ok(rect));
+            detector
+        });
+        let context = Context::new(None, Some(detector));
+        let mut icon = Vector::<u8>::new();
+        imencode_def(".png", &mat, &mut icon).unwrap();
+        let mut state = SkillState::new_custom(0, icon.as_slice());
+
+        let skill = advance_task(Skill::Detecting, &context, &mut state).await;
+        assert_matches!(skill, Skill::Idle(_, _));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn custom_skill_invalid_icon_detecting() {
+        let (detector, _) = create_mock_detector(255, None);
+        let context = Context::new(None, Some(detector));
+        let mut state = SkillState::new_custom(0, b"not an icon");
+
+        let skill = advance_task(Skill::Detecting, &context, &mut state).await;
+        assert_matches!(skill, Skill::Detecting);
+    }
+
     #[tokio::test(start_paused = true)]
     async fn skill_cooldown_recheck_err() {
         let mut state = SkillState::new(SkillKind::ErdaShower);
@@ -1,13 +1,47 @@
+#[cfg(not(any(windows, target_os = "linux")))]
+use crate::Error;
+#[cfg(target_os = "linux")]
+use crate::linux::{self, X11Capture};
 #[cfg(windows)]
-use crate::windows::{BitBltCapture, WgcCapture, WindowBoxCapture, WindowsCapture};
-use crate::{Error, Result, Window, windows::query_capture_name_handle_pairs};
+use crate::windows::{
+    BitBltCapture, WgcCapture, WindowBoxCapture, WindowsCapture, query_capture_name_handle_pairs,
+};
+use crate::{Result, Window};
+
+/// The pixel layout of [`Frame::data`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PixelFormat {
+    /// 4 bytes per pixel in blue, green, red and alpha order.
+    #[default]
+    Bgra,
+    /// 4 bytes per pixel in red, green, blue and alpha order.
+    Rgba,
+    /// 3 bytes per pixel in red, green and blue order.
+    Rgb,
+    /// A full resolution Y plane followed by an interleaved half resolution UV plane.
+    ///
+    /// Both width and height must be even.
+    Nv12,
+}
+
+impl PixelFormat {
+    /// Gets the expected length in bytes of a frame data with `width` and `height`.
+    pub fn data_len(self, width: i32, height: i32) -> usize {
+        let pixels = width.max(0) as usize * height.max(0) as usize;
+        match self {
+            PixelFormat::Bgra | PixelFormat::Rgba => pixels * 4,
+            PixelFormat::Rgb => pixels * 3,
+            PixelFormat::Nv12 => pixels * 3 / 2,
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Frame {
     pub width: i32,
     pub height: i32,
     pub data: Vec<u8>,
-    // TODO: Color format? Currently always BGRA
+    pub format: PixelFormat,
 }
 
 #[cfg(windows)]
@@ -26,50 +60,66 @@ pub struct Capture {
     windows: WindowsCapture,
     #[cfg(windows)]
     windows_kind: WindowsCaptureKind,
+
+    #[cfg(target_os = "linux")]
+    linux: X11Capture,
 }
 
 impl Capture {
+    #[cfg_attr(not(any(windows, target_os = "linux")), allow(unused_variables))]
     pub fn new(window: Window) -> Result<Self> {
-        if cfg!(windows) {
-            return Ok(Self {
-                window,
-                windows: WindowsCapture::BitBlt(BitBltCapture::new(window.windows, false)),
-                windows_kind: WindowsCaptureKind::BitBlt,
-            });
-        }
-
+        #[cfg(windows)]
+        return Ok(Self {
+            window,
+            windows: WindowsCapture::BitBlt(BitBltCapture::new(window.windows, false)),
+            windows_kind: WindowsCaptureKind::BitBlt,
+        });
+
+        #[cfg(target_os = "linux")]
+        return Ok(Self {
+            window,
+            linux: X11Capture::new(window.linux)?,
+        });
+
+        #[cfg(not(any(windows, target_os = "linux")))]
         Err(Error::PlatformNotSupported)
     }
 
     #[inline]
     pub fn grab(&mut self) -> Result<Frame> {
-        if cfg!(windows) {
-            return self.windows.grab();
-        }
+        #[cfg(windows)]
+        return self.windows.grab();
 
+        #[cfg(target_os = "linux")]
+        return self.linux.grab();
+
+        #[cfg(not(any(windows, target_os = "linux")))]
         Err(Error::PlatformNotSupported)
     }
 
     #[inline]
     pub fn window(&self) -> Result<Window> {
-        if cfg!(windows) {
-            return match &self.windows {
-                WindowsCapture::Wgc(_) | WindowsCapture::BitBlt(_) => Ok(self.window),
-                WindowsCapture::BitBltArea(capture) => Ok(capture.handle().into()),
-            };
-        }
+        #[cfg(windows)]
+        return match &self.windows {
+            WindowsCapture::Wgc(_) | WindowsCapture::BitBlt(_) => Ok(self.window),
+            WindowsCapture::BitBltArea(capture) => Ok(capture.handle().into()),
+        };
 
-        Err(Error::PlatformNotSupported)
+        #[cfg(not(windows))]
+        Ok(self.window)
     }
 
     #[inline]
     pub fn set_window(&mut self, window: Window) -> Result<()> {
         self.window = window;
 
-        if cfg!(windows) {
-            return self.windows_capture_kind(self.windows_kind);
-        }
+        #[cfg(windows)]
+        return self.windows_capture_kind(self.windows_kind);
 
+        #[cfg(target_os = "linux")]
+        return self.linux.set_handle(window.linux);
+
+        #[cfg(not(any(windows, target_os = "linux")))]
         Err(Error::PlatformNotSupported)
     }
 
@@ -93,12 +143,18 @@ impl Capture {
 }
 
 pub fn query_capture_name_window_pairs() -> Result<Vec<(String, Window)>> {
-    if cfg!(windows) {
-        return Ok(query_capture_name_handle_pairs()
-            .into_iter()
-            .map(|(name, handle)| (name, handle.into()))
-            .collect::<Vec<_>>());
-    }
-
+    #[cfg(windows)]
+    return Ok(query_capture_name_handle_pairs()
+        .into_iter()
+        .map(|(name, handle)| (name, handle.into()))
+        .collect::<Vec<_>>());
+
+    #[cfg(target_os = "linux")]
+    return Ok(linux::query_capture_name_handle_pairs()?
+        .into_iter()
+        .map(|(name, handle)| (name, handle.into()))
+        .collect::<Vec<_>>());
+
+    #[cfg(not(any(windows, target_os = "linux")))]
     Err(Error::PlatformNotSupported)
 }
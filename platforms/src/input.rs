@@ -1,3 +1,5 @@
+#[cfg(target_os = "linux")]
+use crate::linux::{LinuxInput, LinuxInputReceiver};
 use crate::{Error, Result, Window};
 #[cfg(windows)]
 use crate::{windows::WindowsInput, windows::WindowsInputReceiver};
@@ -108,61 +110,89 @@ pub enum InputKind {
 pub struct Input {
     #[cfg(windows)]
     windows: WindowsInput,
+    #[cfg(target_os = "linux")]
+    linux: LinuxInput,
 }
 
 impl Input {
+    #[cfg_attr(not(any(windows, target_os = "linux")), allow(unused_variables))]
     pub fn new(window: Window, kind: InputKind) -> Result<Self> {
-        if cfg!(windows) {
-            return Ok(Self {
-                windows: WindowsInput::new(window.windows, kind),
-            });
-        }
+        #[cfg(windows)]
+        return Ok(Self {
+            windows: WindowsInput::new(window.windows, kind),
+        });
 
+        #[cfg(target_os = "linux")]
+        return Ok(Self {
+            linux: LinuxInput::new(window.linux, kind),
+        });
+
+        #[cfg(not(any(windows, target_os = "linux")))]
         Err(Error::PlatformNotSupported)
     }
 
     /// Sends mouse `kind` with coordinates `x`, `y` in relative to the provided [`Window`].
+    #[cfg_attr(not(any(windows, target_os = "linux")), allow(unused_variables))]
     pub fn send_mouse(&self, x: i32, y: i32, kind: MouseKind) -> Result<()> {
-        if cfg!(windows) {
-            return self.windows.send_mouse(x, y, kind);
-        }
+        #[cfg(windows)]
+        return self.windows.send_mouse(x, y, kind);
+
+        #[cfg(target_os = "linux")]
+        return self.linux.send_mouse(x, y, kind);
 
+        #[cfg(not(any(windows, target_os = "linux")))]
         Err(Error::PlatformNotSupported)
     }
 
     /// Retrieves the current state of key `kind`.
+    #[cfg_attr(not(any(windows, target_os = "linux")), allow(unused_variables))]
     pub fn key_state(&self, kind: KeyKind) -> Result<KeyState> {
-        if cfg!(windows) {
-            return self.windows.key_state(kind);
-        }
+        #[cfg(windows)]
+        return self.windows.key_state(kind);
 
+        #[cfg(target_os = "linux")]
+        return self.linux.key_state(kind);
+
+        #[cfg(not(any(windows, target_os = "linux")))]
         Err(Error::PlatformNotSupported)
     }
 
     /// Sends a single key press `kind`.
+    #[cfg_attr(not(any(windows, target_os = "linux")), allow(unused_variables))]
     pub fn send_key(&self, kind: KeyKind) -> Result<()> {
-        if cfg!(windows) {
-            return self.windows.send_key(kind);
-        }
+        #[cfg(windows)]
+        return self.windows.send_key(kind);
+
+        #[cfg(target_os = "linux")]
+        return self.linux.send_key(kind);
 
+        #[cfg(not(any(windows, target_os = "linux")))]
         Err(Error::PlatformNotSupported)
     }
 
     /// Holds down key `kind`.
+    #[cfg_attr(not(any(windows, target_os = "linux")), allow(unused_variables))]
     pub fn send_key_down(&self, kind: KeyKind) -> Result<()> {
-        if cfg!(windows) {
-            return self.windows.send_key_down(kind);
-        }
+        #[cfg(windows)]
+        return self.windows.send_key_down(kind);
 
+        #[cfg(target_os = "linux")]
+        return self.linux.send_key_down(kind);
+
+        #[cfg(not(any(windows, target_os = "linux")))]
         Ok(())
     }
 
     /// Releases key `kind`.
+    #[cfg_attr(not(any(windows, target_os = "linux")), allow(unused_variables))]
     pub fn send_key_up(&self, kind: KeyKind) -> Result<()> {
-        if cfg!(windows) {
-            return self.windows.send_key_up(kind);
-        }
+        #[cfg(windows)]
+        return self.windows.send_key_up(kind);
+
+        #[cfg(target_os = "linux")]
+        return self.linux.send_key_up(kind);
 
+        #[cfg(not(any(windows, target_os = "linux")))]
         Err(Error::PlatformNotSupported)
     }
 }
@@ -171,25 +201,36 @@ impl Input {
 pub struct InputReceiver {
     #[cfg(windows)]
     windows: WindowsInputReceiver,
+    #[cfg(target_os = "linux")]
+    linux: LinuxInputReceiver,
 }
 
 impl InputReceiver {
+    #[cfg_attr(not(any(windows, target_os = "linux")), allow(unused_variables))]
     pub fn new(window: Window, input_kind: InputKind) -> Result<Self> {
-        if cfg!(windows) {
-            return Ok(Self {
-                windows: WindowsInputReceiver::new(window.windows, input_kind),
-            });
-        }
+        #[cfg(windows)]
+        return Ok(Self {
+            windows: WindowsInputReceiver::new(window.windows, input_kind),
+        });
 
+        #[cfg(target_os = "linux")]
+        return Ok(Self {
+            linux: LinuxInputReceiver::new(window.linux, input_kind),
+        });
+
+        #[cfg(not(any(windows, target_os = "linux")))]
         Err(Error::PlatformNotSupported)
     }
 
     /// Attempts to receive a key stroke previously sent from the OS.
     pub fn try_recv(&mut self) -> Result<KeyKind> {
-        if cfg!(windows) {
-            return self.windows.try_recv().ok_or(Error::KeyNotReceived);
-        }
+        #[cfg(windows)]
+        return self.windows.try_recv().ok_or(Error::KeyNotReceived);
+
+        #[cfg(target_os = "linux")]
+        return self.linux.try_recv().ok_or(Error::KeyNotReceived);
 
+        #[cfg(not(any(windows, target_os = "linux")))]
         Err(Error::PlatformNotSupported)
     }
 }
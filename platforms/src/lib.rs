@@ -1,13 +1,17 @@
-#![feature(str_from_raw_parts)]
+#![cfg_attr(windows, feature(str_from_raw_parts))]
 
 use thiserror::Error;
 
+#[cfg(target_os = "linux")]
+use crate::linux::client_to_monitor_or_frame;
 #[cfg(windows)]
 use crate::windows::{Handle, HandleKind, client_to_monitor_or_frame};
 
 pub mod capture;
 pub mod input;
 
+#[cfg(target_os = "linux")]
+mod linux;
 #[cfg(windows)]
 mod windows;
 
@@ -38,6 +42,10 @@ pub enum Error {
     #[cfg(windows)]
     #[error("win32 API error {0}: {1}")]
     Win32(u32, String),
+
+    #[cfg(target_os = "linux")]
+    #[error("X11 error: {0}")]
+    X11(String),
 }
 
 /// Relativeness of a point to be converted to.
@@ -67,6 +75,8 @@ pub struct ConvertedCoordinates {
 pub struct Window {
     #[cfg(windows)]
     windows: Handle,
+    #[cfg(target_os = "linux")]
+    linux: linux::Handle,
 }
 
 impl Window {
@@ -77,22 +87,43 @@ impl Window {
         }
     }
 
+    #[cfg(target_os = "linux")]
+    pub fn new(class: &'static str) -> Self {
+        Self {
+            linux: linux::Handle::new(linux::HandleKind::Dynamic(class)),
+        }
+    }
+
+    #[cfg(not(any(windows, target_os = "linux")))]
+    pub fn new(_class: &'static str) -> Self {
+        Self {}
+    }
+
     #[inline]
+    #[cfg_attr(not(any(windows, target_os = "linux")), allow(unused_variables))]
     pub fn convert_coordinate(
         &self,
         x: i32,
         y: i32,
         relative: CoordinateRelative,
     ) -> Result<ConvertedCoordinates> {
-        if cfg!(windows) {
-            return client_to_monitor_or_frame(
-                self.windows,
-                x,
-                y,
-                matches!(relative, CoordinateRelative::Monitor),
-            );
-        }
-
+        #[cfg(windows)]
+        return client_to_monitor_or_frame(
+            self.windows,
+            x,
+            y,
+            matches!(relative, CoordinateRelative::Monitor),
+        );
+
+        #[cfg(target_os = "linux")]
+        return client_to_monitor_or_frame(
+            self.linux,
+            x,
+            y,
+            matches!(relative, CoordinateRelative::Monitor),
+        );
+
+        #[cfg(not(any(windows, target_os = "linux")))]
         Err(Error::PlatformNotSupported)
     }
 }
@@ -104,8 +135,16 @@ impl From<Handle> for Window {
     }
 }
 
-pub fn init() {
-    if cfg!(windows) {
-        windows::init();
+#[cfg(target_os = "linux")]
+impl From<linux::Handle> for Window {
+    fn from(value: linux::Handle) -> Self {
+        Self { linux: value }
     }
 }
+
+pub fn init() {
+    #[cfg(windows)]
+    windows::init();
+    #[cfg(target_os = "linux")]
+    linux::init();
+}
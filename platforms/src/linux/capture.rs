@@ -0,0 +1,236 @@
+use std::{ptr, slice};
+
+use x11rb::{
+    connection::{Connection as _, RequestConnection},
+    protocol::{
+        shm::{self, ConnectionExt as _},
+        xproto::{self, ConnectionExt as _, ImageFormat},
+    },
+};
+
+use super::{Connection, Handle, connection};
+use crate::{
+    Error, Result,
+    capture::{Frame, PixelFormat},
+};
+
+/// A System V shared memory segment attached to the X server.
+#[derive(Debug)]
+struct ShmSegment {
+    seg: shm::Seg,
+    ptr: *mut u8,
+    size: usize,
+}
+
+// SAFETY: The segment is exclusively owned and only accessed through `&mut X11Capture`
+unsafe impl Send for ShmSegment {}
+
+impl ShmSegment {
+    fn new(conn: &Connection, size: usize) -> Result<Self> {
+        let id = unsafe { libc::shmget(libc::IPC_PRIVATE, size, libc::IPC_CREAT | 0o600) };
+        if id < 0 {
+            return Err(Error::X11(
+                "failed to create shared memory segment".to_string(),
+            ));
+        }
+
+        let ptr = unsafe { libc::shmat(id, ptr::null(), 0) };
+        if ptr as isize == -1 {
+            unsafe { libc::shmctl(id, libc::IPC_RMID, ptr::null_mut()) };
+            return Err(Error::X11(
+                "failed to attach shared memory segment".to_string(),
+            ));
+        }
+
+        let attached = conn
+            .inner
+            .generate_id()
+            .map_err(Error::from)
+            .and_then(|seg| {
+                conn.inner.shm_attach(seg, id as u32, false)?.check()?;
+                Ok(seg)
+            });
+        // Marks for removal right away so the segment is freed once both sides detached even if
+        // the process does not exit gracefully
+        unsafe { libc::shmctl(id, libc::IPC_RMID, ptr::null_mut()) };
+
+        match attached {
+            Ok(seg) => Ok(Self {
+                seg,
+                ptr: ptr.cast(),
+                size,
+            }),
+            Err(err) => {
+                unsafe { libc::shmdt(ptr) };
+                Err(err)
+            }
+        }
+    }
+}
+
+impl Drop for ShmSegment {
+    fn drop(&mut self) {
+        if let Ok(conn) = connection() {
+            let _ = conn.inner.shm_detach(self.seg);
+        }
+        unsafe { libc::shmdt(self.ptr.cast()) };
+    }
+}
+
+/// Captures a X11 window with MIT-SHM extension if available.
+#[derive(Debug)]
+pub struct X11Capture {
+    handle: Handle,
+    shm: Option<ShmSegment>,
+    shm_supported: bool,
+}
+
+impl X11Capture {
+    pub fn new(handle: Handle) -> Result<Self> {
+        let conn = connection()?;
+        let shm_supported = conn
+            .inner
+            .extension_information(shm::X11_EXTENSION_NAME)?
+            .is_some();
+
+        Ok(Self {
+            handle,
+            shm: None,
+            shm_supported,
+        })
+    }
+
+    pub fn set_handle(&mut self, handle: Handle) -> Result<()> {
+        self.handle = handle;
+        Ok(())
+    }
+
+    pub fn grab(&mut self) -> Result<Frame> {
+        let conn = connection()?;
+        let window = self.handle.as_inner().ok_or(Error::WindowNotFound)?;
+        let geometry = conn.inner.get_geometry(window)?.reply()?;
+        if geometry.width == 0 || geometry.height == 0 {
+            return Err(Error::WindowInvalidSize);
+        }
+        // Only 24 and 32 bits depth use 4 bytes per pixel in Z pixmap format
+        if geometry.depth != 24 && geometry.depth != 32 {
+            return Err(Error::WindowFrameNotAvailable);
+        }
+
+        let width = geometry.width;
+        let height = geometry.height;
+        let size = PixelFormat::Bgra.data_len(width as i32, height as i32);
+        let mut data = if self.shm_supported {
+            self.grab_shm(conn, window, width, height, size)?
+        } else {
+            conn.inner
+                .get_image(ImageFormat::Z_PIXMAP, window, 0, 0, width, height, !0)?
+                .reply()?
+                .data
+        };
+        if data.len() != size {
+            return Err(Error::WindowFrameNotAvailable);
+        }
+        // The padding byte of 24 bits depth is undefined
+        for pixel in data.as_chunks_mut::<4>().0 {
+            pixel[3] = 255;
+        }
+
+        Ok(Frame {
+            width: width as i32,
+            height: height as i32,
+            data,
+            format: PixelFormat::Bgra,
+        })
+    }
+
+    #[inline]
+    fn grab_shm(
+        &mut self,
+        conn: &Connection,
+        window: xproto::Window,
+        width: u16,
+        height: u16,
+        size: usize,
+    ) -> Result<Vec<u8>> {
+        if self.shm.as_ref().is_none_or(|shm| shm.size < size) {
+            self.shm = None;
+            self.shm = Some(ShmSegment::new(conn, size)?);
+        }
+        let shm = self.shm.as_ref().unwrap();
+        let reply = conn
+            .inner
+            .shm_get_image(
+                window,
+                0,
+                0,
+                width,
+                height,
+                !0,
+                ImageFormat::Z_PIXMAP.into(),
+                shm.seg,
+                0,
+            )?
+            .reply()?;
+        let len = (reply.size as usize).min(shm.size);
+
+        Ok(unsafe { slice::from_raw_parts(shm.ptr, len) }.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use x11rb::protocol::xproto::{CreateWindowAux, MapState, WindowClass};
+
+    use super::*;
+    use crate::linux::HandleKind;
+
+    #[test]
+    #[ignore = "requires a X server on DISPLAY"]
+    fn grab_window_frame() {
+        let conn = connection().unwrap();
+        let screen = &conn.inner.setup().roots[conn.screen];
+        let window = conn.inner.generate_id().unwrap();
+        conn.inner
+            .create_window(
+                x11rb::COPY_DEPTH_FROM_PARENT,
+                window,
+                screen.root,
+                0,
+                0,
+                64,
+                48,
+                0,
+                WindowClass::INPUT_OUTPUT,
+                x11rb::COPY_FROM_PARENT,
+                &CreateWindowAux::new().background_pixel(screen.black_pixel),
+            )
+            .unwrap()
+            .check()
+            .unwrap();
+        conn.inner.map_window(window).unwrap().check().unwrap();
+        for _ in 0..50 {
+            let attributes = conn
+                .inner
+                .get_window_attributes(window)
+                .unwrap()
+                .reply()
+                .unwrap();
+            if attributes.map_state == MapState::VIEWABLE {
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        let mut capture = X11Capture::new(Handle::new(HandleKind::Fixed(window))).unwrap();
+        let frame = capture.grab();
+        conn.inner.destroy_window(window).unwrap().check().unwrap();
+
+        let frame = frame.unwrap();
+        assert_eq!((frame.width, frame.height), (64, 48));
+        assert_eq!(frame.format, PixelFormat::Bgra);
+        assert_eq!(frame.data.len(), PixelFormat::Bgra.data_len(64, 48));
+    }
+}
@@ -0,0 +1,319 @@
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+};
+
+use x11rb::{
+    connection::Connection as _,
+    protocol::xproto::{self, AtomEnum, ConnectionExt, MapState},
+};
+
+use super::{Connection, connection};
+use crate::{ConvertedCoordinates, Error, Result};
+
+#[derive(Clone, Debug)]
+pub struct HandleCell {
+    inner: Handle,
+    inner_cell: Cell<Option<xproto::Window>>,
+}
+
+impl HandleCell {
+    pub fn new(handle: Handle) -> Self {
+        Self {
+            inner: handle,
+            inner_cell: Cell::new(None),
+        }
+    }
+
+    #[inline]
+    pub fn as_inner(&self) -> Option<xproto::Window> {
+        match self.inner.kind {
+            HandleKind::Fixed(window) => Some(window),
+            HandleKind::Dynamic(class) => {
+                let conn = connection().ok()?;
+                if self.inner_cell.get().is_none() {
+                    self.inner_cell.set(query_handle(conn, class));
+                }
+
+                let window = self.inner_cell.get()?;
+                if is_class_matched(conn, window, class) {
+                    Some(window)
+                } else {
+                    self.inner_cell.set(None);
+                    None
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandleKind {
+    Fixed(xproto::Window),
+    Dynamic(&'static str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Handle {
+    kind: HandleKind,
+}
+
+impl Handle {
+    pub fn new(kind: HandleKind) -> Self {
+        Self { kind }
+    }
+
+    pub fn as_inner(&self) -> Option<xproto::Window> {
+        match self.kind {
+            HandleKind::Fixed(window) => Some(window),
+            HandleKind::Dynamic(class) => query_handle_cached(connection().ok()?, class),
+        }
+    }
+}
+
+pub fn query_capture_name_handle_pairs() -> Result<Vec<(String, Handle)>> {
+    let conn = connection()?;
+    let mut vec = Vec::new();
+
+    for window in query_client_windows(conn)? {
+        let viewable = conn
+            .inner
+            .get_window_attributes(window)?
+            .reply()
+            .is_ok_and(|attributes| attributes.map_state == MapState::VIEWABLE);
+        if !viewable {
+            continue;
+        }
+
+        if let Some(name) = query_window_name(conn, window).filter(|name| !name.is_empty()) {
+            vec.push((name, Handle::new(HandleKind::Fixed(window))));
+        }
+    }
+
+    Ok(vec)
+}
+
+pub fn client_to_monitor_or_frame(
+    handle: Handle,
+    x: i32,
+    y: i32,
+    monitor_coordinate: bool,
+) -> Result<ConvertedCoordinates> {
+    let conn = connection()?;
+    let window = handle.as_inner().ok_or(Error::WindowNotFound)?;
+    let root = conn.inner.setup().roots[conn.screen].root;
+    let point = conn
+        .inner
+        .translate_coordinates(window, root, x as i16, y as i16)?
+        .reply()?;
+
+    if !monitor_coordinate {
+        // The top-level window is the frame decorated by the window manager if any
+        let frame = query_top_level_window(conn, window, root)?;
+        let geometry = conn.inner.get_geometry(frame)?.reply()?;
+        let frame_point = conn
+            .inner
+            .translate_coordinates(frame, root, 0, 0)?
+            .reply()?;
+
+        return Ok(ConvertedCoordinates {
+            width: geometry.width as i32,
+            height: geometry.height as i32,
+            x: (point.dst_x - frame_point.dst_x) as i32,
+            y: (point.dst_y - frame_point.dst_y) as i32,
+        });
+    }
+
+    // TODO: Use RandR to support multiple monitors
+    let screen = &conn.inner.setup().roots[conn.screen];
+    Ok(ConvertedCoordinates {
+        width: screen.width_in_pixels as i32,
+        height: screen.height_in_pixels as i32,
+        x: point.dst_x as i32,
+        y: point.dst_y as i32,
+    })
+}
+
+/// Queries the client windows managed by the window manager.
+///
+/// Falls back to the root window children when there is no EWMH-compliant window manager (e.g.
+/// a bare Xvfb).
+fn query_client_windows(conn: &Connection) -> Result<Vec<xproto::Window>> {
+    let root = conn.inner.setup().roots[conn.screen].root;
+    let clients = conn
+        .inner
+        .get_property(
+            false,
+            root,
+            conn.atoms._NET_CLIENT_LIST,
+            AtomEnum::WINDOW,
+            0,
+            u32::MAX,
+        )?
+        .reply()?;
+    let clients = clients
+        .value32()
+        .map(|clients| clients.collect::<Vec<_>>())
+        .unwrap_or_default();
+    if !clients.is_empty() {
+        return Ok(clients);
+    }
+
+    Ok(conn.inner.query_tree(root)?.reply()?.children)
+}
+
+#[inline]
+fn query_window_name(conn: &Connection, window: xproto::Window) -> Option<String> {
+    let name = conn
+        .inner
+        .get_property(
+            false,
+            window,
+            conn.atoms._NET_WM_NAME,
+            conn.atoms.UTF8_STRING,
+            0,
+            u32::MAX,
+        )
+        .ok()?
+        .reply()
+        .ok()?;
+    if !name.value.is_empty() {
+        return String::from_utf8(name.value).ok();
+    }
+
+    let name = conn
+        .inner
+        .get_property(
+            false,
+            window,
+            AtomEnum::WM_NAME,
+            AtomEnum::STRING,
+            0,
+            u32::MAX,
+        )
+        .ok()?
+        .reply()
+        .ok()?;
+    Some(String::from_utf8_lossy(&name.value).into_owned())
+}
+
+#[inline]
+pub(super) fn query_top_level_window(
+    conn: &Connection,
+    mut window: xproto::Window,
+    root: xproto::Window,
+) -> Result<xproto::Window> {
+    loop {
+        let parent = conn.inner.query_tree(window)?.reply()?.parent;
+        if parent == root || parent == x11rb::NONE {
+            return Ok(window);
+        }
+        window = parent;
+    }
+}
+
+/// Same as [`query_handle`] but reuses the last found window for `class` while its class still
+/// matches.
+///
+/// Unlike [`HandleCell`], [`Handle`] is [`Copy`] so the found windows are cached per class
+/// for the whole process instead.
+fn query_handle_cached(conn: &Connection, class: &'static str) -> Option<xproto::Window> {
+    static WINDOWS: LazyLock<Mutex<HashMap<&'static str, xproto::Window>>> =
+        LazyLock::new(Mutex::default);
+
+    let mut windows = WINDOWS.lock().unwrap();
+    if let Some(window) = windows.get(class).copied()
+        && is_class_matched(conn, window, class)
+    {
+        return Some(window);
+    }
+
+    let window = query_handle(conn, class);
+    match window {
+        Some(window) => windows.insert(class, window),
+        None => windows.remove(class),
+    };
+    window
+}
+
+#[inline]
+fn query_handle(conn: &Connection, class: &'static str) -> Option<xproto::Window> {
+    query_client_windows(conn)
+        .ok()?
+        .into_iter()
+        .find(|window| is_class_matched(conn, *window, class))
+}
+
+#[inline]
+fn is_class_matched(conn: &Connection, window: xproto::Window, class: &'static str) -> bool {
+    conn.inner
+        .get_property(
+            false,
+            window,
+            AtomEnum::WM_CLASS,
+            AtomEnum::STRING,
+            0,
+            u32::MAX,
+        )
+        .ok()
+        .and_then(|cookie| cookie.reply().ok())
+        .is_some_and(|reply| is_wm_class_matched(&reply.value, class))
+}
+
+/// Checks whether the `WM_CLASS` property `value` matches Windows class name `class`.
+///
+/// `WM_CLASS` consists of null-terminated instance and class names, either of which needs to be
+/// `class` optionally followed by a region suffix (e.g. `MapleStoryClassSG`). Under Wine, these
+/// are the executable name instead, which must be `class` without the `Class` suffix (e.g.
+/// `maplestory.exe`). Names are compared case-insensitively.
+fn is_wm_class_matched(value: &[u8], class: &str) -> bool {
+    let class = class.to_ascii_lowercase();
+    let exe_stem = class.strip_suffix("class").unwrap_or(&class);
+
+    value
+        .split(|byte| *byte == 0)
+        .filter_map(|name| str::from_utf8(name).ok())
+        .map(|name| name.to_ascii_lowercase())
+        .filter(|name| !name.is_empty())
+        .any(|name| match name.strip_suffix(".exe") {
+            Some(stem) => stem == exe_stem,
+            None => name.starts_with(&class),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wm_class_matched_native_and_wine() {
+        assert!(is_wm_class_matched(
+            b"MapleStoryClass\0MapleStoryClass\0",
+            "MapleStoryClass"
+        ));
+        assert!(is_wm_class_matched(
+            b"MapleStoryClassSG\0Wine\0",
+            "MapleStoryClass"
+        ));
+        assert!(is_wm_class_matched(
+            b"maplestory.exe\0Wine\0",
+            "MapleStoryClass"
+        ));
+        assert!(!is_wm_class_matched(
+            b"firefox\0Firefox\0",
+            "MapleStoryClass"
+        ));
+        assert!(!is_wm_class_matched(b"\0\0", "MapleStoryClass"));
+        assert!(!is_wm_class_matched(b"maple\0Maple\0", "MapleStoryClass"));
+        assert!(!is_wm_class_matched(b"map\0Map\0", "MapleStoryClass"));
+        assert!(!is_wm_class_matched(
+            b"maplestorylauncher.exe\0Wine\0",
+            "MapleStoryClass"
+        ));
+        assert!(!is_wm_class_matched(
+            b"maple.exe\0Wine\0",
+            "MapleStoryClass"
+        ));
+    }
+}
@@ -0,0 +1,640 @@
+use std::{
+    cell::RefCell,
+    process,
+    sync::{LazyLock, RwLock},
+    thread,
+    time::Duration,
+};
+
+use bit_vec::BitVec;
+use log::error;
+use tokio::sync::broadcast::{self, Receiver, Sender};
+use x11rb::{
+    CURRENT_TIME, NONE,
+    connection::Connection as _,
+    protocol::{
+        Event,
+        xinput::{ConnectionExt as _, DeviceId, EventMask, XIEventMask},
+        xproto::{
+            self, AtomEnum, BUTTON_PRESS_EVENT, BUTTON_RELEASE_EVENT, ConnectionExt as _,
+            KEY_PRESS_EVENT, KEY_RELEASE_EVENT, MOTION_NOTIFY_EVENT, Mapping,
+        },
+        xtest::ConnectionExt as _,
+    },
+};
+use xkeysym::Keysym;
+
+use super::{Connection, Handle, HandleCell, connection, handle::query_top_level_window};
+use crate::{
+    Error, Result,
+    input::{InputKind, KeyKind, KeyState, MouseKind},
+};
+
+/// XInput2 device id for all master devices.
+const XI_ALL_MASTER_DEVICES: DeviceId = 1;
+
+/// XInput2 device id for all devices.
+const XI_ALL_DEVICES: DeviceId = 0;
+
+/// Name of the slave keyboard device that XTest fake inputs come from.
+const XTEST_KEYBOARD_NAME: &str = "XTEST keyboard";
+
+const BUTTON_LEFT: u8 = 1;
+
+const BUTTON_SCROLL_DOWN: u8 = 5;
+
+static KEY_CHANNEL: LazyLock<Sender<KeyKind>> = LazyLock::new(|| broadcast::channel(1).0);
+/// The current keyboard mapping, re-queried by [`listen_keys`] when the mapping changes.
+static KEYMAP: LazyLock<RwLock<Result<Keymap>>> =
+    LazyLock::new(|| RwLock::new(connection().and_then(Keymap::query)));
+
+pub fn init() {
+    let Ok(conn) = connection() else {
+        return;
+    };
+
+    thread::spawn(move || {
+        if let Err(err) = listen_keys(conn) {
+            error!(target: "input", "stopped listening for keys {err:?}");
+        }
+    });
+}
+
+/// Listens for key releases from physical keyboards and broadcasts them to [`KEY_CHANNEL`].
+///
+/// Raw events are used so that keys are received regardless of the focused window. Keys sent
+/// through XTest are ignored, similar to ignoring injected keys on Windows.
+fn listen_keys(conn: &Connection) -> Result<()> {
+    conn.inner.xinput_xi_query_version(2, 2)?.reply()?;
+    let xtest_keyboard = conn
+        .inner
+        .xinput_xi_query_device(XI_ALL_DEVICES)?
+        .reply()?
+        .infos
+        .into_iter()
+        .find(|info| String::from_utf8_lossy(&info.name).contains(XTEST_KEYBOARD_NAME))
+        .map(|info| info.deviceid);
+    let mask = EventMask {
+        deviceid: XI_ALL_MASTER_DEVICES,
+        mask: vec![XIEventMask::RAW_KEY_RELEASE],
+    };
+    conn.inner
+        .xinput_xi_select_events(root(conn), &[mask])?
+        .check()?;
+
+    loop {
+        match conn.inner.wait_for_event()? {
+            Event::XinputRawKeyRelease(event) if Some(event.sourceid) != xtest_keyboard => {
+                let key = KEYMAP
+                    .read()
+                    .unwrap()
+                    .as_ref()
+                    .ok()
+                    .and_then(|keymap| keymap.keysym(event.detail as u8))
+                    .and_then(|keysym| KeyKind::try_from(keysym).ok());
+                if let Some(key) = key {
+                    let _ = KEY_CHANNEL.send(key);
+                }
+            }
+            // Sent to every client without selecting when the keyboard layout changes
+            Event::MappingNotify(event) if event.request == Mapping::KEYBOARD => {
+                *KEYMAP.write().unwrap() = Keymap::query(conn);
+            }
+            _ => (),
+        }
+    }
+}
+
+/// The keyboard mapping from key codes to key symbols.
+#[derive(Debug)]
+struct Keymap {
+    min_keycode: u8,
+    keysyms_per_keycode: u8,
+    keysyms: Vec<u32>,
+}
+
+impl Keymap {
+    fn query(conn: &Connection) -> Result<Self> {
+        let setup = conn.inner.setup();
+        let count = setup.max_keycode - setup.min_keycode + 1;
+        let reply = conn
+            .inner
+            .get_keyboard_mapping(setup.min_keycode, count)?
+            .reply()?;
+
+        Ok(Self {
+            min_keycode: setup.min_keycode,
+            keysyms_per_keycode: reply.keysyms_per_keycode,
+            keysyms: reply.keysyms,
+        })
+    }
+
+    /// Gets the first key code that produces `keysym` in any column.
+    fn keycode(&self, keysym: Keysym) -> Option<u8> {
+        self.keysyms
+            .chunks(self.keysyms_per_keycode.max(1) as usize)
+            .position(|keysyms| keysyms.contains(&keysym.raw()))
+            .map(|index| self.min_keycode + index as u8)
+    }
+
+    /// Gets the unshifted key symbol of `keycode`.
+    fn keysym(&self, keycode: u8) -> Option<Keysym> {
+        let index = keycode.checked_sub(self.min_keycode)? as usize;
+        self.keysyms
+            .get(index * self.keysyms_per_keycode as usize)
+            .copied()
+            .filter(|keysym| *keysym != NONE)
+            .map(Keysym::from)
+    }
+}
+
+#[derive(Debug)]
+pub struct LinuxInputReceiver {
+    handle: HandleCell,
+    input_kind: InputKind,
+    rx: Receiver<KeyKind>,
+}
+
+impl LinuxInputReceiver {
+    pub fn new(handle: Handle, input_kind: InputKind) -> Self {
+        Self {
+            handle: HandleCell::new(handle),
+            input_kind,
+            rx: KEY_CHANNEL.subscribe(),
+        }
+    }
+
+    pub fn try_recv(&mut self) -> Option<KeyKind> {
+        self.rx
+            .try_recv()
+            .ok()
+            .and_then(|key| self.can_process_key().then_some(key))
+    }
+
+    fn can_process_key(&self) -> bool {
+        let Ok(conn) = connection() else {
+            return false;
+        };
+        let Some(active) = query_active_window(conn) else {
+            return false;
+        };
+        if query_window_pid(conn, active) == Some(process::id()) {
+            return true;
+        }
+
+        self.handle
+            .as_inner()
+            .map(|window| is_foreground(conn, window, self.input_kind))
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug)]
+pub struct LinuxInput {
+    handle: HandleCell,
+    input_kind: InputKind,
+    key_down: RefCell<BitVec>,
+}
+
+impl LinuxInput {
+    pub fn new(handle: Handle, kind: InputKind) -> Self {
+        Self {
+            handle: HandleCell::new(handle),
+            input_kind: kind,
+            key_down: RefCell::new(BitVec::from_elem(256, false)),
+        }
+    }
+
+    pub fn send_mouse(&self, x: i32, y: i32, kind: MouseKind) -> Result<()> {
+        let conn = connection()?;
+        let mut window = self.get_window()?;
+        if !is_foreground(conn, window, self.input_kind) {
+            return Err(Error::WindowNotFound);
+        }
+        if matches!(self.input_kind, InputKind::Foreground) {
+            window = query_active_window(conn).ok_or(Error::WindowNotFound)?;
+        }
+
+        let point = conn
+            .inner
+            .translate_coordinates(window, root(conn), x as i16, y as i16)?
+            .reply()?;
+        fake_input(conn, MOTION_NOTIFY_EVENT, 0, point.dst_x, point.dst_y)?;
+
+        match kind {
+            MouseKind::Move => (),
+            MouseKind::Click => {
+                fake_input(conn, BUTTON_PRESS_EVENT, BUTTON_LEFT, 0, 0)?;
+                conn.inner.flush()?;
+                // Same as Windows or double-click won't work...
+                thread::sleep(Duration::from_millis(80));
+                fake_input(conn, BUTTON_RELEASE_EVENT, BUTTON_LEFT, 0, 0)?;
+            }
+            MouseKind::Scroll => {
+                fake_input(conn, BUTTON_PRESS_EVENT, BUTTON_SCROLL_DOWN, 0, 0)?;
+                fake_input(conn, BUTTON_RELEASE_EVENT, BUTTON_SCROLL_DOWN, 0, 0)?;
+            }
+        }
+        conn.inner.flush()?;
+
+        Ok(())
+    }
+
+    pub fn key_state(&self, kind: KeyKind) -> Result<KeyState> {
+        let conn = connection()?;
+        let keycode = keycode_from(kind)?;
+        let keys = conn.inner.query_keymap()?.reply()?.keys;
+        let is_down = keys[keycode as usize / 8] & (1 << (keycode % 8)) != 0;
+        let state = if is_down {
+            KeyState::Pressed
+        } else {
+            KeyState::Released
+        };
+
+        Ok(state)
+    }
+
+    pub fn send_key(&self, kind: KeyKind) -> Result<()> {
+        self.send_key_down(kind)?;
+        self.send_key_up(kind)?;
+        Ok(())
+    }
+
+    pub fn send_key_up(&self, kind: KeyKind) -> Result<()> {
+        self.send_input(kind, false)
+    }
+
+    pub fn send_key_down(&self, kind: KeyKind) -> Result<()> {
+        self.send_input(kind, true)
+    }
+
+    #[inline]
+    fn send_input(&self, kind: KeyKind, is_down: bool) -> Result<()> {
+        let conn = connection()?;
+        let window = self.get_window()?;
+        if is_down && !is_foreground(conn, window, self.input_kind) {
+            return Err(Error::KeyNotSent);
+        }
+        let keycode = keycode_from(kind)?;
+        let mut key_down = self.key_down.borrow_mut();
+        // SAFETY: Key code is u8 and BitVec was initialized with 256 elements
+        let was_key_down = unsafe { key_down.get_unchecked(keycode as usize) };
+        match (is_down, was_key_down) {
+            (true, true) | (false, false) => return Err(Error::KeyNotSent),
+            _ => {
+                key_down.set(keycode as usize, is_down);
+            }
+        }
+
+        let event = if is_down {
+            KEY_PRESS_EVENT
+        } else {
+            KEY_RELEASE_EVENT
+        };
+        fake_input(conn, event, keycode, 0, 0)?;
+        conn.inner.flush()?;
+
+        Ok(())
+    }
+
+    #[inline]
+    fn get_window(&self) -> Result<xproto::Window> {
+        self.handle.as_inner().ok_or(Error::WindowNotFound)
+    }
+}
+
+#[inline]
+fn root(conn: &Connection) -> xproto::Window {
+    conn.inner.setup().roots[conn.screen].root
+}
+
+#[inline]
+fn keycode_from(kind: KeyKind) -> Result<u8> {
+    KEYMAP
+        .read()
+        .unwrap()
+        .as_ref()
+        .map_err(Clone::clone)?
+        .keycode(kind.into())
+        .ok_or(Error::KeyNotFound)
+}
+
+#[inline]
+fn fake_input(conn: &Connection, event: u8, detail: u8, x: i16, y: i16) -> Result<()> {
+    conn.inner
+        .xtest_fake_input(event, detail, CURRENT_TIME, root(conn), x, y, NONE as u8)?;
+    Ok(())
+}
+
+/// Queries the focused window.
+///
+/// Falls back to the input focus when there is no EWMH-compliant window manager.
+#[inline]
+fn query_active_window(conn: &Connection) -> Option<xproto::Window> {
+    let active = conn
+        .inner
+        .get_property(
+            false,
+            root(conn),
+            conn.atoms._NET_ACTIVE_WINDOW,
+            AtomEnum::WINDOW,
+            0,
+            1,
+        )
+        .ok()?
+        .reply()
+        .ok()?
+        .value32()
+        .and_then(|mut windows| windows.next())
+        .filter(|window| *window != NONE);
+    if active.is_some() {
+        return active;
+    }
+
+    let focus = conn.inner.get_input_focus().ok()?.reply().ok()?.focus;
+    // 0 is None and 1 is PointerRoot
+    (focus > 1).then_some(focus)
+}
+
+#[inline]
+fn query_window_pid(conn: &Connection, window: xproto::Window) -> Option<u32> {
+    conn.inner
+        .get_property(
+            false,
+            window,
+            conn.atoms._NET_WM_PID,
+            AtomEnum::CARDINAL,
+            0,
+            1,
+        )
+        .ok()?
+        .reply()
+        .ok()?
+        .value32()?
+        .next()
+}
+
+#[inline]
+fn query_window_rect(conn: &Connection, window: xproto::Window) -> Option<(i32, i32, i32, i32)> {
+    let geometry = conn.inner.get_geometry(window).ok()?.reply().ok()?;
+    let point = conn
+        .inner
+        .translate_coordinates(window, root(conn), 0, 0)
+        .ok()?
+        .reply()
+        .ok()?;
+    let left = point.dst_x as i32;
+    let top = point.dst_y as i32;
+
+    Some((
+        left,
+        top,
+        left + geometry.width as i32,
+        top + geometry.height as i32,
+    ))
+}
+
+#[inline]
+fn is_same_window(conn: &Connection, a: xproto::Window, b: xproto::Window) -> bool {
+    if a == b {
+        return true;
+    }
+
+    let root = root(conn);
+    let a = query_top_level_window(conn, a, root);
+    let b = query_top_level_window(conn, b, root);
+    matches!((a, b), (Ok(a), Ok(b)) if a == b)
+}
+
+#[inline]
+fn is_foreground(conn: &Connection, window: xproto::Window, kind: InputKind) -> bool {
+    let Some(active) = query_active_window(conn) else {
+        return false;
+    };
+    let is_window = is_same_window(conn, active, window);
+
+    match kind {
+        InputKind::Focused => is_window,
+        InputKind::Foreground => {
+            if is_window {
+                return false;
+            }
+            let Some((left, top, right, bottom)) = query_window_rect(conn, window) else {
+                return false;
+            };
+            let Some((fg_left, fg_top, fg_right, fg_bottom)) = query_window_rect(conn, active)
+            else {
+                return false;
+            };
+
+            left < fg_right && fg_left < right && top < fg_bottom && fg_top < bottom
+        }
+    }
+}
+
+impl TryFrom<Keysym> for KeyKind {
+    type Error = Error;
+
+    fn try_from(value: Keysym) -> Result<Self> {
+        Ok(match value {
+            Keysym::a => KeyKind::A,
+            Keysym::b => KeyKind::B,
+            Keysym::c => KeyKind::C,
+            Keysym::d => KeyKind::D,
+            Keysym::e => KeyKind::E,
+            Keysym::f => KeyKind::F,
+            Keysym::g => KeyKind::G,
+            Keysym::h => KeyKind::H,
+            Keysym::i => KeyKind::I,
+            Keysym::j => KeyKind::J,
+            Keysym::k => KeyKind::K,
+            Keysym::l => KeyKind::L,
+            Keysym::m => KeyKind::M,
+            Keysym::n => KeyKind::N,
+            Keysym::o => KeyKind::O,
+            Keysym::p => KeyKind::P,
+            Keysym::q => KeyKind::Q,
+            Keysym::r => KeyKind::R,
+            Keysym::s => KeyKind::S,
+            Keysym::t => KeyKind::T,
+            Keysym::u => KeyKind::U,
+            Keysym::v => KeyKind::V,
+            Keysym::w => KeyKind::W,
+            Keysym::x => KeyKind::X,
+            Keysym::y => KeyKind::Y,
+            Keysym::z => KeyKind::Z,
+            Keysym::_0 => KeyKind::Zero,
+            Keysym::_1 => KeyKind::One,
+            Keysym::_2 => KeyKind::Two,
+            Keysym::_3 => KeyKind::Three,
+            Keysym::_4 => KeyKind::Four,
+            Keysym::_5 => KeyKind::Five,
+            Keysym::_6 => KeyKind::Six,
+            Keysym::_7 => KeyKind::Seven,
+            Keysym::_8 => KeyKind::Eight,
+            Keysym::_9 => KeyKind::Nine,
+            Keysym::F1 => KeyKind::F1,
+            Keysym::F2 => KeyKind::F2,
+            Keysym::F3 => KeyKind::F3,
+            Keysym::F4 => KeyKind::F4,
+            Keysym::F5 => KeyKind::F5,
+            Keysym::F6 => KeyKind::F6,
+            Keysym::F7 => KeyKind::F7,
+            Keysym::F8 => KeyKind::F8,
+            Keysym::F9 => KeyKind::F9,
+            Keysym::F10 => KeyKind::F10,
+            Keysym::F11 => KeyKind::F11,
+            Keysym::F12 => KeyKind::F12,
+            Keysym::Up => KeyKind::Up,
+            Keysym::Down => KeyKind::Down,
+            Keysym::Left => KeyKind::Left,
+            Keysym::Right => KeyKind::Right,
+            Keysym::Home => KeyKind::Home,
+            Keysym::End => KeyKind::End,
+            Keysym::Prior => KeyKind::PageUp,
+            Keysym::Next => KeyKind::PageDown,
+            Keysym::Insert => KeyKind::Insert,
+            Keysym::Delete => KeyKind::Delete,
+            Keysym::Control_L | Keysym::Control_R => KeyKind::Ctrl,
+            Keysym::Return => KeyKind::Enter,
+            Keysym::space => KeyKind::Space,
+            Keysym::grave => KeyKind::Tilde,
+            Keysym::apostrophe => KeyKind::Quote,
+            Keysym::semicolon => KeyKind::Semicolon,
+            Keysym::comma => KeyKind::Comma,
+            Keysym::period => KeyKind::Period,
+            Keysym::slash => KeyKind::Slash,
+            Keysym::Escape => KeyKind::Esc,
+            Keysym::Shift_L | Keysym::Shift_R => KeyKind::Shift,
+            Keysym::Alt_L | Keysym::Alt_R => KeyKind::Alt,
+            _ => return Err(Error::KeyNotFound),
+        })
+    }
+}
+
+impl From<KeyKind> for Keysym {
+    fn from(value: KeyKind) -> Self {
+        match value {
+            KeyKind::A => Keysym::a,
+            KeyKind::B => Keysym::b,
+            KeyKind::C => Keysym::c,
+            KeyKind::D => Keysym::d,
+            KeyKind::E => Keysym::e,
+            KeyKind::F => Keysym::f,
+            KeyKind::G => Keysym::g,
+            KeyKind::H => Keysym::h,
+            KeyKind::I => Keysym::i,
+            KeyKind::J => Keysym::j,
+            KeyKind::K => Keysym::k,
+            KeyKind::L => Keysym::l,
+            KeyKind::M => Keysym::m,
+            KeyKind::N => Keysym::n,
+            KeyKind::O => Keysym::o,
+            KeyKind::P => Keysym::p,
+            KeyKind::Q => Keysym::q,
+            KeyKind::R => Keysym::r,
+            KeyKind::S => Keysym::s,
+            KeyKind::T => Keysym::t,
+            KeyKind::U => Keysym::u,
+            KeyKind::V => Keysym::v,
+            KeyKind::W => Keysym::w,
+            KeyKind::X => Keysym::x,
+            KeyKind::Y => Keysym::y,
+            KeyKind::Z => Keysym::z,
+            KeyKind::Zero => Keysym::_0,
+            KeyKind::One => Keysym::_1,
+            KeyKind::Two => Keysym::_2,
+            KeyKind::Three => Keysym::_3,
+            KeyKind::Four => Keysym::_4,
+            KeyKind::Five => Keysym::_5,
+            KeyKind::Six => Keysym::_6,
+            KeyKind::Seven => Keysym::_7,
+            KeyKind::Eight => Keysym::_8,
+            KeyKind::Nine => Keysym::_9,
+            KeyKind::F1 => Keysym::F1,
+            KeyKind::F2 => Keysym::F2,
+            KeyKind::F3 => Keysym::F3,
+            KeyKind::F4 => Keysym::F4,
+            KeyKind::F5 => Keysym::F5,
+            KeyKind::F6 => Keysym::F6,
+            KeyKind::F7 => Keysym::F7,
+            KeyKind::F8 => Keysym::F8,
+            KeyKind::F9 => Keysym::F9,
+            KeyKind::F10 => Keysym::F10,
+            KeyKind::F11 => Keysym::F11,
+            KeyKind::F12 => Keysym::F12,
+            KeyKind::Up => Keysym::Up,
+            KeyKind::Down => Keysym::Down,
+            KeyKind::Left => Keysym::Left,
+            KeyKind::Right => Keysym::Right,
+            KeyKind::Home => Keysym::Home,
+            KeyKind::End => Keysym::End,
+            KeyKind::PageUp => Keysym::Prior,
+            KeyKind::PageDown => Keysym::Next,
+            KeyKind::Insert => Keysym::Insert,
+            KeyKind::Delete => Keysym::Delete,
+            KeyKind::Ctrl => Keysym::Control_L,
+            KeyKind::Enter => Keysym::Return,
+            KeyKind::Space => Keysym::space,
+            KeyKind::Tilde => Keysym::grave,
+            KeyKind::Quote => Keysym::apostrophe,
+            KeyKind::Semicolon => Keysym::semicolon,
+            KeyKind::Comma => Keysym::comma,
+            KeyKind::Period => Keysym::period,
+            KeyKind::Slash => Keysym::slash,
+            KeyKind::Esc => Keysym::Escape,
+            KeyKind::Shift => Keysym::Shift_L,
+            KeyKind::Alt => Keysym::Alt_L,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keymap_keycode_and_keysym() {
+        // Key codes 8, 9 and 10 with two columns each
+        let keymap = Keymap {
+            min_keycode: 8,
+            keysyms_per_keycode: 2,
+            keysyms: vec![
+                Keysym::a.raw(),
+                Keysym::A.raw(),
+                Keysym::Control_L.raw(),
+                NONE,
+                NONE,
+                NONE,
+            ],
+        };
+
+        assert_eq!(keymap.keycode(KeyKind::A.into()), Some(8));
+        assert_eq!(keymap.keycode(KeyKind::Ctrl.into()), Some(9));
+        assert_eq!(keymap.keycode(KeyKind::Alt.into()), None);
+        assert_eq!(keymap.keysym(8), Some(Keysym::a));
+        assert_eq!(keymap.keysym(10), None);
+        assert_eq!(keymap.keysym(7), None);
+    }
+
+    #[test]
+    fn keysym_key_kind_round_trip() {
+        for key in [
+            KeyKind::A,
+            KeyKind::Zero,
+            KeyKind::F12,
+            KeyKind::PageDown,
+            KeyKind::Ctrl,
+            KeyKind::Tilde,
+            KeyKind::Alt,
+        ] {
+            let keysym = Keysym::from(key);
+            assert_eq!(Keysym::from(KeyKind::try_from(keysym).unwrap()), keysym);
+        }
+        assert!(matches!(
+            KeyKind::try_from(Keysym::Shift_R),
+            Ok(KeyKind::Shift)
+        ));
+        assert!(KeyKind::try_from(Keysym::Tab).is_err());
+    }
+}
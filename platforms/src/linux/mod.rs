@@ -0,0 +1,87 @@
+use std::sync::{
+    LazyLock,
+    atomic::{AtomicBool, Ordering},
+};
+
+use x11rb::{
+    errors::{ConnectError, ConnectionError, ReplyError, ReplyOrIdError},
+    rust_connection::RustConnection,
+};
+
+mod capture;
+mod handle;
+mod input;
+
+pub use {capture::*, handle::*, input::*};
+
+use crate::{Error, Result};
+
+x11rb::atom_manager! {
+    pub Atoms: AtomsCookie {
+        _NET_ACTIVE_WINDOW,
+        _NET_CLIENT_LIST,
+        _NET_WM_NAME,
+        _NET_WM_PID,
+        UTF8_STRING,
+    }
+}
+
+/// A connection to the X server shared by capture and window queries.
+#[derive(Debug)]
+pub struct Connection {
+    pub inner: RustConnection,
+    pub screen: usize,
+    pub atoms: Atoms,
+}
+
+static CONNECTION: LazyLock<Result<Connection>> = LazyLock::new(|| {
+    let (inner, screen) = x11rb::connect(None)?;
+    let atoms = Atoms::new(&inner)?.reply()?;
+
+    Ok(Connection {
+        inner,
+        screen,
+        atoms,
+    })
+});
+
+/// Gets the shared connection to the X server specified by `DISPLAY`.
+#[inline]
+pub fn connection() -> Result<&'static Connection> {
+    CONNECTION.as_ref().map_err(Clone::clone)
+}
+
+pub fn init() {
+    static INITIALIZED: AtomicBool = AtomicBool::new(false);
+
+    if INITIALIZED
+        .compare_exchange(false, true, Ordering::SeqCst, Ordering::Acquire)
+        .is_ok()
+    {
+        input::init();
+    }
+}
+
+impl From<ConnectError> for Error {
+    fn from(error: ConnectError) -> Self {
+        Error::X11(error.to_string())
+    }
+}
+
+impl From<ConnectionError> for Error {
+    fn from(error: ConnectionError) -> Self {
+        Error::X11(error.to_string())
+    }
+}
+
+impl From<ReplyError> for Error {
+    fn from(error: ReplyError) -> Self {
+        Error::X11(error.to_string())
+    }
+}
+
+impl From<ReplyOrIdError> for Error {
+    fn from(error: ReplyOrIdError) -> Self {
+        Error::X11(error.to_string())
+    }
+}
@@ -10,7 +10,10 @@ use windows::Win32::UI::WindowsAndMessaging::GetClientRect;
 use windows::core::{Owned, PCWSTR};
 
 use super::{HandleCell, handle::Handle};
-use crate::{Error, Result, capture::Frame};
+use crate::{
+    Error, Result,
+    capture::{Frame, PixelFormat},
+};
 
 #[derive(Debug)]
 struct DeviceContext {
@@ -125,6 +128,7 @@ impl BitBltCapture {
             width: bitmap.width,
             height: bitmap.height,
             data,
+            format: PixelFormat::Bgra,
         })
     }
 }
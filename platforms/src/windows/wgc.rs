@@ -2,7 +2,7 @@
 //! Thanks https://github.com/obsproject/obs-studio/blob/cfb23a51ff8acad13dc739c31854d9f451e05298/libobs-d3d11/d3d11-subsystem.cpp#L587
 //! Thanks https://github.com/obsproject/obs-studio/blob/cfb23a51ff8acad13dc739c31854d9f451e05298/libobs-winrt/winrt-capture.cpp#L244
 
-use std::{cmp::min, mem, ptr, slice, sync::mpsc, time::Duration};
+use std::{cmp::min, mem, slice, sync::mpsc, time::Duration};
 
 use windows::{
     Foundation::TypedEventHandler,
@@ -45,7 +45,10 @@ use windows::{
 };
 
 use super::{Handle, HandleCell};
-use crate::{Error, Result, capture::Frame};
+use crate::{
+    Error, Result,
+    capture::{Frame, PixelFormat},
+};
 
 const MAX_FRAME_FAILURE: u32 = 3;
 
@@ -144,26 +147,12 @@ impl WgcCaptureInner {
                 (texture_height * resource.RowPitch) as usize,
             )
         };
-        let vec = if texture_height * 4 != resource.RowPitch {
-            let capacity = (texture_width * texture_height * 4) as usize;
-            let dst_stride = (texture_width * 4) as usize;
-            let mut vec = Vec::<u8>::with_capacity(capacity);
-            let vec_ptr = vec.as_mut_ptr();
-            for i in 0..texture_height as usize {
-                let src_offset = resource.RowPitch as usize * i;
-                let dst_offset = dst_stride * i;
-                unsafe {
-                    ptr::copy_nonoverlapping(
-                        buffer.as_ptr().add(src_offset),
-                        vec_ptr.add(dst_offset),
-                        dst_stride,
-                    );
-                }
-            }
-            vec
-        } else {
-            buffer.to_vec()
-        };
+        let vec = tightly_packed_from(
+            buffer,
+            texture_width as usize * 4,
+            texture_height as usize,
+            resource.RowPitch as usize,
+        );
         unsafe {
             self.d3d11_context.Unmap(texture, 0);
         };
@@ -179,6 +168,7 @@ impl WgcCaptureInner {
             width: texture_width as i32,
             height: texture_height as i32,
             data: vec,
+            format: PixelFormat::Bgra,
         })
     }
 }
@@ -290,6 +280,22 @@ impl WgcCapture {
     }
 }
 
+/// Copies `height` rows of `stride` bytes from `buffer` whose rows are `row_pitch` bytes apart.
+///
+/// The mapped texture rows can be padded past the texture width for alignment.
+#[inline]
+fn tightly_packed_from(buffer: &[u8], stride: usize, height: usize, row_pitch: usize) -> Vec<u8> {
+    if stride == row_pitch {
+        return buffer[..stride * height].to_vec();
+    }
+
+    let mut vec = Vec::with_capacity(stride * height);
+    for row in buffer.chunks(row_pitch).take(height) {
+        vec.extend_from_slice(&row[..stride]);
+    }
+    vec
+}
+
 #[inline]
 fn get_client_rect(handle: HWND, width: u32, height: u32) -> Result<D3D11_BOX> {
     let mut window_rect = RECT::default();
@@ -424,3 +430,31 @@ fn create_d3d_device(d3d11_device: &ID3D11Device) -> Result<IDirect3DDevice> {
     let d3d_device = inspectable.cast()?;
     Ok(d3d_device)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tightly_packed_from_removes_row_padding() {
+        // 2x2 BGRA texture with rows padded to 12 bytes
+        let buffer = [
+            1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0, //
+            3, 3, 3, 3, 4, 4, 4, 4, 0, 0, 0, 0,
+        ];
+
+        let vec = tightly_packed_from(&buffer, 8, 2, 12);
+
+        assert_eq!(vec.len(), PixelFormat::Bgra.data_len(2, 2));
+        assert_eq!(vec, [1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4]);
+    }
+
+    #[test]
+    fn tightly_packed_from_copies_unpadded_rows() {
+        let buffer = [1, 1, 1, 1, 2, 2, 2, 2];
+
+        let vec = tightly_packed_from(&buffer, 4, 2, 4);
+
+        assert_eq!(vec, buffer);
+    }
+}
@@ -9,7 +9,8 @@ use std::{
 use backend::{
     Action, ActionCondition, ActionKey, ActionKeyDirection, ActionKeyWith, ActionMove, Bound,
     IntoEnumIterator, KeyBinding, LinkKeyBinding, Minimap, MobbingKey, Platform, Position,
-    RotationMode, key_receiver, update_minimap, upsert_minimap,
+    RevisionKind, RotationMode, key_receiver, restore_minimap_revision, update_minimap,
+    upsert_minimap,
 };
 use dioxus::prelude::*;
 use futures_util::StreamExt;
@@ -19,6 +20,7 @@ use tokio::sync::broadcast::error::RecvError;
 use crate::{
     AppState,
     button::{Button, ButtonKind},
+    history::History,
     icons::{DownArrowIcon, PositionIcon, UpArrowIcon, XIcon},
     inputs::{Checkbox, KeyBindingInput, MillisInput, NumberInputI32, NumberInputU32},
     popup::Popup,
@@ -245,6 +247,26 @@ pub fn Actions() -> Element {
                 disabled: minimap().is_none() || minimap_preset().is_none(),
             }
             SectionLegends {}
+            Section { name: "History",
+                History {
+                    kind: RevisionKind::Minimap,
+                    data_id: minimap().and_then(|minimap| minimap.id),
+                    on_restore: move |id| async move {
+                        let Some(restored) = restore_minimap_revision(id).await else {
+                            return;
+                        };
+                        if minimap_preset
+                            .peek()
+                            .as_ref()
+                            .is_none_or(|preset| !restored.actions.contains_key(preset))
+                        {
+                            minimap_preset.set(restored.actions.keys().next().cloned());
+                        }
+                        minimap.set(Some(restored));
+                        update_minimap(minimap_preset(), minimap()).await;
+                    },
+                }
+            }
         }
         if let Some(kind) = popup_input_kind() {
             match kind {
@@ -894,6 +916,29 @@ fn SectionActions(
                 actions: minimap_preset_actions(),
             }
         }
+        Section { name: "Skill off cooldown priority actions",
+            ActionList {
+                on_add_click: move |_| {
+                    let action = Action::Key(ActionKey {
+                        condition: ActionCondition::SkillOffCooldown(0),
+                        ..ActionKey::default()
+                    });
+                    popup_input(ActionInputKind::Add(action));
+                },
+                on_item_click: move |(action, index)| {
+                    popup_input(ActionInputKind::Edit(action, index));
+                },
+                on_item_move: move |(index, condition, up)| {
+                    move_action((index, condition, up));
+                },
+                on_item_delete: move |index| {
+                    delete_action(index);
+                },
+                condition_filter: ActionCondition::SkillOffCooldown(0),
+                disabled,
+                actions: minimap_preset_actions(),
+            }
+        }
         Section { name: "Every milliseconds priority actions",
             ActionList {
                 on_add_click: move |_| {
@@ -1140,6 +1185,7 @@ fn PopupActionInput(
         ActionInputKind::Add(_) | ActionInputKind::Edit(_, _) => match action.condition() {
             ActionCondition::EveryMillis(_)
             | ActionCondition::ErdaShowerOffCooldown
+            | ActionCondition::SkillOffCooldown(_)
             | ActionCondition::Any => {
                 let actions = actions();
                 let filtered = filter_actions(actions, action.condition());
@@ -1158,6 +1204,7 @@ fn PopupActionInput(
                 backend::ActionCondition::Any => "normal",
                 backend::ActionCondition::EveryMillis(_) => "every milliseconds",
                 backend::ActionCondition::ErdaShowerOffCooldown => "Erda Shower off cooldown",
+                backend::ActionCondition::SkillOffCooldown(_) => "skill off cooldown",
                 backend::ActionCondition::Linked => "linked",
             };
             if modifying {
@@ -1622,7 +1669,9 @@ fn ActionKeyInput(
             }
             if matches!(
                 action().condition,
-                ActionCondition::EveryMillis(_) | ActionCondition::ErdaShowerOffCooldown
+                ActionCondition::EveryMillis(_)
+                    | ActionCondition::ErdaShowerOffCooldown
+                    | ActionCondition::SkillOffCooldown(_)
             )
             {
                 ActionsCheckbox {
@@ -1647,6 +1696,18 @@ fn ActionKeyInput(
                 }
                 div { class: "col-span-2" }
             }
+            if let ActionCondition::SkillOffCooldown(id) = action().condition {
+                ActionsNumberInputU32 {
+                    label: "Character skill number",
+                    on_value: move |number: u32| {
+                        let mut action = action.write();
+                        let id = number.saturating_sub(1);
+                        action.condition = ActionCondition::SkillOffCooldown(id);
+                    },
+                    value: id + 1,
+                }
+                div { class: "col-span-2" }
+            }
 
             // Wait before use
             ActionsMillisInput {
@@ -1896,10 +1957,10 @@ fn ActionKeyItem(action: ActionKey) -> Element {
         Some(LinkKeyBinding::Along(key)) => format!("{key} ↷ "),
         None => "".to_string(),
     };
-    let millis = if let ActionCondition::EveryMillis(millis) = condition {
-        format!("⟳ {:.2}s / ", millis as f32 / 1000.0)
-    } else {
-        "".to_string()
+    let millis = match condition {
+        ActionCondition::EveryMillis(millis) => format!("⟳ {:.2}s / ", millis as f32 / 1000.0),
+        ActionCondition::SkillOffCooldown(id) => format!("✦ #{} / ", id + 1),
+        _ => "".to_string(),
     };
     let wait_before_secs = if wait_before_use_millis > 0 {
         Some(format!("⏱︎ {:.2}s", wait_before_use_millis as f32 / 1000.0))
@@ -1,9 +1,15 @@
-use std::{fmt::Display, fs::File, io::BufReader};
+use std::{
+    fmt::Display,
+    fs::{self, File},
+    io::BufReader,
+    path::Path,
+};
 
 use backend::{
-    ActionConfiguration, ActionConfigurationCondition, ActionKeyWith, Character, Class,
+    ActionConfiguration, ActionConfigurationCondition, ActionKeyWith, BuffIcon, Character, Class,
     EliteBossBehavior, IntoEnumIterator, KeyBinding, KeyBindingConfiguration, LinkKeyBinding,
-    PotionMode, delete_character, query_characters, update_character, upsert_character,
+    PotionMode, RevisionKind, SkillIcon, delete_character, query_characters, query_minimaps,
+    restore_character_revision, update_character, update_minimap, upsert_character,
 };
 use dioxus::prelude::*;
 use futures_util::StreamExt;
@@ -12,6 +18,8 @@ use rand::distr::{Alphanumeric, SampleString};
 use crate::{
     AppState,
     button::{Button, ButtonKind},
+    exp::ExpHistory,
+    history::History,
     icons::XIcon,
     inputs::{Checkbox, KeyBindingInput, MillisInput, NumberInputU32, PercentageInput},
     popup::Popup,
@@ -35,6 +43,8 @@ enum ActionConfigurationInputKind {
 #[component]
 pub fn Characters() -> Element {
     let mut character = use_context::<AppState>().character;
+    let mut minimap = use_context::<AppState>().minimap;
+    let mut minimap_preset = use_context::<AppState>().minimap_preset;
     let mut characters = use_resource(async || query_characters().await.unwrap_or_default());
     // Maps queried `characters` to names
     let character_names = use_memo(move || {
@@ -73,6 +83,29 @@ pub fn Characters() -> Element {
                 match message {
                     CharacterUpdate::Set => {
                         update_character(character()).await;
+
+                        // Loads the bound minimap and preset if any
+                        let Some((id, preset)) =
+                            character().and_then(|character| character.minimap_id_preset)
+                        else {
+                            continue;
+                        };
+                        let Some(bound_minimap) = query_minimaps()
+                            .await
+                            .unwrap_or_default()
+                            .into_iter()
+                            .find(|minimap| minimap.id == Some(id))
+                        else {
+                            continue;
+                        };
+                        let preset = if bound_minimap.actions.contains_key(&preset) {
+                            Some(preset)
+                        } else {
+                            bound_minimap.actions.keys().min().cloned()
+                        };
+                        minimap_preset.set(preset);
+                        minimap.set(Some(bound_minimap));
+                        update_minimap(minimap_preset(), minimap()).await;
                     }
                     CharacterUpdate::Update(new_character) => {
                         save_character(new_character).await;
@@ -130,7 +163,26 @@ pub fn Characters() -> Element {
                 character_view,
                 save_character,
             }
+            SectionMinimapBinding { character_view, save_character }
+            SectionSkills { character_view, save_character }
             SectionOthers { character_view, save_character }
+            Section { name: "EXP History",
+                ExpHistory { character_id: character().and_then(|character| character.id) }
+            }
+            Section { name: "History",
+                History {
+                    kind: RevisionKind::Character,
+                    data_id: character().and_then(|character| character.id),
+                    on_restore: move |id| async move {
+                        let Some(restored) = restore_character_revision(id).await else {
+                            return;
+                        };
+                        character.set(Some(restored));
+                        characters.restart();
+                        update_character(character()).await;
+                    },
+                }
+            }
         }
 
         if let Some(kind) = action_input_kind() {
@@ -312,6 +364,17 @@ fn SectionKeyBindings(
                     },
                     value: character_view().potion_key,
                 }
+                KeyBindingConfigurationInput {
+                    label: "MP potion",
+                    disabled: character_view().id.is_none(),
+                    on_value: move |key_config: Option<KeyBindingConfiguration>| {
+                        save_character(Character {
+                            mana_potion_key: key_config.expect("not optional"),
+                            ..character_view.peek().clone()
+                        });
+                    },
+                    value: character_view().mana_potion_key,
+                }
                 div { class: "col-span-full grid-cols-3 grid gap-2 justify-items-stretch",
                     KeyBindingConfigurationInput {
                         label: "Familiar menu",
@@ -387,6 +450,50 @@ fn SectionBuffs(character_view: Memo<Character>, save_character: Callback<Charac
         }
     }
 
+    let import_element_id = use_memo(|| Alphanumeric.sample_string(&mut rand::rng(), 8));
+    let import = use_callback(move |_| {
+        let js = format!(
+            r#"
+            const element = document.getElementById("{}");
+            if (element === null) {{
+                return;
+            }}
+            element.click();
+            "#,
+            import_element_id()
+        );
+        document::eval(js.as_str());
+    });
+    let import_icons = use_callback(move |files: Vec<String>| {
+        let mut character = character_view.peek().clone();
+        for file in files {
+            let Ok(icon) = fs::read(&file) else {
+                continue;
+            };
+            let name = Path::new(&file)
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or_default()
+                .to_string();
+            character.buffs.push(BuffIcon {
+                name,
+                icon,
+                ..BuffIcon::default()
+            });
+        }
+        save_character(character);
+    });
+    let update_custom_buff = use_callback(move |(index, buff): (usize, Option<BuffIcon>)| {
+        let mut character = character_view.peek().clone();
+        match buff {
+            Some(buff) => character.buffs[index] = buff,
+            None => {
+                character.buffs.remove(index);
+            }
+        }
+        save_character(character);
+    });
+
     rsx! {
         Section { name: "Buffs",
             CharactersCheckbox {
@@ -594,6 +701,86 @@ fn SectionBuffs(character_view: Memo<Character>, save_character: Callback<Charac
                     },
                     value: character_view().extreme_gold_potion_key,
                 }
+                for (index , buff) in character_view().buffs.into_iter().enumerate() {
+                    div { class: "relative group flex gap-2",
+                        KeyBindingInput {
+                            label: buff.name.clone(),
+                            div_class: "flex-1",
+                            disabled: character_view().id.is_none(),
+                            on_value: {
+                                let buff = buff.clone();
+                                move |key: Option<KeyBinding>| {
+                                    let mut buff = buff.clone();
+                                    buff.key.key = key.expect("not optional");
+                                    update_custom_buff((index, Some(buff)));
+                                }
+                            },
+                            value: Some(buff.key.key),
+                        }
+                        div { class: "w-20",
+                            CharactersNumberU32Input {
+                                label: "Max fails",
+                                disabled: character_view().id.is_none(),
+                                on_value: {
+                                    let buff = buff.clone();
+                                    move |max_fail_count| {
+                                        update_custom_buff((
+                                            index,
+                                            Some(BuffIcon {
+                                                max_fail_count,
+                                                ..buff.clone()
+                                            }),
+                                        ));
+                                    }
+                                },
+                                value: buff.max_fail_count,
+                            }
+                        }
+                        CharactersCheckbox {
+                            label: "Enabled",
+                            disabled: character_view().id.is_none(),
+                            on_value: {
+                                let buff = buff.clone();
+                                move |enabled| {
+                                    let mut buff = buff.clone();
+                                    buff.key.enabled = enabled;
+                                    update_custom_buff((index, Some(buff)));
+                                }
+                            },
+                            value: buff.key.enabled,
+                        }
+                        div {
+                            class: "absolute invisible group-hover:visible top-0 right-0 w-4 h-4 flex justify-center items-center",
+                            onclick: move |e| {
+                                e.stop_propagation();
+                                update_custom_buff((index, None));
+                            },
+                            XIcon { class: "w-[11px] h-[11px] fill-current text-red-500" }
+                        }
+                    }
+                }
+            }
+            input {
+                id: import_element_id(),
+                class: "w-0 h-0 invisible",
+                r#type: "file",
+                accept: ".png",
+                multiple: true,
+                name: "Buff icons",
+                onchange: move |e| {
+                    if let Some(files) = e.data.files().map(|engine| engine.files()) {
+                        import_icons(files);
+                    }
+                },
+            }
+            Button {
+                text: "Add buff icon",
+                kind: ButtonKind::Secondary,
+                disabled: character_view().id.is_none(),
+                on_click: move |_| {
+                    import(());
+                },
+                class: "label mt-2",
             }
         }
     }
@@ -638,6 +825,161 @@ fn SectionFixedActions(
     }
 }
 
+#[component]
+fn SectionMinimapBinding(
+    character_view: Memo<Character>,
+    save_character: Callback<Character>,
+) -> Element {
+    let minimap = use_context::<AppState>().minimap;
+    let minimap_preset = use_context::<AppState>().minimap_preset;
+    let minimaps = use_resource(async || query_minimaps().await.unwrap_or_default());
+    // Maps the bound minimap and preset of `character_view` to a displayable text
+    let binding_text = use_memo(move || {
+        let Some((id, preset)) = character_view().minimap_id_preset else {
+            return "None".to_string();
+        };
+        let name = minimaps()
+            .unwrap_or_default()
+            .into_iter()
+            .find(|minimap| minimap.id == Some(id))
+            .map(|minimap| minimap.name)
+            .unwrap_or("Deleted map".to_string());
+
+        format!("{name} / {preset}")
+    });
+    let bind_disabled = use_memo(move || {
+        character_view().id.is_none()
+            || minimap().and_then(|minimap| minimap.id).is_none()
+            || minimap_preset().is_none()
+    });
+
+    rsx! {
+        Section { name: "Map binding",
+            div { class: "flex gap-4 items-center",
+                div { class: "flex-grow paragraph-xs text-ellipsis overflow-hidden whitespace-nowrap",
+                    {binding_text()}
+                }
+                Button {
+                    class: "w-32",
+                    text: "Bind current",
+                    kind: ButtonKind::Primary,
+                    disabled: bind_disabled(),
+                    on_click: move |_| {
+                        let id = minimap.peek().as_ref().and_then(|minimap| minimap.id);
+                        let Some(id_preset) = id.zip(minimap_preset.peek().clone()) else {
+                            return;
+                        };
+                        save_character(Character {
+                            minimap_id_preset: Some(id_preset),
+                            ..character_view.peek().clone()
+                        });
+                    },
+                }
+                Button {
+                    class: "w-32",
+                    text: "Clear",
+                    kind: ButtonKind::Secondary,
+                    disabled: character_view().minimap_id_preset.is_none(),
+                    on_click: move |_| {
+                        save_character(Character {
+                            minimap_id_preset: None,
+                            ..character_view.peek().clone()
+                        });
+                    },
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn SectionSkills(character_view: Memo<Character>, save_character: Callback<Character>) -> Element {
+    let import_element_id = use_memo(|| Alphanumeric.sample_string(&mut rand::rng(), 8));
+    let import = use_callback(move |_| {
+        let js = format!(
+            r#"
+            const element = document.getElementById("{}");
+            if (element === null) {{
+                return;
+            }}
+            element.click();
+            "#,
+            import_element_id()
+        );
+        document::eval(js.as_str());
+    });
+    let import_icons = use_callback(move |files: Vec<String>| {
+        let mut character = character_view.peek().clone();
+        for file in files {
+            let Ok(icon) = fs::read(&file) else {
+                continue;
+            };
+            let name = Path::new(&file)
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or_default()
+                .to_string();
+            let id = character
+                .skills
+                .iter()
+                .map(|skill| skill.id + 1)
+                .max()
+                .unwrap_or_default()
+                .max(character.next_skill_id);
+            character.next_skill_id = id + 1;
+            character.skills.push(SkillIcon { id, name, icon });
+        }
+        save_character(character);
+    });
+
+    rsx! {
+        Section { name: "Skills",
+            div { class: "flex flex-col",
+                for (index , skill) in character_view().skills.into_iter().enumerate() {
+                    div { class: "relative group flex items-center h-6 paragraph-xs !text-gray-400 group-hover:bg-gray-900",
+                        div { class: "w-10 text-center", "#{skill.id + 1}" }
+                        div { class: "flex-grow text-ellipsis overflow-hidden whitespace-nowrap",
+                            "{skill.name}"
+                        }
+                        div {
+                            class: "absolute invisible group-hover:visible top-0 right-1 w-4 h-6 flex justify-center items-center",
+                            onclick: move |e| {
+                                e.stop_propagation();
+                                let mut character = character_view.peek().clone();
+                                character.skills.remove(index);
+                                save_character(character);
+                            },
+                            XIcon { class: "w-[11px] h-[11px] fill-current text-red-500" }
+                        }
+                    }
+                }
+                input {
+                    id: import_element_id(),
+                    class: "w-0 h-0 invisible",
+                    r#type: "file",
+                    accept: ".png",
+                    multiple: true,
+                    name: "Skill icons",
+                    onchange: move |e| {
+                        if let Some(files) = e.data.files().map(|engine| engine.files()) {
+                            import_icons(files);
+                        }
+                    },
+                }
+                Button {
+                    text: "Add skill icon",
+                    kind: ButtonKind::Secondary,
+                    disabled: character_view().id.is_none(),
+                    on_click: move |_| {
+                        import(());
+                    },
+                    class: "label mt-2",
+                }
+            }
+        }
+    }
+}
+
 #[component]
 fn SectionOthers(character_view: Memo<Character>, save_character: Callback<Character>) -> Element {
     let export_element_id = use_memo(|| Alphanumeric.sample_string(&mut rand::rng(), 8));
@@ -798,6 +1140,60 @@ fn SectionOthers(character_view: Memo<Character>, save_character: Callback<Chara
                     },
                     value: character_view().potion_key.enabled,
                 }
+                CharactersSelect::<PotionMode> {
+                    label: "MP potion mode",
+                    disabled: character_view().id.is_none(),
+                    on_select: move |mana_potion_mode| {
+                        save_character(Character {
+                            mana_potion_mode,
+                            ..character_view.peek().clone()
+                        });
+                    },
+                    selected: character_view().mana_potion_mode,
+                }
+                match character_view().mana_potion_mode {
+                    PotionMode::EveryMillis(millis) => rsx! {
+                        CharactersMillisInput {
+                            label: "Use every",
+                            disabled: character_view().id.is_none(),
+                            on_value: move |millis| {
+                                save_character(Character {
+                                    mana_potion_mode: PotionMode::EveryMillis(millis),
+                                    ..character_view.peek().clone()
+                                });
+                            },
+                            value: millis,
+                        }
+                    },
+                    PotionMode::Percentage(percent) => rsx! {
+                        CharactersPercentageInput {
+                            label: "Use below mana",
+                            disabled: character_view().id.is_none(),
+                            on_value: move |percent| {
+                                save_character(Character {
+                                    mana_potion_mode: PotionMode::Percentage(percent),
+                                    ..character_view.peek().clone()
+                                });
+                            },
+                            value: percent,
+                        }
+                    },
+                }
+                CharactersCheckbox {
+                    label: "Use MP potion",
+                    disabled: character_view().id.is_none(),
+                    on_value: move |enabled| {
+                        let character = character_view.peek().clone();
+                        save_character(Character {
+                            mana_potion_key: KeyBindingConfiguration {
+                                enabled,
+                                ..character.mana_potion_key
+                            },
+                            ..character
+                        });
+                    },
+                    value: character_view().mana_potion_key.enabled,
+                }
                 CharactersSelect::<Class> {
                     label: "Link key timing class",
                     disabled: character_view().id.is_none(),
@@ -1,15 +1,19 @@
 use backend::{
-    DebugState, auto_save_rune, capture_image, debug_state_receiver, infer_minimap, infer_rune,
-    record_images, test_spin_rune,
+    DebugState, auto_save_rune, capture_image, compare_input_latency, debug_state_receiver,
+    infer_minimap, infer_rune, record_images, test_spin_rune,
 };
 use dioxus::prelude::*;
 use tokio::sync::broadcast::error::RecvError;
 
-use crate::button::{Button, ButtonKind};
+use crate::{
+    button::{Button, ButtonKind},
+    inputs::TextInput,
+};
 
 #[component]
 pub fn Debug() -> Element {
     let mut state = use_signal(DebugState::default);
+    let mut latency_urls = use_signal(String::default);
 
     use_future(move || async move {
         let mut rx = debug_state_receiver().await;
@@ -80,6 +84,52 @@ pub fn Debug() -> Element {
                     }
                 }
             }
+            Section { name: "Input Latency",
+                div { class: "grid grid-cols-[auto_100px] gap-3",
+                    TextInput {
+                        label: "RPC server URLs (comma separated)",
+                        on_value: move |urls| {
+                            latency_urls.set(urls);
+                        },
+                        value: latency_urls(),
+                    }
+                    div { class: "flex items-end",
+                        Button {
+                            text: "Compare",
+                            kind: ButtonKind::Secondary,
+                            on_click: move |_| async move {
+                                let urls = latency_urls
+                                    .peek()
+                                    .split(',')
+                                    .map(|url| url.trim().to_string())
+                                    .filter(|url| !url.is_empty())
+                                    .collect::<Vec<_>>();
+                                compare_input_latency(urls).await;
+                            },
+                            class: "w-full",
+                        }
+                    }
+                }
+                div { class: "flex flex-col pt-2",
+                    for latency in state().input_latencies {
+                        div { class: "grid grid-cols-[auto_240px] h-6 paragraph-xs !text-gray-400",
+                            div { class: "text-ellipsis overflow-hidden whitespace-nowrap",
+                                {latency.url}
+                            }
+                            match latency.latency {
+                                Ok((mean, min, max)) => rsx! {
+                                    div { "{mean:.2} ms (min {min:.2} / max {max:.2})" }
+                                },
+                                Err(err) => rsx! {
+                                    div { class: "text-ellipsis overflow-hidden whitespace-nowrap",
+                                        {err}
+                                    }
+                                },
+                            }
+                        }
+                    }
+                }
+            }
         }
     }
 }
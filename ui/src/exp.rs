@@ -0,0 +1,190 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use backend::{
+    DatabaseEvent, ExpSession, database_event_receiver, query_exp_sessions, query_minimaps,
+};
+use dioxus::prelude::*;
+use tokio::sync::broadcast::error::RecvError;
+
+const ITEM_TEXT_CLASS: &str =
+    "inline-block pt-1 text-ellipsis overflow-hidden whitespace-nowrap paragraph-xs";
+
+/// The maximum number of most recent sessions listed.
+const MAX_RECENT_SESSIONS: usize = 20;
+
+/// EXP sessions of a character on the same minimap and preset combined.
+#[derive(PartialEq, Clone, Debug)]
+struct ExpSummary {
+    minimap: String,
+    preset: Option<String>,
+    sessions: usize,
+    duration_millis: i64,
+    gained: f32,
+    exp_per_hour: Option<f32>,
+}
+
+/// Lists EXP sessions of the character with `character_id` from newest to oldest.
+///
+/// Sessions are also combined per minimap and preset, ordered by EXP per hour to compare where
+/// the character levels fastest.
+#[component]
+pub fn ExpHistory(character_id: Option<i64>) -> Element {
+    let mut sessions = use_resource(use_reactive!(|character_id| async move {
+        let Some(character_id) = character_id else {
+            return vec![];
+        };
+        query_exp_sessions()
+            .await
+            .into_iter()
+            .filter(|session| session.character_id == character_id)
+            .collect::<Vec<_>>()
+    }));
+    let mut minimap_names = use_resource(async || {
+        query_minimaps()
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|minimap| Some((minimap.id?, minimap.name)))
+            .collect::<HashMap<_, _>>()
+    });
+
+    use_future(move || async move {
+        let mut rx = database_event_receiver();
+        loop {
+            let event = match rx.recv().await {
+                Ok(value) => value,
+                Err(RecvError::Closed) => break,
+                Err(RecvError::Lagged(_)) => continue,
+            };
+            match event {
+                DatabaseEvent::ExpSessionUpdated => sessions.restart(),
+                DatabaseEvent::MinimapUpdated(_) | DatabaseEvent::MinimapDeleted(_) => {
+                    minimap_names.restart()
+                }
+                DatabaseEvent::ProfileSwitched(_) => {
+                    sessions.restart();
+                    minimap_names.restart();
+                }
+                _ => (),
+            }
+        }
+    });
+
+    let minimap_name = move |id: i64| {
+        minimap_names()
+            .and_then(|names| names.get(&id).cloned())
+            .unwrap_or(format!("#{id}"))
+    };
+    let sessions_view = use_memo(move || sessions().unwrap_or_default());
+    let summaries = use_memo(move || summaries_from(&sessions_view(), minimap_name));
+
+    rsx! {
+        div { class: "flex flex-col gap-1",
+            if sessions_view().is_empty() {
+                div { class: "paragraph-xs text-gray-400", "No EXP sessions" }
+            } else {
+                div { class: "grid grid-cols-5 gap-2 paragraph-xs text-gray-400",
+                    div { "Map" }
+                    div { "Preset" }
+                    div { "Sessions" }
+                    div { "Duration" }
+                    div { "EXP / hour (gained)" }
+                }
+            }
+            for summary in summaries() {
+                div { class: "grid grid-cols-5 gap-2 h-6",
+                    div { class: ITEM_TEXT_CLASS, {summary.minimap} }
+                    div { class: ITEM_TEXT_CLASS, {summary.preset.unwrap_or("-".to_string())} }
+                    div { class: ITEM_TEXT_CLASS, {summary.sessions.to_string()} }
+                    div { class: ITEM_TEXT_CLASS, {duration_from(summary.duration_millis)} }
+                    div { class: ITEM_TEXT_CLASS,
+                        {exp_text_from(summary.gained, summary.exp_per_hour)}
+                    }
+                }
+            }
+            if !sessions_view().is_empty() {
+                div { class: "flex items-center title-xs h-10", "Recent sessions" }
+                div { class: "grid grid-cols-5 gap-2 paragraph-xs text-gray-400",
+                    div { "Map" }
+                    div { "Preset" }
+                    div { "Started" }
+                    div { "Duration" }
+                    div { "EXP / hour (gained)" }
+                }
+            }
+            for session in sessions_view().into_iter().take(MAX_RECENT_SESSIONS) {
+                div { class: "grid grid-cols-5 gap-2 h-6",
+                    div { class: ITEM_TEXT_CLASS, {minimap_name(session.minimap_id)} }
+                    div { class: ITEM_TEXT_CLASS, {session.preset.clone().unwrap_or("-".to_string())} }
+                    div { class: ITEM_TEXT_CLASS, {timestamp_from(session.start_millis)} }
+                    div { class: ITEM_TEXT_CLASS,
+                        {duration_from(session.end_millis - session.start_millis)}
+                    }
+                    div { class: ITEM_TEXT_CLASS,
+                        {exp_text_from(session.gained, session.exp_per_hour())}
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Combines `sessions` per minimap and preset, ordered by EXP per hour from highest to lowest.
+fn summaries_from(
+    sessions: &[ExpSession],
+    minimap_name: impl Fn(i64) -> String,
+) -> Vec<ExpSummary> {
+    let mut combined = HashMap::<(i64, Option<String>), (usize, ExpSession)>::new();
+    for session in sessions {
+        let (count, total) = combined
+            .entry((session.minimap_id, session.preset.clone()))
+            .or_default();
+        *count += 1;
+        // Only the duration matters when extrapolating EXP per hour
+        total.end_millis += session.end_millis - session.start_millis;
+        total.gained += session.gained;
+    }
+
+    let mut summaries = combined
+        .into_iter()
+        .map(|((minimap_id, preset), (sessions, total))| ExpSummary {
+            minimap: minimap_name(minimap_id),
+            preset,
+            sessions,
+            duration_millis: total.end_millis,
+            gained: total.gained,
+            exp_per_hour: total.exp_per_hour(),
+        })
+        .collect::<Vec<_>>();
+    summaries.sort_by(|first, second| {
+        second
+            .exp_per_hour
+            .unwrap_or_default()
+            .total_cmp(&first.exp_per_hour.unwrap_or_default())
+            .then_with(|| first.minimap.cmp(&second.minimap))
+            .then_with(|| first.preset.cmp(&second.preset))
+    });
+    summaries
+}
+
+#[inline]
+fn exp_text_from(gained: f32, exp_per_hour: Option<f32>) -> String {
+    match exp_per_hour {
+        Some(exp_per_hour) => format!("{exp_per_hour:.3}% ({gained:.3}%)"),
+        None => format!("- ({gained:.3}%)"),
+    }
+}
+
+#[inline]
+fn duration_from(millis: i64) -> String {
+    let minutes = millis.max(0) / 60_000;
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+}
+
+#[inline]
+fn timestamp_from(millis: i64) -> String {
+    humantime::format_rfc3339_seconds(UNIX_EPOCH + Duration::from_millis(millis as u64)).to_string()
+}
@@ -0,0 +1,117 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use backend::{
+    DatabaseEvent, RevisionKind, database_event_receiver, diff_revisions, query_revisions,
+};
+use dioxus::prelude::*;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::button::{Button, ButtonKind};
+
+const ITEM_TEXT_CLASS: &str =
+    "inline-block pt-1 text-ellipsis overflow-hidden whitespace-nowrap paragraph-xs";
+
+/// Lists revisions of the minimap or character with `data_id` from newest to oldest.
+///
+/// Each revision can be compared to the one before it or restored through `on_restore`.
+#[component]
+pub fn History(kind: RevisionKind, data_id: Option<i64>, on_restore: EventHandler<i64>) -> Element {
+    // The index in `revisions` to compare with its previous revision
+    let mut selected_index = use_signal::<Option<usize>>(|| None);
+    let mut revisions = use_resource(use_reactive!(|kind, data_id| async move {
+        // Indices are shifted whenever revisions are re-queried
+        selected_index.set(None);
+        match data_id {
+            Some(id) => query_revisions(kind, id).await,
+            None => vec![],
+        }
+    }));
+    let changes = use_resource(move || async move {
+        let index = selected_index()?;
+        let revisions = revisions()?;
+        let to = revisions.get(index)?;
+        let from = revisions.get(index + 1)?;
+
+        diff_revisions(from.id, to.id).await
+    });
+
+    use_future(move || async move {
+        let mut rx = database_event_receiver();
+        loop {
+            let event = match rx.recv().await {
+                Ok(value) => value,
+                Err(RecvError::Closed) => break,
+                Err(RecvError::Lagged(_)) => continue,
+            };
+            let updated = matches!(
+                (event, kind),
+                (DatabaseEvent::MinimapUpdated(_), RevisionKind::Minimap)
+                    | (DatabaseEvent::CharacterUpdated(_), RevisionKind::Character)
+            );
+            if updated {
+                revisions.restart();
+            }
+        }
+    });
+
+    let revisions_view = use_memo(move || revisions().unwrap_or_default());
+
+    rsx! {
+        div { class: "flex flex-col gap-1",
+            if revisions_view().is_empty() {
+                div { class: "paragraph-xs text-gray-400", "No revisions" }
+            }
+            for (index , revision) in revisions_view().into_iter().enumerate() {
+                div { class: "flex gap-2 items-center h-6",
+                    div { class: "flex-grow {ITEM_TEXT_CLASS}",
+                        {timestamp_from(revision.timestamp_millis)}
+                    }
+                    Button {
+                        class: "w-16",
+                        text: "Diff",
+                        kind: ButtonKind::Secondary,
+                        disabled: index + 1 >= revisions_view().len(),
+                        on_click: move |_| {
+                            if selected_index() == Some(index) {
+                                selected_index.set(None);
+                            } else {
+                                selected_index.set(Some(index));
+                            }
+                        },
+                    }
+                    Button {
+                        class: "w-16",
+                        text: "Restore",
+                        kind: ButtonKind::Primary,
+                        disabled: index == 0,
+                        on_click: move |_| {
+                            selected_index.set(None);
+                            on_restore(revision.id);
+                        },
+                    }
+                }
+                if selected_index() == Some(index) {
+                    div { class: "flex flex-col pl-2 border-l-2 border-gray-700",
+                        for change in changes().flatten().unwrap_or_default() {
+                            div { class: "{ITEM_TEXT_CLASS}",
+                                {
+                                    format!(
+                                        "{}: {} → {}",
+                                        change.path,
+                                        change.from.unwrap_or("none".to_string()),
+                                        change.to.unwrap_or("none".to_string()),
+                                    )
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[inline]
+fn timestamp_from(millis: i64) -> String {
+    humantime::format_rfc3339_seconds(UNIX_EPOCH + Duration::from_millis(millis as u64)).to_string()
+}
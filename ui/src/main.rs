@@ -30,6 +30,8 @@ mod button;
 mod characters;
 #[cfg(debug_assertions)]
 mod debug;
+mod exp;
+mod history;
 mod icons;
 mod inputs;
 mod minimap;
@@ -8,8 +8,8 @@ use std::{
 use backend::{
     Action, ActionKey, ActionMove, DatabaseEvent, GameOperation, Minimap as MinimapData, Position,
     RotateKind, RotationMode, create_minimap, database_event_receiver, delete_minimap,
-    game_state_receiver, query_minimaps, redetect_minimap, rotate_actions, update_minimap,
-    upsert_minimap,
+    game_state_receiver, minimap_auto_selected_receiver, query_minimaps, redetect_minimap,
+    rotate_actions, update_minimap, upsert_minimap,
 };
 use dioxus::{document::EvalError, prelude::*};
 use futures_util::StreamExt;
@@ -288,6 +288,11 @@ struct ActionView {
 struct MinimapState {
     position: Option<(i32, i32)>,
     health: Option<(u32, u32)>,
+    mana: Option<(u32, u32)>,
+    exp: Option<f32>,
+    exp_gained: Option<f32>,
+    exp_per_hour: Option<f32>,
+    map_name: Option<String>,
     state: String,
     normal_action: Option<String>,
     priority_action: Option<String>,
@@ -386,6 +391,19 @@ pub fn Minimap() -> Element {
             coroutine.send(MinimapUpdate::Set);
         }
     });
+    // Follows minimap and preset automatically selected on map entry
+    use_future(move || async move {
+        let mut rx = minimap_auto_selected_receiver().await;
+        loop {
+            let (selected, preset) = match rx.recv().await {
+                Ok(value) => value,
+                Err(RecvError::Closed) => break,
+                Err(RecvError::Lagged(_)) => continue,
+            };
+            minimap.set(Some(selected));
+            minimap_preset.set(preset);
+        }
+    });
     // External modification checking
     use_future(move || async move {
         let mut rx = database_event_receiver();
@@ -395,9 +413,15 @@ pub fn Minimap() -> Element {
                 Err(RecvError::Closed) => break,
                 Err(RecvError::Lagged(_)) => continue,
             };
+            if matches!(event, DatabaseEvent::ProfileSwitched(_)) {
+                minimap.set(None);
+                minimap_preset.set(None);
+            }
             if matches!(
                 event,
-                DatabaseEvent::MinimapUpdated(_) | DatabaseEvent::MinimapDeleted(_)
+                DatabaseEvent::MinimapUpdated(_)
+                    | DatabaseEvent::MinimapDeleted(_)
+                    | DatabaseEvent::ProfileSwitched(_)
             ) {
                 minimaps.restart();
             }
@@ -534,6 +558,11 @@ fn Canvas(
             let current_state = MinimapState {
                 position: current_state.position,
                 health: current_state.health,
+                mana: current_state.mana,
+                exp: current_state.exp,
+                exp_gained: current_state.exp_gained,
+                exp_per_hour: current_state.exp_per_hour,
+                map_name: current_state.map_name,
                 state: current_state.state,
                 normal_action: current_state.normal_action,
                 priority_action: current_state.priority_action,
@@ -593,6 +622,10 @@ fn Info(
     struct GameStateInfo {
         position: String,
         health: String,
+        mana: String,
+        exp: String,
+        exp_per_hour: String,
+        map_name: String,
         state: String,
         normal_action: String,
         priority_action: String,
@@ -606,6 +639,10 @@ fn Info(
         let mut info = GameStateInfo {
             position: "Unknown".to_string(),
             health: "Unknown".to_string(),
+            mana: "Unknown".to_string(),
+            exp: "Unknown".to_string(),
+            exp_per_hour: "Unknown".to_string(),
+            map_name: "Unknown".to_string(),
             state: "Unknown".to_string(),
             normal_action: "None".to_string(),
             priority_action: "None".to_string(),
@@ -635,6 +672,21 @@ fn Info(
             if let Some((current, max)) = state.health {
                 info.health = format!("{current} / {max}");
             }
+            if let Some((current, max)) = state.mana {
+                info.mana = format!("{current} / {max}");
+            }
+            if let Some(exp) = state.exp {
+                info.exp = match state.exp_gained {
+                    Some(gained) => format!("{exp:.3}% (+{gained:.3}%)"),
+                    None => format!("{exp:.3}%"),
+                };
+            }
+            if let Some(exp_per_hour) = state.exp_per_hour {
+                info.exp_per_hour = format!("{exp_per_hour:.3}%");
+            }
+            if let Some(map_name) = state.map_name {
+                info.map_name = map_name;
+            }
             if let Some(action) = state.normal_action {
                 info.normal_action = action;
             }
@@ -652,8 +704,12 @@ fn Info(
     rsx! {
         div { class: "grid grid-cols-2 items-center justify-center px-4 py-3 gap-1",
             InfoItem { name: "State", value: info().state }
+            InfoItem { name: "Map", value: info().map_name }
             InfoItem { name: "Position", value: info().position }
             InfoItem { name: "Health", value: info().health }
+            InfoItem { name: "Mana", value: info().mana }
+            InfoItem { name: "EXP", value: info().exp }
+            InfoItem { name: "EXP/hour", value: info().exp_per_hour }
             InfoItem { name: "Priority action", value: info().priority_action }
             InfoItem { name: "Normal action", value: info().normal_action }
             InfoItem { name: "Erda Shower", value: info().erda_shower_state }
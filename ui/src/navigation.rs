@@ -389,7 +389,9 @@ fn SectionPaths(popup: Signal<Option<NavigationPopup>>) -> Element {
             };
             if matches!(
                 event,
-                DatabaseEvent::NavigationPathsUpdated | DatabaseEvent::NavigationPathsDeleted
+                DatabaseEvent::NavigationPathsUpdated
+                    | DatabaseEvent::NavigationPathsDeleted
+                    | DatabaseEvent::ProfileSwitched(_)
             ) {
                 paths.restart();
             }
@@ -1,23 +1,33 @@
 use std::{fmt::Display, fs::File, io::BufReader};
 
 use backend::{
-    CaptureMode, CycleRunStopMode, FamiliarRarity, Familiars, InputMethod, IntoEnumIterator,
-    KeyBinding, KeyBindingConfiguration, Notifications, Settings as SettingsData,
-    SwappableFamiliars, query_capture_handles, query_settings, refresh_capture_handles,
-    select_capture_handle, upsert_settings,
+    Bundle, CaptureMode, CapturePlayback, CycleRunStopMode, DatabaseEvent, FamiliarRarity,
+    Familiars, InputMethod, IntoEnumIterator, KeyBinding, KeyBindingConfiguration,
+    MinimapAutoSelectPolicy, Notifications, Settings as SettingsData, SwappableFamiliars,
+    current_profile, database_event_receiver, delete_profile, delete_quarantined_row,
+    export_bundle, import_bundle, query_capture_handles, query_models, query_profiles,
+    query_quarantined_rows, query_settings, refresh_capture_handles, select_capture_handle,
+    switch_profile, upsert_settings,
 };
 use dioxus::prelude::*;
 use futures_util::StreamExt;
 use rand::distr::{Alphanumeric, SampleString};
+use tokio::sync::broadcast::error::RecvError;
 
 use crate::{
     AppState,
     button::{Button, ButtonKind},
     icons::{EyePasswordHideIcon, EyePasswordShowIcon},
     inputs::{Checkbox, KeyBindingInput, MillisInput, TextInput},
-    select::{EnumSelect, Select},
+    select::{EnumSelect, Select, TextSelect},
 };
 
+const DEFAULT_PROFILE: &str = "Default";
+
+const PLAYBACK_SPEEDS: [f32; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+
+const PLAYBACK_SPEED_DEFAULT_INDEX: usize = 2;
+
 #[derive(Debug)]
 enum SettingsUpdate {
     Update(SettingsData),
@@ -52,6 +62,7 @@ pub fn Settings() -> Element {
 
     rsx! {
         div { class: "flex flex-col h-full overflow-y-auto scrollbar",
+            SectionProfiles { settings }
             SectionCapture { settings_view, save_settings }
             SectionInput { settings_view, save_settings }
             SectionFamiliars { settings_view, save_settings }
@@ -59,6 +70,9 @@ pub fn Settings() -> Element {
             SectionHotkeys { settings_view, save_settings }
             SectionRunStopCycle { settings_view, save_settings }
             SectionOthers { settings_view, save_settings }
+            SectionBundle { settings }
+            SectionRecovery {}
+            SectionModels {}
         }
     }
 }
@@ -73,6 +87,66 @@ fn Section(name: &'static str, children: Element) -> Element {
     }
 }
 
+#[component]
+fn SectionProfiles(settings: Signal<Option<SettingsData>>) -> Element {
+    let mut character = use_context::<AppState>().character;
+    let mut profiles = use_resource(async || query_profiles().await);
+    let mut profile = use_resource(async || current_profile().await);
+    // Prepends the default profile to queried `profiles`
+    let profile_names = use_memo(move || {
+        let default = vec![DEFAULT_PROFILE.to_string()];
+        let names = profiles().unwrap_or_default();
+
+        [default, names].concat()
+    });
+    // Maps currently opened `profile` to the index in `profile_names`
+    let profile_index = use_memo(move || match profile()? {
+        Some(profile) => profile_names()
+            .into_iter()
+            .skip(1)
+            .position(|name| name == profile)
+            .map(|index| index + 1),
+        None => Some(0),
+    });
+    let switch = use_callback(move |profile_name: Option<String>| async move {
+        if switch_profile(profile_name).await {
+            character.set(None);
+            settings.set(Some(query_settings().await));
+            profiles.restart();
+            profile.restart();
+        }
+    });
+
+    rsx! {
+        Section { name: "Profile",
+            TextSelect {
+                class: "w-full",
+                options: profile_names(),
+                disabled: false,
+                placeholder: "Create a profile...",
+                on_create: move |name| async move {
+                    switch(Some(name)).await;
+                },
+                on_delete: move |index| async move {
+                    if index == 0 {
+                        return;
+                    }
+                    let Some(name) = profile_names.peek().get(index).cloned() else {
+                        return;
+                    };
+                    if delete_profile(name).await {
+                        profiles.restart();
+                    }
+                },
+                on_select: move |(index, name)| async move {
+                    switch((index != 0).then_some(name)).await;
+                },
+                selected: profile_index(),
+            }
+        }
+    }
+}
+
 #[component]
 fn SectionCapture(
     settings_view: Memo<SettingsData>,
@@ -119,6 +193,72 @@ fn SectionCapture(
                     selected: settings_view().capture_mode,
                 }
             }
+            if settings_view().capture_mode == CaptureMode::Playback {
+                div { class: "grid grid-cols-2 gap-3 mt-2",
+                    SettingsTextInput {
+                        text_label: "Image directory or video path",
+                        button_label: "Update",
+                        on_value: move |path| {
+                            save_settings(SettingsData {
+                                capture_playback: CapturePlayback {
+                                    path,
+                                    ..settings_view.peek().capture_playback.clone()
+                                },
+                                ..settings_view.peek().clone()
+                            });
+                        },
+                        value: settings_view().capture_playback.path,
+                    }
+                    SettingsSelect {
+                        label: "Playback speed",
+                        options: PLAYBACK_SPEEDS
+                            .iter()
+                            .map(|speed| format!("{speed}x"))
+                            .collect::<Vec<_>>(),
+                        on_select: move |(index, _)| {
+                            save_settings(SettingsData {
+                                capture_playback: CapturePlayback {
+                                    speed: PLAYBACK_SPEEDS[index],
+                                    ..settings_view.peek().capture_playback.clone()
+                                },
+                                ..settings_view.peek().clone()
+                            });
+                        },
+                        selected: PLAYBACK_SPEEDS
+                            .iter()
+                            .position(|speed| *speed == settings_view().capture_playback.speed)
+                            .unwrap_or(PLAYBACK_SPEED_DEFAULT_INDEX),
+                    }
+                    SettingsCheckbox {
+                        label: "Loop playback",
+                        on_value: move |looping| {
+                            save_settings(SettingsData {
+                                capture_playback: CapturePlayback {
+                                    looping,
+                                    ..settings_view.peek().capture_playback.clone()
+                                },
+                                ..settings_view.peek().clone()
+                            });
+                        },
+                        value: settings_view().capture_playback.looping,
+                    }
+                }
+            }
+            if settings_view().capture_mode == CaptureMode::Rpc {
+                div { class: "grid grid-cols-2 gap-3 mt-2",
+                    SettingsTextInput {
+                        text_label: "Frame source RPC server URL",
+                        button_label: "Update",
+                        on_value: move |capture_rpc_server_url| {
+                            save_settings(SettingsData {
+                                capture_rpc_server_url,
+                                ..settings_view.peek().clone()
+                            });
+                        },
+                        value: settings_view().capture_rpc_server_url,
+                    }
+                }
+            }
             Button {
                 text: "Refresh handles",
                 kind: ButtonKind::Secondary,
@@ -407,6 +547,19 @@ fn SectionControlAndNotifications(
                     },
                     value: notifications_view().notify_on_fail_or_change_map,
                 }
+                SettingsCheckbox {
+                    label: "Input server disconnects or reconnects",
+                    on_value: move |notify_on_input_disconnect_or_reconnect| {
+                        save_settings(SettingsData {
+                            notifications: Notifications {
+                                notify_on_input_disconnect_or_reconnect,
+                                ..notifications_view.peek().clone()
+                            },
+                            ..settings_view.peek().clone()
+                        });
+                    },
+                    value: notifications_view().notify_on_input_disconnect_or_reconnect,
+                }
             }
         }
     }
@@ -611,7 +764,16 @@ fn SectionOthers(
                     },
                     value: settings_view().enable_rune_solving,
                 }
-                div {}
+                SettingsEnumSelect::<MinimapAutoSelectPolicy> {
+                    label: "Auto select map on entry",
+                    on_select: move |minimap_auto_select| {
+                        save_settings(SettingsData {
+                            minimap_auto_select,
+                            ..settings_view.peek().clone()
+                        });
+                    },
+                    selected: settings_view().minimap_auto_select,
+                }
                 SettingsCheckbox {
                     label: "Stop actions on fail or map changed",
                     on_value: move |stop_on_fail_or_change_map| {
@@ -674,6 +836,233 @@ fn SectionOthers(
     }
 }
 
+#[component]
+fn SectionBundle(settings: Signal<Option<SettingsData>>) -> Element {
+    let export_element_id = use_memo(|| Alphanumeric.sample_string(&mut rand::rng(), 8));
+    let export = use_callback(move |_| async move {
+        let js = format!(
+            r#"
+            const element = document.getElementById("{}");
+            if (element === null) {{
+                return;
+            }}
+            const json = await dioxus.recv();
+
+            element.setAttribute("href", "data:application/json;charset=utf-8," + encodeURIComponent(json));
+            element.setAttribute("download", "bundle.json");
+            element.click();
+            "#,
+            export_element_id(),
+        );
+        let eval = document::eval(js.as_str());
+        let Some(bundle) = export_bundle().await else {
+            return;
+        };
+        let Ok(json) = serde_json::to_string_pretty(&bundle) else {
+            return;
+        };
+        let _ = eval.send(json);
+    });
+
+    let import_element_id = use_memo(|| Alphanumeric.sample_string(&mut rand::rng(), 8));
+    let import = use_callback(move |_| {
+        let js = format!(
+            r#"
+            const element = document.getElementById("{}");
+            if (element === null) {{
+                return;
+            }}
+            element.click();
+            "#,
+            import_element_id()
+        );
+        document::eval(js.as_str());
+    });
+    let import_bundle_file = use_callback(move |file| async move {
+        let Ok(file) = File::open(file) else {
+            return;
+        };
+        let reader = BufReader::new(file);
+        let Ok(bundle) = serde_json::from_reader::<_, Bundle>(reader) else {
+            return;
+        };
+        if import_bundle(bundle).await {
+            settings.set(Some(query_settings().await));
+        }
+    });
+
+    rsx! {
+        Section { name: "Backup",
+            div { class: "grid grid-cols-2 gap-3",
+                div {
+                    a { id: export_element_id(), class: "w-0 h-0 invisible" }
+                    Button {
+                        class: "w-full",
+                        text: "Export all",
+                        kind: ButtonKind::Primary,
+                        on_click: move |_| async move {
+                            export(()).await;
+                        },
+                    }
+                }
+                div {
+                    input {
+                        id: import_element_id(),
+                        class: "w-0 h-0 invisible",
+                        r#type: "file",
+                        accept: ".json",
+                        name: "Bundle JSON",
+                        onchange: move |e| async move {
+                            if let Some(file) = e
+                                .data
+                                .files()
+                                .and_then(|engine| engine.files().into_iter().next())
+                            {
+                                import_bundle_file(file).await;
+                            }
+                        },
+                    }
+                    Button {
+                        class: "w-full",
+                        text: "Import all",
+                        kind: ButtonKind::Primary,
+                        on_click: move |_| {
+                            import(());
+                        },
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn SectionRecovery() -> Element {
+    let mut rows = use_resource(async || query_quarantined_rows().await);
+    let export_element_id = use_memo(|| Alphanumeric.sample_string(&mut rand::rng(), 8));
+    let export = use_callback(move |json: String| {
+        let js = format!(
+            r#"
+            const element = document.getElementById("{}");
+            if (element === null) {{
+                return;
+            }}
+            const json = await dioxus.recv();
+
+            element.setAttribute("href", "data:application/json;charset=utf-8," + encodeURIComponent(json));
+            element.setAttribute("download", "recovered.json");
+            element.click();
+            "#,
+            export_element_id(),
+        );
+        let eval = document::eval(js.as_str());
+        let _ = eval.send(json);
+    });
+
+    use_future(move || async move {
+        let mut rx = database_event_receiver();
+        loop {
+            let event = match rx.recv().await {
+                Ok(value) => value,
+                Err(RecvError::Closed) => break,
+                Err(RecvError::Lagged(_)) => continue,
+            };
+            if matches!(
+                event,
+                DatabaseEvent::RowQuarantined(_) | DatabaseEvent::ProfileSwitched(_)
+            ) {
+                rows.restart();
+            }
+        }
+    });
+
+    rsx! {
+        Section { name: "Recovery",
+            a { id: export_element_id(), class: "w-0 h-0 invisible" }
+            div { class: "flex flex-col gap-2",
+                if rows().unwrap_or_default().is_empty() {
+                    div { class: "paragraph-xs text-gray-400", "No data failed to load" }
+                }
+                for (id , title , error , data) in rows()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|row| {
+                        let name = row.name.unwrap_or(format!("#{}", row.data_id));
+                        (row.id, format!("{name} ({})", row.table), row.error, row.data)
+                    })
+                {
+                    div { class: "flex gap-3 items-center",
+                        div { class: "flex-grow flex flex-col paragraph-xs overflow-hidden",
+                            div { class: "text-ellipsis overflow-hidden whitespace-nowrap",
+                                {title}
+                            }
+                            div { class: "text-gray-400 text-ellipsis overflow-hidden whitespace-nowrap",
+                                {error}
+                            }
+                        }
+                        Button {
+                            class: "w-20",
+                            text: "Export",
+                            kind: ButtonKind::Primary,
+                            on_click: move |_| {
+                                export(data.clone());
+                            },
+                        }
+                        Button {
+                            class: "w-20",
+                            text: "Discard",
+                            kind: ButtonKind::Danger,
+                            on_click: move |_| async move {
+                                if delete_quarantined_row(id).await {
+                                    rows.restart();
+                                }
+                            },
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn SectionModels() -> Element {
+    let models = use_resource(async || query_models().await);
+
+    rsx! {
+        Section { name: "Models",
+            div { class: "flex flex-col gap-2",
+                for (kind , source , version , error) in models()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|model| {
+                        let source = model
+                            .path
+                            .map(|path| path.display().to_string())
+                            .unwrap_or("Embedded".to_string());
+                        (model.kind, source, model.version, model.error)
+                    })
+                {
+                    div { class: "flex flex-col paragraph-xs overflow-hidden",
+                        div { class: "flex gap-3",
+                            div { class: "w-32", {kind.to_string()} }
+                            div { class: "flex-grow text-ellipsis overflow-hidden whitespace-nowrap",
+                                {source}
+                            }
+                            div { class: "text-gray-400", {version} }
+                        }
+                        if let Some(error) = error {
+                            div { class: "text-red-500 text-ellipsis overflow-hidden whitespace-nowrap",
+                                {format!("Override rejected: {error}")}
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
 #[component]
 fn SettingsSelect<T: 'static + Clone + PartialEq + Display>(
     label: &'static str,